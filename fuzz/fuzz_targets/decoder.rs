@@ -0,0 +1,22 @@
+//! Feeds arbitrary bytes to the [`Decoder`], decoding every frame to its message type.
+
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use restate_sdk_shared_core::dump::dump_stream;
+use restate_sdk_shared_core::test_utils::{Decoder, SUPPORTED_VERSIONS};
+use std::io;
+
+fuzz_target!(|data: &[u8]| {
+    for version in SUPPORTED_VERSIONS {
+        // Push the input in two chunks, to exercise frames split across buffers
+        let (first, second) = data.split_at(data.len() / 2);
+        let mut decoder = Decoder::new(version);
+        decoder.push(Bytes::copy_from_slice(first));
+        decoder.push(Bytes::copy_from_slice(second));
+        while let Ok(Some(_)) = decoder.consume_next() {}
+
+        let _ = dump_stream(version, Bytes::copy_from_slice(data), io::sink());
+    }
+});
@@ -0,0 +1,48 @@
+//! Feeds arbitrary input chunks to a [`CoreVM`], then runs a handler reading the input and writing the output.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use restate_sdk_shared_core::test_utils::SUPPORTED_VERSIONS;
+use restate_sdk_shared_core::{
+    CoreVM, EntryOptions, NonEmptyValue, TakeOutputResult, VMOptions, VM,
+};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    version: u8,
+    chunks: Vec<Vec<u8>>,
+    close_input: bool,
+}
+
+fuzz_target!(|input: Input| {
+    let version = SUPPORTED_VERSIONS[input.version as usize % SUPPORTED_VERSIONS.len()];
+    let mut vm = CoreVM::new(
+        vec![("content-type".to_owned(), version.to_string())],
+        VMOptions::default(),
+    )
+    .unwrap();
+
+    for chunk in input.chunks {
+        vm.notify_input(Bytes::from(chunk));
+    }
+    if input.close_input {
+        vm.notify_input_closed();
+    }
+
+    if let Ok(true) = vm.is_ready_to_execute() {
+        if let Ok(input) = vm.sys_input() {
+            let _ =
+                vm.sys_write_output(NonEmptyValue::Success(input.input), EntryOptions::default());
+            let _ = vm.sys_end();
+        }
+    }
+
+    while let TakeOutputResult::Buffer(b) = vm.take_output() {
+        if b.is_empty() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,323 @@
+//! Starts a [`CoreVM`] with an arbitrary start message, then interleaves arbitrary input messages and syscalls.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use restate_sdk_shared_core::test_utils::messages::{
+    completion_message, start_message, CompletionMessage, Empty, EntryAckMessage, Failure,
+    InputEntryMessage, StartMessage,
+};
+use restate_sdk_shared_core::test_utils::{Encoder, SUPPORTED_VERSIONS};
+use restate_sdk_shared_core::{
+    AsyncResultHandle, CancelInvocationTarget, CoreVM, EntryOptions, Error, GetInvocationIdTarget,
+    Jitter, NonEmptyValue, RetryPolicy, RunExitResult, SendHandle, StateKeysQuery, Target,
+    TerminalFailure, VMOptions, VM,
+};
+use std::time::Duration;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    version: u8,
+    known_entries: u32,
+    partial_state: bool,
+    state: Vec<(String, Vec<u8>)>,
+    retry_count_since_last_stored_entry: u32,
+    duration_since_last_stored_entry: u64,
+    input: Vec<u8>,
+    ops: Vec<Op>,
+}
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    // --- Input
+    RawInput(Vec<u8>),
+    Completion(u32, Completion),
+    Ack(u32),
+    CloseInput,
+    // --- Syscalls
+    Input,
+    Random,
+    StateGet(String),
+    StateGetKeys,
+    StateGetMany(Vec<String>),
+    StateGetKeysWithPrefix(String, Option<String>, Option<u8>),
+    StateSet(String, Vec<u8>),
+    StateClear(String),
+    StateClearAll,
+    Sleep(Duration),
+    Call(String, String, Option<String>, Vec<u8>),
+    Send(String, String, Option<Duration>, Vec<u8>),
+    Awakeable,
+    CompleteAwakeable(String, Value),
+    GetPromise(String),
+    PeekPromise(String),
+    CompletePromise(String, Value),
+    RunEnter(String),
+    RunExit(RunResult, Policy),
+    GetCallInvocationId(u32),
+    CancelInvocation(u32),
+    WriteOutput(Value),
+    End,
+    NotifyAwaitPoint(u32),
+    TakeAsyncResult(u32),
+    NotifyError(u16, String, Option<u64>),
+    TakeOutput,
+}
+
+#[derive(Debug, Arbitrary)]
+enum Completion {
+    Empty,
+    Value(Vec<u8>),
+    Failure(u32, String),
+}
+
+#[derive(Debug, Arbitrary)]
+enum Value {
+    Success(Vec<u8>),
+    Failure(u16, String),
+}
+
+#[derive(Debug, Arbitrary)]
+enum RunResult {
+    Success(Vec<u8>),
+    TerminalFailure(u16, String),
+    RetryableFailure(u64, u16, String),
+}
+
+#[derive(Debug, Arbitrary)]
+enum Policy {
+    Infinite,
+    None,
+    FixedDelay(u64, Option<u32>, Option<u64>),
+    Exponential(u64, f32, Option<u32>, Option<u64>, Option<u64>, u8),
+    TerminalCodes(u16, u16),
+}
+
+impl From<Value> for NonEmptyValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Success(b) => NonEmptyValue::Success(b.into()),
+            Value::Failure(code, message) => {
+                NonEmptyValue::Failure(TerminalFailure { code, message })
+            }
+        }
+    }
+}
+
+impl From<Policy> for RetryPolicy {
+    fn from(policy: Policy) -> Self {
+        match policy {
+            Policy::Infinite => RetryPolicy::Infinite,
+            Policy::None => RetryPolicy::None,
+            Policy::FixedDelay(interval, max_attempts, max_duration) => RetryPolicy::fixed_delay(
+                Duration::from_millis(interval),
+                max_attempts,
+                max_duration.map(Duration::from_millis),
+            ),
+            Policy::Exponential(
+                initial_interval,
+                factor,
+                max_attempts,
+                max_interval,
+                max_duration,
+                jitter,
+            ) => RetryPolicy::exponential(
+                Duration::from_millis(initial_interval),
+                factor,
+                max_attempts,
+                max_interval.map(Duration::from_millis),
+                max_duration.map(Duration::from_millis),
+            )
+            .with_jitter(match jitter % 4 {
+                0 => Jitter::None,
+                1 => Jitter::Full,
+                2 => Jitter::Equal,
+                _ => Jitter::Decorrelated,
+            }),
+            Policy::TerminalCodes(start, end) => {
+                RetryPolicy::default().with_terminal_codes(start..=end)
+            }
+        }
+    }
+}
+
+fn target(service: String, handler: String, key: Option<String>) -> Target {
+    Target {
+        service,
+        handler,
+        key,
+        idempotency_key: None,
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let version = SUPPORTED_VERSIONS[input.version as usize % SUPPORTED_VERSIONS.len()];
+    let encoder = Encoder::new(version);
+    let mut vm = CoreVM::new(
+        vec![("content-type".to_owned(), version.to_string())],
+        VMOptions::default(),
+    )
+    .unwrap();
+
+    vm.notify_input(
+        encoder.encode(&StartMessage {
+            id: Bytes::from_static(b"123"),
+            debug_id: "123".to_owned(),
+            known_entries: input.known_entries,
+            state_map: input
+                .state
+                .into_iter()
+                .map(|(key, value)| start_message::StateEntry {
+                    key: key.into(),
+                    value: value.into(),
+                })
+                .collect(),
+            partial_state: input.partial_state,
+            key: "".to_owned(),
+            retry_count_since_last_stored_entry: input.retry_count_since_last_stored_entry,
+            duration_since_last_stored_entry: input.duration_since_last_stored_entry,
+        }),
+    );
+    vm.notify_input(encoder.encode(&InputEntryMessage {
+        value: input.input.into(),
+        ..InputEntryMessage::default()
+    }));
+
+    for op in input.ops {
+        match op {
+            Op::RawInput(b) => vm.notify_input(b.into()),
+            Op::Completion(entry_index, completion) => {
+                vm.notify_input(encoder.encode(&CompletionMessage {
+                    entry_index,
+                    result: Some(match completion {
+                        Completion::Empty => completion_message::Result::Empty(Empty {}),
+                        Completion::Value(b) => completion_message::Result::Value(b.into()),
+                        Completion::Failure(code, message) => {
+                            completion_message::Result::Failure(Failure { code, message })
+                        }
+                    }),
+                }))
+            }
+            Op::Ack(entry_index) => {
+                vm.notify_input(encoder.encode(&EntryAckMessage { entry_index }))
+            }
+            Op::CloseInput => vm.notify_input_closed(),
+            Op::Input => {
+                let _ = vm.sys_input();
+            }
+            Op::Random => {
+                let _ = vm.sys_random().map(|rng| rng.next_u64());
+            }
+            Op::StateGet(key) => {
+                let _ = vm.sys_state_get(key, EntryOptions::default());
+            }
+            Op::StateGetKeys => {
+                let _ = vm.sys_state_get_keys(EntryOptions::default());
+            }
+            Op::StateGetMany(keys) => {
+                let _ = vm.sys_state_get_many(keys, EntryOptions::default());
+            }
+            Op::StateGetKeysWithPrefix(prefix, start_after, limit) => {
+                let _ = vm.sys_state_get_keys_with_prefix(
+                    StateKeysQuery {
+                        prefix,
+                        start_after,
+                        limit: limit.map(usize::from),
+                    },
+                    EntryOptions::default(),
+                );
+            }
+            Op::StateSet(key, value) => {
+                let _ = vm.sys_state_set(key, value.into(), EntryOptions::default());
+            }
+            Op::StateClear(key) => {
+                let _ = vm.sys_state_clear(key, EntryOptions::default());
+            }
+            Op::StateClearAll => {
+                let _ = vm.sys_state_clear_all(EntryOptions::default());
+            }
+            Op::Sleep(duration) => {
+                let _ = vm.sys_sleep(duration, EntryOptions::default());
+            }
+            Op::Call(service, handler, key, input) => {
+                let _ = vm.sys_call(
+                    target(service, handler, key),
+                    input.into(),
+                    EntryOptions::default(),
+                );
+            }
+            Op::Send(service, handler, delay, input) => {
+                let _ = vm.sys_send(
+                    target(service, handler, None),
+                    input.into(),
+                    delay,
+                    EntryOptions::default(),
+                );
+            }
+            Op::Awakeable => {
+                let _ = vm.sys_awakeable(EntryOptions::default());
+            }
+            Op::CompleteAwakeable(id, value) => {
+                let _ = vm.sys_complete_awakeable(id, value.into(), EntryOptions::default());
+            }
+            Op::GetPromise(key) => {
+                let _ = vm.sys_get_promise(key, EntryOptions::default());
+            }
+            Op::PeekPromise(key) => {
+                let _ = vm.sys_peek_promise(key, EntryOptions::default());
+            }
+            Op::CompletePromise(key, value) => {
+                let _ = vm.sys_complete_promise(key, value.into(), EntryOptions::default());
+            }
+            Op::RunEnter(name) => {
+                let _ = vm.sys_run_enter(name);
+            }
+            Op::RunExit(result, policy) => {
+                let result = match result {
+                    RunResult::Success(b) => RunExitResult::Success(b.into()),
+                    RunResult::TerminalFailure(code, message) => {
+                        RunExitResult::TerminalFailure(TerminalFailure { code, message })
+                    }
+                    RunResult::RetryableFailure(attempt_duration, code, message) => {
+                        RunExitResult::RetryableFailure {
+                            attempt_duration: Duration::from_millis(attempt_duration),
+                            error: Error::new(code, message),
+                        }
+                    }
+                };
+                let _ = vm.sys_run_exit(result, policy.into());
+            }
+            Op::GetCallInvocationId(handle) => {
+                let _ = vm.sys_get_call_invocation_id(
+                    GetInvocationIdTarget::CallEntry(AsyncResultHandle::from(handle)),
+                    EntryOptions::default(),
+                );
+            }
+            Op::CancelInvocation(handle) => {
+                let _ = vm.sys_cancel_invocation(
+                    CancelInvocationTarget::SendEntry(SendHandle::from(handle)),
+                    EntryOptions::default(),
+                );
+            }
+            Op::WriteOutput(value) => {
+                let _ = vm.sys_write_output(value.into(), EntryOptions::default());
+            }
+            Op::End => {
+                let _ = vm.sys_end();
+            }
+            Op::NotifyAwaitPoint(handle) => vm.notify_await_point(handle.into()),
+            Op::TakeAsyncResult(handle) => {
+                let _ = vm.take_async_result(handle.into());
+            }
+            Op::NotifyError(code, message, next_retry_delay) => vm.notify_error(
+                Error::new(code, message),
+                next_retry_delay.map(Duration::from_millis),
+            ),
+            Op::TakeOutput => {
+                let _ = vm.take_output();
+            }
+        }
+    }
+});
@@ -0,0 +1,88 @@
+//! Prints a human-readable dump of a service protocol stream.
+//!
+//! Usage: `restate-protocol-dump [--protocol-version <content-type>] [--json] [FILE]`
+//!
+//! Reads from stdin when no file is provided. The input can be either a raw service protocol stream,
+//! or a recording written by [`InputRecorder`](restate_sdk_shared_core::recording::InputRecorder),
+//! in which case the protocol version is taken from the recorded `content-type` header.
+//!
+//! With the `serde` feature, `--json` prints the frames as JSON lines.
+
+use bytes::Bytes;
+use restate_sdk_shared_core::dump::dump_stream;
+use restate_sdk_shared_core::recording::{Recording, RecordingError};
+use restate_sdk_shared_core::Version;
+use std::io::Read;
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+const USAGE: &str =
+    "Usage: restate-protocol-dump [--protocol-version <content-type>] [--json] [FILE]";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut version = None;
+    let mut json = false;
+    let mut file = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--protocol-version" => {
+                version = Some(args.next().ok_or(USAGE)?.parse::<Version>()?);
+            }
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let input = match file {
+        Some(path) => fs::read(path)?,
+        None => {
+            let mut buf = vec![];
+            io::stdin().read_to_end(&mut buf)?;
+            buf
+        }
+    };
+
+    let (recorded_version, stream) = match Recording::decode(Bytes::from(input.clone())) {
+        Ok(recording) => (
+            recording
+                .request_headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+                .map(|(_, v)| v.parse::<Version>())
+                .transpose()?,
+            recording.input,
+        ),
+        Err(RecordingError::BadMagic) => (None, Bytes::from(input)),
+        Err(e) => return Err(e.into()),
+    };
+
+    let version = version
+        .or(recorded_version)
+        .unwrap_or(Version::maximum_supported_version());
+    if json {
+        #[cfg(feature = "serde")]
+        restate_sdk_shared_core::dump::stream_to_json_lines(version, stream, io::stdout().lock())?;
+        #[cfg(not(feature = "serde"))]
+        return Err("--json requires the serde feature".into());
+    } else {
+        dump_stream(version, stream, io::stdout().lock())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,447 @@
+//! Human-readable dump of a service protocol stream, useful to debug captures of the communication with the runtime.
+//!
+//! With the `serde` feature, streams can also be converted back and forth to JSON lines,
+//! see `stream_to_json_lines` and `json_lines_to_stream`.
+//!
+//! See also the `restate-protocol-dump` binary.
+
+use crate::service_protocol::messages::*;
+use crate::service_protocol::{Decoder, DecodingError, MessageType, RawMessage};
+use crate::Version;
+#[cfg(feature = "serde")]
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use paste::paste;
+#[cfg(feature = "serde")]
+use prost::Message;
+use std::fmt::Debug;
+use std::io;
+use std::io::Write;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DumpError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Decoding(#[from] DecodingError),
+    #[error("stream ended in the middle of a frame, {0} bytes left")]
+    TruncatedFrame(usize),
+    #[error("unsupported service protocol version '{0}', supported versions are [{min} to {max}]", min = Version::minimum_supported_version(), max = Version::maximum_supported_version())]
+    UnsupportedVersion(Version),
+    #[cfg(feature = "serde")]
+    #[error("bad JSON at line {line}: {error}")]
+    Json {
+        line: usize,
+        #[source]
+        error: serde_json::Error,
+    },
+    #[cfg(feature = "serde")]
+    #[error("unknown message type '{ty}' at line {line}")]
+    UnknownMessageType { line: usize, ty: String },
+}
+
+/// Writes every frame of the given service protocol stream to `out`, one per block:
+///
+/// ```text
+/// #0 Start completed=- requires_ack=- length=37
+/// StartMessage {
+///     ...
+/// }
+/// ```
+///
+/// Frames whose payload can't be decoded are reported inline, and the dump continues with the next frame.
+pub fn dump_stream(version: Version, stream: Bytes, mut out: impl Write) -> Result<(), DumpError> {
+    for_each_frame(version, stream, |i, msg| {
+        dump_message(i, msg, &mut out)?;
+        Ok(())
+    })
+}
+
+fn for_each_frame(
+    version: Version,
+    stream: Bytes,
+    mut f: impl FnMut(usize, RawMessage) -> Result<(), DumpError>,
+) -> Result<(), DumpError> {
+    if version < Version::minimum_supported_version()
+        || version > Version::maximum_supported_version()
+    {
+        return Err(DumpError::UnsupportedVersion(version));
+    }
+
+    let stream_len = stream.len();
+    let mut consumed = 0;
+    let mut decoder = Decoder::new(version);
+    decoder.push(stream);
+
+    let mut i = 0;
+    while let Some(msg) = decoder.consume_next()? {
+        consumed += 8 + msg.header().frame_length() as usize;
+        f(i, msg)?;
+        i += 1;
+    }
+
+    if consumed != stream_len {
+        return Err(DumpError::TruncatedFrame(stream_len - consumed));
+    }
+    Ok(())
+}
+
+fn dump_message(index: usize, msg: RawMessage, mut out: impl Write) -> io::Result<()> {
+    let header = msg.header();
+    let flag = |f: Option<bool>| f.map(|b| b.to_string()).unwrap_or_else(|| "-".to_owned());
+    writeln!(
+        out,
+        "#{index} {:?} completed={} requires_ack={} length={}",
+        header.message_type(),
+        flag(header.completed()),
+        flag(header.requires_ack()),
+        header.frame_length()
+    )?;
+
+    match decode_message(msg) {
+        Ok(decoded) => writeln!(out, "{decoded:#?}"),
+        Err(e) => writeln!(out, "<cannot decode payload: {e}>"),
+    }
+}
+
+// Generates the conversions from/to RawMessage for every known message type
+macro_rules! message_conversions {
+    ($($variant:ident),* $(,)?) => {
+        paste! {
+            fn decode_message(msg: RawMessage) -> Result<Box<dyn Debug>, DecodingError> {
+                match msg.ty() {
+                    $(MessageType::$variant => Ok(Box::new(msg.decode_to::<[<$variant Message>]>()?)),)*
+                    // We don't know the schema of custom entries
+                    MessageType::CustomEntry(_) => Ok(Box::new(msg.payload().clone())),
+                }
+            }
+
+            #[cfg(feature = "serde")]
+            fn message_to_json(msg: RawMessage) -> Result<serde_json::Value, DumpError> {
+                Ok(match msg.ty() {
+                    $(MessageType::$variant => serde_json::to_value(msg.decode_to::<[<$variant Message>]>()?)
+                        .expect("Protocol messages should always be serializable to JSON"),)*
+                    MessageType::CustomEntry(_) => serde_json::Value::String(STANDARD.encode(msg.payload())),
+                })
+            }
+
+            #[cfg(feature = "serde")]
+            fn message_from_json(
+                line: usize,
+                frame: &JsonFrame,
+            ) -> Result<(MessageType, Bytes), DumpError> {
+                let json_err = |error| DumpError::Json { line, error };
+                match (frame.ty.as_str(), frame.code) {
+                    $((stringify!($variant), None) => Ok((
+                        MessageType::$variant,
+                        serde_json::from_value::<[<$variant Message>]>(frame.message.clone())
+                            .map_err(json_err)?
+                            .encode_to_vec()
+                            .into(),
+                    )),)*
+                    ("CustomEntry", Some(code)) => Ok((
+                        MessageType::CustomEntry(code),
+                        serde_json::from_value::<String>(frame.message.clone())
+                            .map_err(json_err)
+                            .and_then(|s| {
+                                STANDARD
+                                    .decode(s)
+                                    .map_err(|e| json_err(serde::de::Error::custom(e)))
+                            })?
+                            .into(),
+                    )),
+                    _ => Err(DumpError::UnknownMessageType {
+                        line,
+                        ty: frame.ty.clone(),
+                    }),
+                }
+            }
+        }
+    };
+}
+
+message_conversions!(
+    Start,
+    Completion,
+    Suspension,
+    Error,
+    End,
+    EntryAck,
+    InputEntry,
+    OutputEntry,
+    GetStateEntry,
+    SetStateEntry,
+    ClearStateEntry,
+    GetStateKeysEntry,
+    ClearAllStateEntry,
+    GetPromiseEntry,
+    PeekPromiseEntry,
+    CompletePromiseEntry,
+    SleepEntry,
+    CallEntry,
+    OneWayCallEntry,
+    AwakeableEntry,
+    CompleteAwakeableEntry,
+    RunEntry,
+    CancelInvocationEntry,
+    GetCallInvocationIdEntry,
+    CombinatorEntry,
+);
+
+// --- JSON lines
+
+/// A frame in the JSON lines format. Custom entries have type `CustomEntry`, their `code`, and a base64 encoded `message`.
+#[cfg(feature = "serde")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct JsonFrame {
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    code: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    requires_ack: Option<bool>,
+    message: serde_json::Value,
+}
+
+/// Writes every frame of the given service protocol stream to `out` as a JSON object, one per line:
+///
+/// ```text
+/// {"type":"GetStateEntry","completed":false,"requires_ack":false,"message":{"key":"bXkta2V5","name":"","result":null}}
+/// ```
+///
+/// `bytes` fields are base64 encoded. The output can be converted back with [`json_lines_to_stream`].
+#[cfg(feature = "serde")]
+pub fn stream_to_json_lines(
+    version: Version,
+    stream: Bytes,
+    mut out: impl Write,
+) -> Result<(), DumpError> {
+    for_each_frame(version, stream, |_, msg| {
+        let header = msg.header();
+        let frame = JsonFrame {
+            ty: match header.message_type() {
+                MessageType::CustomEntry(_) => "CustomEntry".to_owned(),
+                ty => format!("{ty:?}"),
+            },
+            code: match header.message_type() {
+                MessageType::CustomEntry(code) => Some(code),
+                _ => None,
+            },
+            completed: header.completed(),
+            requires_ack: header.requires_ack(),
+            message: message_to_json(msg)?,
+        };
+        serde_json::to_writer(&mut out, &frame).map_err(io::Error::from)?;
+        writeln!(out)?;
+        Ok(())
+    })
+}
+
+/// Converts JSON lines written by [`stream_to_json_lines`] back to a service protocol stream. Empty lines are skipped.
+///
+/// Missing message fields take their protobuf default value, and missing flags default to `false`.
+#[cfg(feature = "serde")]
+pub fn json_lines_to_stream(input: impl io::BufRead) -> Result<Bytes, DumpError> {
+    use crate::service_protocol::MessageHeader;
+    use bytes::{BufMut, BytesMut};
+
+    let mut stream = BytesMut::new();
+    for (i, line) in input.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let frame: JsonFrame = serde_json::from_str(&line).map_err(|error| DumpError::Json {
+            line: line_number,
+            error,
+        })?;
+        let (ty, payload) = message_from_json(line_number, &frame)?;
+
+        let header = if ty.is_entry() {
+            MessageHeader::new_ackable_entry_header(
+                ty,
+                ty.has_completed_flag()
+                    .then(|| frame.completed.unwrap_or_default()),
+                Some(frame.requires_ack.unwrap_or_default()),
+                payload.len() as u32,
+            )
+        } else {
+            MessageHeader::new(ty, payload.len() as u32)
+        };
+        stream.put_u64(header.into());
+        stream.put_slice(&payload);
+    }
+
+    Ok(stream.freeze())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::service_protocol::Encoder;
+
+    fn dump_to_string(stream: Bytes) -> Result<String, DumpError> {
+        let mut out = vec![];
+        dump_stream(Version::maximum_supported_version(), stream, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn dump() {
+        let encoder = Encoder::new(Version::maximum_supported_version());
+        let mut stream = vec![];
+        stream.extend_from_slice(&encoder.encode(&StartMessage {
+            id: Bytes::from_static(b"123"),
+            debug_id: "123".to_string(),
+            known_entries: 1,
+            ..Default::default()
+        }));
+        stream.extend_from_slice(&encoder.encode(&GetStateEntryMessage {
+            key: Bytes::from_static(b"my-key"),
+            ..Default::default()
+        }));
+        stream.extend_from_slice(&encoder.encode(&RunEntryMessage::default()));
+
+        let dump = dump_to_string(stream.into()).unwrap();
+
+        assert!(dump.contains("#0 Start completed=- requires_ack=- length=12"));
+        assert!(dump.contains("debug_id: \"123\""));
+        assert!(dump.contains("#1 GetStateEntry completed=false requires_ack=false length=8"));
+        assert!(dump.contains("GetStateEntryMessage {"));
+        assert!(dump.contains("#2 RunEntry completed=- requires_ack=true length=0"));
+    }
+
+    #[test]
+    fn dump_undecodable_payload() {
+        let mut stream = vec![];
+        // Header of an InputEntry with a 2 bytes payload, which is not valid protobuf
+        stream.extend_from_slice(&0x0400_0000_0000_0002u64.to_be_bytes());
+        stream.extend_from_slice(&[0xff, 0xff]);
+
+        let dump = dump_to_string(stream.into()).unwrap();
+
+        assert!(dump.contains("#0 InputEntry"));
+        assert!(dump.contains("<cannot decode payload:"));
+    }
+
+    #[test]
+    fn dump_truncated_frame() {
+        let encoder = Encoder::new(Version::maximum_supported_version());
+        let msg = encoder.encode(&EndMessage {});
+        let mut stream = msg.to_vec();
+        stream.extend_from_slice(&msg[..4]);
+
+        assert!(matches!(
+            dump_to_string(stream.into()),
+            Err(DumpError::TruncatedFrame(4))
+        ));
+    }
+
+    #[test]
+    fn dump_unsupported_version() {
+        assert!(matches!(
+            dump_stream(Version::V1, Bytes::new(), vec![]),
+            Err(DumpError::UnsupportedVersion(Version::V1))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_lines_round_trip() {
+        use crate::service_protocol::messages::get_state_keys_entry_message::StateKeys;
+
+        let encoder = Encoder::new(Version::maximum_supported_version());
+        let mut stream = vec![];
+        stream.extend_from_slice(&encoder.encode(&StartMessage {
+            id: Bytes::from_static(b"123"),
+            debug_id: "123".to_string(),
+            known_entries: 3,
+            ..Default::default()
+        }));
+        stream.extend_from_slice(&encoder.encode(&GetStateEntryMessage {
+            key: Bytes::from_static(b"my-key"),
+            result: Some(get_state_entry_message::Result::Value(Bytes::from_static(
+                b"my-value",
+            ))),
+            ..Default::default()
+        }));
+        stream.extend_from_slice(&encoder.encode(&GetStateKeysEntryMessage {
+            result: Some(get_state_keys_entry_message::Result::Value(StateKeys {
+                keys: vec![Bytes::from_static(b"a"), Bytes::from_static(b"b")],
+            })),
+            ..Default::default()
+        }));
+        stream.extend_from_slice(&encoder.encode(&RunEntryMessage::default()));
+        // Custom entry
+        stream.extend_from_slice(&0xFC03_0000_0000_0002u64.to_be_bytes());
+        stream.extend_from_slice(&[0xff, 0xff]);
+        let stream = Bytes::from(stream);
+
+        let mut json_lines = vec![];
+        stream_to_json_lines(
+            Version::maximum_supported_version(),
+            stream.clone(),
+            &mut json_lines,
+        )
+        .unwrap();
+        let json_lines = String::from_utf8(json_lines).unwrap();
+
+        let lines: Vec<serde_json::Value> = json_lines
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0]["type"], "Start");
+        assert_eq!(lines[0]["message"]["id"], "MTIz");
+        assert_eq!(lines[1]["type"], "GetStateEntry");
+        assert_eq!(lines[1]["completed"], true);
+        assert_eq!(lines[1]["message"]["key"], "bXkta2V5");
+        assert_eq!(lines[1]["message"]["result"]["Value"], "bXktdmFsdWU=");
+        assert_eq!(
+            lines[2]["message"]["result"]["Value"]["keys"],
+            serde_json::json!(["YQ==", "Yg=="])
+        );
+        assert_eq!(lines[3]["requires_ack"], true);
+        assert_eq!(lines[4]["type"], "CustomEntry");
+        assert_eq!(lines[4]["code"], 0xFC03);
+        assert_eq!(lines[4]["message"], "//8=");
+
+        assert_eq!(json_lines_to_stream(json_lines.as_bytes()).unwrap(), stream);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_lines_with_missing_fields() {
+        let stream = json_lines_to_stream(
+            "{\"type\":\"InputEntry\",\"message\":{\"value\":\"bXktZGF0YQ==\"}}\n\n".as_bytes(),
+        )
+        .unwrap();
+
+        let encoder = Encoder::new(Version::maximum_supported_version());
+        assert_eq!(
+            stream,
+            encoder.encode(&InputEntryMessage {
+                value: Bytes::from_static(b"my-data"),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_lines_errors() {
+        assert!(matches!(
+            json_lines_to_stream("{\"type\":\"Unknown\",\"message\":{}}".as_bytes()),
+            Err(DumpError::UnknownMessageType { line: 1, .. })
+        ));
+        assert!(matches!(
+            json_lines_to_stream("\n{\"type\":\"Start\",\"message\":{\"id\":\"%%\"}}".as_bytes()),
+            Err(DumpError::Json { line: 2, .. })
+        ));
+    }
+}
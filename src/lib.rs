@@ -1,16 +1,28 @@
+pub mod dump;
 mod headers;
+#[cfg(feature = "serde")]
+pub mod manifest;
+mod random;
+pub mod recording;
 #[cfg(feature = "request_identity")]
 mod request_identity;
 mod retries;
+#[cfg(feature = "serde")]
+pub mod router;
 mod service_protocol;
+pub mod snapshot;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 mod vm;
 
 use bytes::Bytes;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::time::Duration;
 
-pub use crate::retries::RetryPolicy;
+pub use crate::random::DeterministicRng;
+pub use crate::retries::{Jitter, RetryPolicy, TerminalErrorRule};
 pub use headers::HeaderMap;
 #[cfg(feature = "request_identity")]
 pub use request_identity::*;
@@ -21,6 +33,7 @@ pub use vm::CoreVM;
 pub mod error {
     pub use crate::vm::errors::codes;
     pub use crate::vm::errors::InvocationErrorCode;
+    pub use crate::vm::errors::{EntryFieldMismatch, EntryMismatchError};
 }
 
 use crate::vm::AsyncResultAccessTrackerInner;
@@ -36,6 +49,7 @@ pub struct ResponseHead {
     pub status_code: u16,
     pub headers: Vec<Header>,
     pub version: Version,
+    pub protocol_mode: ProtocolMode,
 }
 
 #[derive(Debug, Clone, Copy, thiserror::Error)]
@@ -111,6 +125,8 @@ pub enum SuspendedOrVMError {
 #[derive(Debug, Eq, PartialEq)]
 pub struct Input {
     pub invocation_id: String,
+    /// Seed derived from the invocation id. With the `sha2_random_seed` feature, it's the first 8 bytes,
+    /// in big endian order, of the SHA-256 of the invocation id, otherwise it's computed with [`DefaultHasher`](std::hash::DefaultHasher).
     pub random_seed: u64,
     pub key: String,
     pub headers: Vec<Header>,
@@ -155,7 +171,7 @@ impl From<SendHandle> for u32 {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Value {
     /// a void/None/undefined success
     Void,
@@ -163,6 +179,8 @@ pub enum Value {
     Failure(TerminalFailure),
     /// Only returned for get_state_keys
     StateKeys(Vec<String>),
+    /// Only returned for get_state_many. `None` means the key is empty.
+    StateValues(BTreeMap<String, Option<Bytes>>),
     /// Only returned for get_call_invocation_id
     InvocationId(String),
     CombinatorResult(Vec<AsyncResultHandle>),
@@ -175,7 +193,7 @@ pub struct TerminalFailure {
     pub message: String,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct EntryRetryInfo {
     /// Number of retries that happened so far for this entry.
     pub retry_count: u32,
@@ -199,6 +217,45 @@ pub enum RunExitResult {
     },
 }
 
+/// Outcome of [`VM::sys_run_exit`], describing the retry decision taken for retryable failures.
+#[derive(Debug, Clone)]
+pub enum RunExitOutcome {
+    /// The run result was recorded as is, await the handle to get it.
+    Recorded(AsyncResultHandle),
+    /// The retryable failure was recorded as terminal failure, because the retry policy bounds were reached.
+    GaveUp {
+        handle: AsyncResultHandle,
+        retry_info: EntryRetryInfo,
+    },
+    /// The retryable failure was recorded as terminal failure, because the retry policy classified it as terminal.
+    ClassifiedAsTerminal {
+        handle: AsyncResultHandle,
+        retry_info: EntryRetryInfo,
+    },
+    /// The retryable failure will be retried by the runtime after `next_retry_delay`,
+    /// or after the runtime default delay when `None`.
+    ///
+    /// The VM has already failed the current attempt with `error`, sending it to the runtime:
+    /// every following syscall fails, and the SDK should only drain the output.
+    Retry {
+        error: Error,
+        next_retry_delay: Option<Duration>,
+        retry_info: EntryRetryInfo,
+    },
+}
+
+impl RunExitOutcome {
+    /// Handle to await the recorded run result, `None` if the run will be retried.
+    pub fn handle(&self) -> Option<AsyncResultHandle> {
+        match self {
+            RunExitOutcome::Recorded(handle)
+            | RunExitOutcome::GaveUp { handle, .. }
+            | RunExitOutcome::ClassifiedAsTerminal { handle, .. } => Some(*handle),
+            RunExitOutcome::Retry { .. } => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum NonEmptyValue {
     Success(Bytes),
@@ -214,6 +271,50 @@ impl From<NonEmptyValue> for Value {
     }
 }
 
+/// Net state changes produced by the handler in the current invocation, see [`CoreVM::state_changes`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct StateChanges {
+    /// True if the handler cleared all the state. `values` contains only the changes after the last clear all.
+    pub cleared_all: bool,
+    /// Last value set for each key, `None` means the key was cleared.
+    pub values: BTreeMap<String, Option<Bytes>>,
+}
+
+/// Prefix and page of the state keys to return, see [`VM::sys_state_get_keys_with_prefix`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct StateKeysQuery {
+    /// Only keys starting with this prefix are returned.
+    pub prefix: String,
+    /// Only keys sorting after this one are returned, usually the last key of the previous page.
+    pub start_after: Option<String>,
+    /// Maximum number of keys to return. `None` means no limit.
+    pub limit: Option<usize>,
+}
+
+impl StateKeysQuery {
+    /// Filters the sorted `keys`.
+    pub(crate) fn apply(&self, keys: Vec<String>) -> Vec<String> {
+        keys.into_iter()
+            .filter(|k| k.starts_with(&self.prefix))
+            .filter(|k| !matches!(&self.start_after, Some(after) if k <= after))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// Options applied to the journal entry created by a syscall.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct EntryOptions {
+    /// User-visible name of the entry. It's recorded in the journal, checked during replay and reported in errors.
+    pub name: String,
+}
+
+impl EntryOptions {
+    pub fn named(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum GetInvocationIdTarget {
     CallEntry(AsyncResultHandle),
@@ -235,15 +336,51 @@ pub enum TakeOutputResult {
 
 pub type VMResult<T> = Result<T, Error>;
 
+/// How the request and response streams are exchanged with the runtime.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "SCREAMING_SNAKE_CASE"))]
+pub enum ProtocolMode {
+    /// Input and output are streamed concurrently, the VM can wait for completions coming from the runtime.
+    #[default]
+    BidiStream,
+    /// The whole request body is received before executing, and the response is sent after.
+    /// The VM starts executing only once the input is closed, and suspends at the first await point that cannot be resolved.
+    /// Syscalls and await points fail while the input is still open.
+    RequestResponse,
+}
+
+#[derive(Clone)]
 pub struct VMOptions {
     /// If true, false when two concurrent async results are awaited at the same time. If false, just log it.
     pub fail_on_wait_concurrent_async_result: bool,
+    /// Protocol mode used to exchange messages with the runtime.
+    pub protocol_mode: ProtocolMode,
+    /// Maximum size in bytes of a single message received from the runtime. `None` means unlimited.
+    pub max_message_size: Option<usize>,
+    /// Maximum number of bytes buffered while waiting for an incomplete message, including its header. `None` means unlimited.
+    pub max_buffered_bytes: Option<usize>,
+    /// Maximum number of entries the runtime can ask to replay. `None` means unlimited.
+    pub max_replay_entries: Option<u32>,
+    /// Maximum length in bytes of the state keys written by the handler. `None` means unlimited.
+    pub max_state_key_length: Option<usize>,
+    /// Maximum size in bytes of the state values written by the handler. `None` means unlimited.
+    pub max_state_value_size: Option<usize>,
+    /// Prefixes of the state keys reserved for the SDK, which the handler cannot write.
+    pub reserved_state_key_prefixes: Vec<String>,
 }
 
 impl Default for VMOptions {
     fn default() -> Self {
         Self {
             fail_on_wait_concurrent_async_result: true,
+            protocol_mode: ProtocolMode::default(),
+            max_message_size: None,
+            max_buffered_bytes: None,
+            max_replay_entries: None,
+            max_state_key_length: None,
+            max_state_value_size: None,
+            reserved_state_key_prefixes: vec![],
         }
     }
 }
@@ -285,58 +422,122 @@ pub trait VM: Sized {
 
     fn sys_input(&mut self) -> VMResult<Input>;
 
-    fn sys_state_get(&mut self, key: String) -> VMResult<AsyncResultHandle>;
+    /// Returns the [`DeterministicRng`] of this invocation, seeded with [`Input::random_seed`].
+    ///
+    /// Fails when invoked between a sys_run_enter and sys_run_exit.
+    fn sys_random(&mut self) -> VMResult<&mut DeterministicRng>;
 
-    fn sys_state_get_keys(&mut self) -> VMResult<AsyncResultHandle>;
+    fn sys_state_get(&mut self, key: String, options: EntryOptions) -> VMResult<AsyncResultHandle>;
 
-    fn sys_state_set(&mut self, key: String, value: Bytes) -> VMResult<()>;
+    fn sys_state_get_keys(&mut self, options: EntryOptions) -> VMResult<AsyncResultHandle>;
 
-    fn sys_state_clear(&mut self, key: String) -> VMResult<()>;
+    /// Like [`VM::sys_state_get_keys`], but the handle resolves only to the sorted keys matching the `query`.
+    /// A page with less keys than [`StateKeysQuery::limit`] is the last one.
+    ///
+    /// When all the keys are known, only the page is recorded in the journal.
+    /// Otherwise, the first query fetches and records all the keys, and the following queries are answered from them.
+    fn sys_state_get_keys_with_prefix(
+        &mut self,
+        query: StateKeysQuery,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle>;
 
-    fn sys_state_clear_all(&mut self) -> VMResult<()>;
+    /// Gets several keys at once, returning a handle resolving to [`Value::StateValues`],
+    /// or to the first [`Value::Failure`] if any of the gets fails.
+    ///
+    /// Keys known in the eager state are resolved right away, without recording them in the journal.
+    /// Only the other keys are recorded like with [`VM::sys_state_get`], and wait for the runtime.
+    /// All the recorded entries share the name in `options`.
+    ///
+    /// Fails without affecting the invocation if `keys` is empty.
+    fn sys_state_get_many(
+        &mut self,
+        keys: Vec<String>,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle>;
 
-    fn sys_sleep(&mut self, wake_up_time_since_unix_epoch: Duration)
-        -> VMResult<AsyncResultHandle>;
+    /// Fails without affecting the invocation if the write violates the state limits of [`VMOptions`].
+    /// Retrying won't help, so the SDK should fail the invocation with a [`TerminalFailure`].
+    fn sys_state_set(&mut self, key: String, value: Bytes, options: EntryOptions) -> VMResult<()>;
 
-    fn sys_call(&mut self, target: Target, input: Bytes) -> VMResult<AsyncResultHandle>;
+    /// Fails like [`VM::sys_state_set`] if the key violates the state limits of [`VMOptions`].
+    fn sys_state_clear(&mut self, key: String, options: EntryOptions) -> VMResult<()>;
+
+    fn sys_state_clear_all(&mut self, options: EntryOptions) -> VMResult<()>;
+
+    fn sys_sleep(
+        &mut self,
+        wake_up_time_since_unix_epoch: Duration,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle>;
+
+    fn sys_call(
+        &mut self,
+        target: Target,
+        input: Bytes,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle>;
 
     fn sys_send(
         &mut self,
         target: Target,
         input: Bytes,
         execution_time_since_unix_epoch: Option<Duration>,
+        options: EntryOptions,
     ) -> VMResult<SendHandle>;
 
-    fn sys_awakeable(&mut self) -> VMResult<(String, AsyncResultHandle)>;
+    fn sys_awakeable(&mut self, options: EntryOptions) -> VMResult<(String, AsyncResultHandle)>;
 
-    fn sys_complete_awakeable(&mut self, id: String, value: NonEmptyValue) -> VMResult<()>;
+    fn sys_complete_awakeable(
+        &mut self,
+        id: String,
+        value: NonEmptyValue,
+        options: EntryOptions,
+    ) -> VMResult<()>;
 
-    fn sys_get_promise(&mut self, key: String) -> VMResult<AsyncResultHandle>;
+    fn sys_get_promise(
+        &mut self,
+        key: String,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle>;
 
-    fn sys_peek_promise(&mut self, key: String) -> VMResult<AsyncResultHandle>;
+    fn sys_peek_promise(
+        &mut self,
+        key: String,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle>;
 
     fn sys_complete_promise(
         &mut self,
         key: String,
         value: NonEmptyValue,
+        options: EntryOptions,
     ) -> VMResult<AsyncResultHandle>;
 
     fn sys_run_enter(&mut self, name: String) -> VMResult<RunEnterResult>;
 
+    /// Records the result of the run, returning the retry decision taken for retryable failures.
+    ///
+    /// When the outcome is [`RunExitOutcome::Retry`], the VM has already failed the current attempt.
     fn sys_run_exit(
         &mut self,
         value: RunExitResult,
         retry_policy: RetryPolicy,
-    ) -> VMResult<AsyncResultHandle>;
+    ) -> VMResult<RunExitOutcome>;
 
     fn sys_get_call_invocation_id(
         &mut self,
         call: GetInvocationIdTarget,
+        options: EntryOptions,
     ) -> VMResult<AsyncResultHandle>;
 
-    fn sys_cancel_invocation(&mut self, target: CancelInvocationTarget) -> VMResult<()>;
+    fn sys_cancel_invocation(
+        &mut self,
+        target: CancelInvocationTarget,
+        options: EntryOptions,
+    ) -> VMResult<()>;
 
-    fn sys_write_output(&mut self, value: NonEmptyValue) -> VMResult<()>;
+    fn sys_write_output(&mut self, value: NonEmptyValue, options: EntryOptions) -> VMResult<()>;
 
     fn sys_end(&mut self) -> VMResult<()>;
 
@@ -0,0 +1,599 @@
+use crate::headers::HeaderMap;
+use crate::vm::errors;
+use crate::{Error, Header, ProtocolMode, ResponseHead, Version};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+const ACCEPT: &str = "accept";
+const CONTENT_TYPE: &str = "content-type";
+
+const CONTENT_TYPE_V1: &str = "application/vnd.restate.endpointmanifest.v1+json";
+
+/// Version of the endpoint manifest returned by the discovery endpoint.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+pub enum DiscoveryVersion {
+    V1 = 1,
+}
+
+impl DiscoveryVersion {
+    pub const fn content_type(&self) -> &'static str {
+        match self {
+            DiscoveryVersion::V1 => CONTENT_TYPE_V1,
+        }
+    }
+
+    pub const fn minimum_supported_version() -> Self {
+        DiscoveryVersion::V1
+    }
+
+    pub const fn maximum_supported_version() -> Self {
+        DiscoveryVersion::V1
+    }
+}
+
+impl fmt::Display for DiscoveryVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.content_type())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unsupported discovery version '{0}'")]
+pub struct UnsupportedDiscoveryVersionError(String);
+
+impl FromStr for DiscoveryVersion {
+    type Err = UnsupportedDiscoveryVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            CONTENT_TYPE_V1 => Ok(DiscoveryVersion::V1),
+            s => Err(UnsupportedDiscoveryVersionError(s.to_owned())),
+        }
+    }
+}
+
+const SERVICE_NAME_PATTERN: &str = "^([a-zA-Z]|_[a-zA-Z0-9])[a-zA-Z0-9._-]*$";
+const HANDLER_NAME_PATTERN: &str = "^([a-zA-Z]|_[a-zA-Z0-9])[a-zA-Z0-9_]*$";
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ManifestError {
+    #[error("invalid service name '{0}', it must match the pattern {SERVICE_NAME_PATTERN}")]
+    InvalidServiceName(String),
+    #[error("invalid handler name '{service}/{handler}', it must match the pattern {HANDLER_NAME_PATTERN}")]
+    InvalidHandlerName { service: String, handler: String },
+    #[error("duplicate service '{0}'")]
+    DuplicateService(String),
+    #[error("duplicate handler '{service}/{handler}'")]
+    DuplicateHandler { service: String, handler: String },
+    #[error("handler '{service}/{handler}' of type {handler_ty:?} is not allowed in a service of type {service_ty:?}")]
+    BadHandlerType {
+        service: String,
+        handler: String,
+        service_ty: ServiceType,
+        handler_ty: HandlerType,
+    },
+    #[error("handler '{service}/{handler}' requires an input, but no input content type was set")]
+    RequiredInputWithoutContentType { service: String, handler: String },
+    #[error("cannot serialize the endpoint manifest: {0}")]
+    Serialize(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ServiceType {
+    VirtualObject,
+    Service,
+    Workflow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HandlerType {
+    Workflow,
+    Exclusive,
+    Shared,
+}
+
+impl ServiceType {
+    fn allows_handler_type(&self, handler_ty: HandlerType) -> bool {
+        match self {
+            ServiceType::Service => false,
+            ServiceType::VirtualObject => {
+                matches!(handler_ty, HandlerType::Exclusive | HandlerType::Shared)
+            }
+            ServiceType::Workflow => {
+                matches!(handler_ty, HandlerType::Workflow | HandlerType::Shared)
+            }
+        }
+    }
+}
+
+/// Description of an input payload. This will be used by Restate to validate incoming requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputPayload {
+    /// If true, a body MUST be sent with a content-type, even if the body length is zero.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    /// Content type of the input. It can accept wildcards, in the same format as the 'Accept' header.
+    /// When unset, no content-type/body is expected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<serde_json::Value>,
+}
+
+impl InputPayload {
+    /// No content-type/body is expected.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// A body with the given content type is expected.
+    pub fn required(content_type: impl Into<String>) -> Self {
+        Self {
+            required: Some(true),
+            content_type: Some(content_type.into()),
+            json_schema: None,
+        }
+    }
+
+    /// Either an empty body, or a body with the given content type.
+    pub fn optional(content_type: impl Into<String>) -> Self {
+        Self {
+            required: Some(false),
+            content_type: Some(content_type.into()),
+            json_schema: None,
+        }
+    }
+
+    pub fn with_json_schema(mut self, json_schema: serde_json::Value) -> Self {
+        self.json_schema = Some(json_schema);
+        self
+    }
+}
+
+/// Description of an output payload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputPayload {
+    /// Content type set on output. This will be used by Restate to set the output content type at the ingress.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// If true, the specified content-type is set even if the output is empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_content_type_if_empty: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<serde_json::Value>,
+}
+
+impl OutputPayload {
+    /// The handler returns no output.
+    pub fn empty() -> Self {
+        Self {
+            content_type: None,
+            set_content_type_if_empty: Some(false),
+            json_schema: None,
+        }
+    }
+
+    /// The handler output has the given content type, which is set only when the output is not empty.
+    pub fn with_content_type(content_type: impl Into<String>) -> Self {
+        Self {
+            content_type: Some(content_type.into()),
+            set_content_type_if_empty: Some(false),
+            json_schema: None,
+        }
+    }
+
+    pub fn with_json_schema(mut self, json_schema: serde_json::Value) -> Self {
+        self.json_schema = Some(json_schema);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handler {
+    pub name: String,
+    /// If unspecified, defaults to EXCLUSIVE for Virtual Object or WORKFLOW for Workflows. This should be unset for Services.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ty: Option<HandlerType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<InputPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputPayload>,
+}
+
+impl Handler {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ty: None,
+            input: None,
+            output: None,
+        }
+    }
+
+    pub fn with_ty(mut self, ty: HandlerType) -> Self {
+        self.ty = Some(ty);
+        self
+    }
+
+    pub fn with_input(mut self, input: InputPayload) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    pub fn with_output(mut self, output: OutputPayload) -> Self {
+        self.output = Some(output);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    pub name: String,
+    pub ty: ServiceType,
+    pub handlers: Vec<Handler>,
+}
+
+impl Service {
+    pub fn new(name: impl Into<String>, ty: ServiceType) -> Self {
+        Self {
+            name: name.into(),
+            ty,
+            handlers: vec![],
+        }
+    }
+
+    pub fn with_handler(mut self, handler: Handler) -> Self {
+        self.handlers.push(handler);
+        self
+    }
+
+    fn validate(&self) -> Result<(), ManifestError> {
+        if !is_valid_name(&self.name, |c| c == '.' || c == '-') {
+            return Err(ManifestError::InvalidServiceName(self.name.clone()));
+        }
+
+        for (i, handler) in self.handlers.iter().enumerate() {
+            if !is_valid_name(&handler.name, |_| false) {
+                return Err(ManifestError::InvalidHandlerName {
+                    service: self.name.clone(),
+                    handler: handler.name.clone(),
+                });
+            }
+            if self.handlers[..i].iter().any(|h| h.name == handler.name) {
+                return Err(ManifestError::DuplicateHandler {
+                    service: self.name.clone(),
+                    handler: handler.name.clone(),
+                });
+            }
+            if let Some(handler_ty) = handler.ty {
+                if !self.ty.allows_handler_type(handler_ty) {
+                    return Err(ManifestError::BadHandlerType {
+                        service: self.name.clone(),
+                        handler: handler.name.clone(),
+                        service_ty: self.ty,
+                        handler_ty,
+                    });
+                }
+            }
+            if let Some(InputPayload {
+                required: Some(true),
+                content_type: None,
+                ..
+            }) = &handler.input
+            {
+                return Err(ManifestError::RequiredInputWithoutContentType {
+                    service: self.name.clone(),
+                    handler: handler.name.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoint {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_mode: Option<ProtocolMode>,
+    pub min_protocol_version: i32,
+    pub max_protocol_version: i32,
+    pub services: Vec<Service>,
+}
+
+impl Endpoint {
+    /// Create a new endpoint manifest, advertising all the protocol versions supported by this crate.
+    pub fn new(protocol_mode: ProtocolMode) -> Self {
+        Self {
+            protocol_mode: Some(protocol_mode),
+            min_protocol_version: Version::minimum_supported_version() as i32,
+            max_protocol_version: Version::maximum_supported_version() as i32,
+            services: vec![],
+        }
+    }
+
+    pub fn with_service(mut self, service: Service) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Validate the manifest against the rules of the endpoint manifest schema.
+    pub fn validate(&self) -> Result<(), ManifestError> {
+        for (i, service) in self.services.iter().enumerate() {
+            service.validate()?;
+            if self.services[..i].iter().any(|s| s.name == service.name) {
+                return Err(ManifestError::DuplicateService(service.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate and serialize the manifest to JSON.
+    pub fn serialize(&self) -> Result<Bytes, ManifestError> {
+        self.validate()?;
+        serde_json::to_vec(self)
+            .map(Bytes::from)
+            .map_err(|e| ManifestError::Serialize(e.to_string()))
+    }
+
+    /// Handle a discovery request, negotiating the manifest version using the `accept` header.
+    ///
+    /// The returned [`ResponseHead::version`] is the maximum service protocol version advertised by this manifest.
+    pub fn handle_discovery_request(
+        &self,
+        request_headers: impl HeaderMap,
+    ) -> Result<(ResponseHead, Bytes), Error> {
+        let accept = request_headers.extract(ACCEPT).map_err(|e| {
+            Error::new(
+                errors::codes::BAD_REQUEST,
+                format!("cannot read '{ACCEPT}' header: {e:?}"),
+            )
+        })?;
+        let version = negotiate_discovery_version(accept)?;
+
+        let body = self.serialize()?;
+
+        Ok((
+            ResponseHead {
+                status_code: 200,
+                headers: vec![Header {
+                    key: Cow::Borrowed(CONTENT_TYPE),
+                    value: Cow::Borrowed(version.content_type()),
+                }],
+                version: Version::maximum_supported_version(),
+                protocol_mode: self.protocol_mode.unwrap_or_default(),
+            },
+            body,
+        ))
+    }
+}
+
+/// Picks the highest supported discovery version among the ones listed in the `accept` header.
+/// When the header is missing, or accepts any media type, the maximum supported version is used.
+fn negotiate_discovery_version(accept: Option<&str>) -> Result<DiscoveryVersion, Error> {
+    let Some(accept) = accept else {
+        return Ok(DiscoveryVersion::maximum_supported_version());
+    };
+
+    accept
+        .split(',')
+        .filter_map(|media_range| {
+            // Drop the media type parameters, e.g. the quality value
+            let media_type = media_range.split(';').next().unwrap_or_default().trim();
+            if media_type == "*/*" || media_type == "application/*" {
+                Some(DiscoveryVersion::maximum_supported_version())
+            } else {
+                media_type.parse::<DiscoveryVersion>().ok()
+            }
+        })
+        .max()
+        .ok_or_else(|| {
+            Error::new(
+                errors::codes::UNSUPPORTED_MEDIA_TYPE,
+                format!(
+                    "Unsupported discovery version, accept header '{accept}' doesn't contain any of the supported versions [{:?} to {:?}]",
+                    DiscoveryVersion::minimum_supported_version(),
+                    DiscoveryVersion::maximum_supported_version()
+                ),
+            )
+        })
+}
+
+/// Checks `^([a-zA-Z]|_[a-zA-Z0-9])[a-zA-Z0-9_]*$`, plus the additional characters allowed by `is_extra_char`.
+fn is_valid_name(name: &str, is_extra_char: impl Fn(char) -> bool) -> bool {
+    let mut chars = name.chars();
+    let rest = match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => chars,
+        Some('_') => match chars.next() {
+            Some(c) if c.is_ascii_alphanumeric() => chars,
+            _ => return false,
+        },
+        _ => return false,
+    };
+    rest.into_iter()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || is_extra_char(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let endpoint = Endpoint::new(ProtocolMode::BidiStream)
+            .with_service(
+                Service::new("Greeter", ServiceType::Service).with_handler(
+                    Handler::new("greet")
+                        .with_input(InputPayload::required("application/json"))
+                        .with_output(OutputPayload::with_content_type("application/json")),
+                ),
+            )
+            .with_service(
+                Service::new("Counter", ServiceType::VirtualObject)
+                    .with_handler(Handler::new("add").with_ty(HandlerType::Exclusive))
+                    .with_handler(
+                        Handler::new("get")
+                            .with_ty(HandlerType::Shared)
+                            .with_input(InputPayload::empty())
+                            .with_output(OutputPayload::empty()),
+                    ),
+            );
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&endpoint.serialize().unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "protocolMode": "BIDI_STREAM",
+                "minProtocolVersion": 2,
+                "maxProtocolVersion": 3,
+                "services": [
+                    {
+                        "name": "Greeter",
+                        "ty": "SERVICE",
+                        "handlers": [{
+                            "name": "greet",
+                            "input": {"required": true, "contentType": "application/json"},
+                            "output": {"contentType": "application/json", "setContentTypeIfEmpty": false}
+                        }]
+                    },
+                    {
+                        "name": "Counter",
+                        "ty": "VIRTUAL_OBJECT",
+                        "handlers": [
+                            {"name": "add", "ty": "EXCLUSIVE"},
+                            {"name": "get", "ty": "SHARED", "input": {}, "output": {"setContentTypeIfEmpty": false}}
+                        ]
+                    }
+                ]
+            })
+        );
+        assert_eq!(serde_json::from_value::<Endpoint>(json).unwrap(), endpoint);
+    }
+
+    #[test]
+    fn valid_names() {
+        let service_names = vec![
+            ("Greeter", true),
+            ("my.package.Greeter", true),
+            ("my-greeter", true),
+            ("_1greeter", true),
+            ("_", false),
+            ("__", false),
+            ("1greeter", false),
+            ("-greeter", false),
+            ("greeter/greet", false),
+            ("", false),
+        ];
+        for (name, valid) in service_names {
+            assert_eq!(
+                is_valid_name(name, |c| c == '.' || c == '-'),
+                valid,
+                "{name}"
+            );
+        }
+
+        let handler_names = vec![
+            ("greet", true),
+            ("_greet", true),
+            ("greet_2", true),
+            ("my.greet", false),
+            ("my-greet", false),
+            ("2greet", false),
+        ];
+        for (name, valid) in handler_names {
+            assert_eq!(is_valid_name(name, |_| false), valid, "{name}");
+        }
+    }
+
+    #[test]
+    fn bad_handler_type() {
+        let endpoint = Endpoint::new(ProtocolMode::RequestResponse).with_service(
+            Service::new("Greeter", ServiceType::Service)
+                .with_handler(Handler::new("greet").with_ty(HandlerType::Shared)),
+        );
+
+        assert!(matches!(
+            endpoint.serialize(),
+            Err(ManifestError::BadHandlerType { .. })
+        ));
+    }
+
+    #[test]
+    fn negotiate_version() {
+        let accept_headers = vec![
+            (None, Some(DiscoveryVersion::V1)),
+            (Some(CONTENT_TYPE_V1), Some(DiscoveryVersion::V1)),
+            (
+                Some("application/vnd.restate.endpointmanifest.v2+json, application/vnd.restate.endpointmanifest.v1+json"),
+                Some(DiscoveryVersion::V1),
+            ),
+            (
+                Some("application/vnd.restate.endpointmanifest.v1+json;q=0.9"),
+                Some(DiscoveryVersion::V1),
+            ),
+            (Some("*/*"), Some(DiscoveryVersion::V1)),
+            (Some("application/vnd.restate.endpointmanifest.v2+json"), None),
+            (Some("text/plain"), None),
+            (Some(""), None),
+        ];
+
+        for (accept, expected) in accept_headers {
+            assert_eq!(
+                negotiate_discovery_version(accept).ok(),
+                expected,
+                "{accept:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn handle_discovery_request() {
+        let endpoint = Endpoint::new(ProtocolMode::RequestResponse).with_service(
+            Service::new("Greeter", ServiceType::Service).with_handler(Handler::new("greet")),
+        );
+
+        let (head, body) = endpoint
+            .handle_discovery_request(vec![(ACCEPT.to_owned(), CONTENT_TYPE_V1.to_owned())])
+            .unwrap();
+
+        assert_eq!(head.status_code, 200);
+        assert_eq!(
+            head.headers,
+            vec![Header {
+                key: Cow::Borrowed(CONTENT_TYPE),
+                value: Cow::Borrowed(CONTENT_TYPE_V1)
+            }]
+        );
+        assert_eq!(head.protocol_mode, ProtocolMode::RequestResponse);
+        assert_eq!(body, endpoint.serialize().unwrap());
+
+        let err = endpoint
+            .handle_discovery_request(vec![(ACCEPT.to_owned(), "text/plain".to_owned())])
+            .unwrap_err();
+        assert_eq!(err.code(), u16::from(errors::codes::UNSUPPORTED_MEDIA_TYPE));
+    }
+
+    #[test]
+    fn duplicate_service() {
+        let endpoint = Endpoint::new(ProtocolMode::BidiStream)
+            .with_service(Service::new("Greeter", ServiceType::Service))
+            .with_service(Service::new("Greeter", ServiceType::Workflow));
+
+        assert!(matches!(
+            endpoint.validate(),
+            Err(ManifestError::DuplicateService(_))
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+/// Deterministic pseudo-random number generator, seeded from the invocation id.
+///
+/// The algorithm is fixed, so every SDK generates the same sequence of values for the same invocation:
+///
+/// * The state is initialized from the 64-bit seed using [SplitMix64](https://prng.di.unimi.it/splitmix64.c),
+///   generating the 4 words of the state in order.
+/// * Values are generated with [xoshiro256++](https://prng.di.unimi.it/xoshiro256plusplus.c).
+/// * `f64` values are built from the 53 most significant bits of the next `u64`, and are in the range `[0, 1)`.
+/// * UUIDs are built from the next two `u64` in big endian order, with version and variant bits set as per RFC 9562 UUIDv4.
+///
+/// The VM seeds it with [`Input::random_seed`](crate::Input::random_seed).
+///
+/// Because the seed is the same on every attempt, the generated values are stable across replays,
+/// as long as the handler code asks for values in the same order.
+#[derive(Debug, Clone)]
+pub struct DeterministicRng {
+    s: [u64; 4],
+}
+
+impl DeterministicRng {
+    pub fn new(seed: u64) -> Self {
+        let mut sm = seed;
+        let mut next_splitmix64 = || {
+            sm = sm.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = sm;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Self {
+            s: [
+                next_splitmix64(),
+                next_splitmix64(),
+                next_splitmix64(),
+                next_splitmix64(),
+            ],
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        result
+    }
+
+    /// Returns a value in the range `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Returns a random UUID v4, in its lowercase hyphenated string representation.
+    pub fn next_uuid_v4(&mut self) -> String {
+        let mut b = [0u8; 16];
+        b[..8].copy_from_slice(&self.next_u64().to_be_bytes());
+        b[8..].copy_from_slice(&self.next_u64().to_be_bytes());
+
+        // Version 4
+        b[6] = (b[6] & 0x0f) | 0x40;
+        // Variant RFC 9562
+        b[8] = (b[8] & 0x3f) | 0x80;
+
+        format!(
+            "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+            b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7], b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15]
+        )
+    }
+}
+
+/// Computes the random seed of the invocation from the invocation id, as sent by the runtime in the start message.
+///
+/// With the `sha2_random_seed` feature, the seed is the first 8 bytes, in big endian order, of the SHA-256 of the invocation id.
+#[cfg(feature = "sha2_random_seed")]
+pub(crate) fn compute_random_seed(id: &[u8]) -> u64 {
+    use bytes::Buf;
+    use sha2::{Digest, Sha256};
+
+    let id_hash = Sha256::digest(id);
+    let mut b = id_hash.as_slice();
+    b.get_u64()
+}
+
+/// Computes the random seed of the invocation from the invocation id, as sent by the runtime in the start message.
+///
+/// Without the `sha2_random_seed` feature, the seed is the hash of the invocation id computed with [`DefaultHasher`](std::hash::DefaultHasher).
+#[cfg(not(feature = "sha2_random_seed"))]
+pub(crate) fn compute_random_seed(id: &[u8]) -> u64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xoshiro256plusplus_reference() {
+        // Reference values from the xoshiro256++ reference implementation
+        let mut rng = DeterministicRng { s: [1, 2, 3, 4] };
+        let expected = [
+            41943041,
+            58720359,
+            3588806011781223,
+            3591011842654386,
+            9228616714210784205,
+            9973669472204895162,
+            14011001112246962877,
+            12406186145184390807,
+            15849039046786891736,
+            10450023813501588000,
+        ];
+        for e in expected {
+            assert_eq!(rng.next_u64(), e);
+        }
+    }
+
+    #[cfg(feature = "sha2_random_seed")]
+    #[test]
+    fn random_seed_is_sha256_prefix() {
+        // echo -n 123 | sha256sum
+        assert_eq!(compute_random_seed(b"123"), 0xa665a45920422f9d);
+    }
+
+    #[test]
+    fn splitmix64_seeding() {
+        // Reference values of SplitMix64 seeded with 0
+        let rng = DeterministicRng::new(0);
+        assert_eq!(
+            rng.s,
+            [
+                0xe220a8397b1dcdaf,
+                0x6e789e6aa1b965f4,
+                0x06c45d188009454f,
+                0xf88bb8a8724c81ec
+            ]
+        );
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut rng1 = DeterministicRng::new(123);
+        let mut rng2 = DeterministicRng::new(123);
+        for _ in 0..10 {
+            assert_eq!(rng1.next_u64(), rng2.next_u64());
+        }
+        assert_ne!(
+            DeterministicRng::new(123).next_u64(),
+            DeterministicRng::new(124).next_u64()
+        );
+    }
+
+    #[test]
+    fn f64_range() {
+        let mut rng = DeterministicRng::new(42);
+        for _ in 0..1000 {
+            let f = rng.next_f64();
+            assert!((0.0..1.0).contains(&f));
+        }
+    }
+
+    #[test]
+    fn uuid_v4_format() {
+        let mut rng = DeterministicRng::new(42);
+        let uuid = rng.next_uuid_v4();
+
+        assert_eq!(uuid.len(), 36);
+        let groups: Vec<&str> = uuid.split('-').collect();
+        assert_eq!(
+            groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+            vec![8, 4, 4, 4, 12]
+        );
+        assert!(groups[2].starts_with('4'));
+        assert!(matches!(
+            groups[3].chars().next(),
+            Some('8' | '9' | 'a' | 'b')
+        ));
+        assert!(uuid
+            .chars()
+            .all(|c| c == '-' || c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+    }
+}
@@ -0,0 +1,417 @@
+//! Recording of the input stream of an invocation, to reproduce it offline.
+//!
+//! A recording is made of a small container header, followed by the bytes passed to [`VM::notify_input`]
+//! in the service protocol framing, and by whether [`VM::notify_input_closed`] was invoked:
+//!
+//! ```text
+//! magic: b"RSIR"
+//! format version: u16, big endian
+//! headers count: u32, big endian
+//! for each header:
+//!     key length: u32, big endian
+//!     key: UTF-8 bytes
+//!     value length: u32, big endian
+//!     value: UTF-8 bytes
+//! records, until the end of the recording:
+//!     tag: u8, 0 for input, 1 for input closed
+//!     for input:
+//!         length: u32, big endian
+//!         bytes: the service protocol messages, as passed to VM::notify_input
+//! ```
+//!
+//! Use [`InputRecorder`] to capture the input, [`Recording`] to read it back and [`Replay`] to feed it to a new [`CoreVM`].
+
+use crate::error::codes;
+use crate::service_protocol::Decoder;
+use crate::{CoreVM, Error, VMOptions, VM};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::VecDeque;
+use std::io;
+use std::io::{Read, Write};
+use std::mem;
+
+const MAGIC: &[u8; 4] = b"RSIR";
+const FORMAT_VERSION: u16 = 1;
+const INPUT_RECORD: u8 = 0;
+const INPUT_CLOSED_RECORD: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("not a recording, bad magic number")]
+    BadMagic,
+    #[error("unsupported recording format version {0}")]
+    UnsupportedFormatVersion(u16),
+    #[error("truncated recording")]
+    Truncated,
+    #[error("recording header is not valid UTF-8")]
+    BadHeaderEncoding,
+    #[error("unknown recording record tag {0}")]
+    UnknownRecord(u8),
+    #[error("input recorded after the input was closed")]
+    InputAfterClose,
+}
+
+/// Writes the recording of the input stream of an invocation.
+pub struct InputRecorder<W> {
+    writer: W,
+}
+
+impl<W: Write> InputRecorder<W> {
+    /// Writes the container header, including the given request headers.
+    pub fn new(mut writer: W, request_headers: &[(String, String)]) -> io::Result<Self> {
+        let mut header = BytesMut::new();
+        header.put_slice(MAGIC);
+        header.put_u16(FORMAT_VERSION);
+        header.put_u32(request_headers.len() as u32);
+        for (key, value) in request_headers {
+            header.put_u32(key.len() as u32);
+            header.put_slice(key.as_bytes());
+            header.put_u32(value.len() as u32);
+            header.put_slice(value.as_bytes());
+        }
+        writer.write_all(&header)?;
+
+        Ok(Self { writer })
+    }
+
+    /// Record the given buffer. Should be invoked with the same buffers passed to [`VM::notify_input`].
+    pub fn record(&mut self, buffer: &[u8]) -> io::Result<()> {
+        let mut record_header = [0; 5];
+        record_header[0] = INPUT_RECORD;
+        record_header[1..].copy_from_slice(&(buffer.len() as u32).to_be_bytes());
+        self.writer.write_all(&record_header)?;
+        self.writer.write_all(buffer)
+    }
+
+    /// Record that the input was closed. Should be invoked together with [`VM::notify_input_closed`].
+    pub fn record_input_closed(&mut self) -> io::Result<()> {
+        self.writer.write_all(&[INPUT_CLOSED_RECORD])
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Recording read back.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub request_headers: Vec<(String, String)>,
+    pub input: Bytes,
+    pub input_closed: bool,
+}
+
+impl Recording {
+    pub fn read_from(mut reader: impl Read) -> Result<Self, RecordingError> {
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf)?;
+        Self::decode(Bytes::from(buf))
+    }
+
+    pub fn decode(mut buf: Bytes) -> Result<Self, RecordingError> {
+        if buf.remaining() < MAGIC.len() || &buf.split_to(MAGIC.len())[..] != MAGIC {
+            return Err(RecordingError::BadMagic);
+        }
+        if buf.remaining() < 6 {
+            return Err(RecordingError::Truncated);
+        }
+        let format_version = buf.get_u16();
+        if format_version != FORMAT_VERSION {
+            return Err(RecordingError::UnsupportedFormatVersion(format_version));
+        }
+
+        let headers_count = buf.get_u32();
+        let mut request_headers = Vec::new();
+        for _ in 0..headers_count {
+            let key = read_string(&mut buf)?;
+            let value = read_string(&mut buf)?;
+            request_headers.push((key, value));
+        }
+
+        let mut input = BytesMut::new();
+        let mut input_closed = false;
+        while buf.has_remaining() {
+            match buf.get_u8() {
+                INPUT_RECORD if input_closed => return Err(RecordingError::InputAfterClose),
+                INPUT_RECORD => {
+                    if buf.remaining() < 4 {
+                        return Err(RecordingError::Truncated);
+                    }
+                    let len = buf.get_u32() as usize;
+                    if buf.remaining() < len {
+                        return Err(RecordingError::Truncated);
+                    }
+                    input.put(buf.split_to(len));
+                }
+                INPUT_CLOSED_RECORD => input_closed = true,
+                tag => return Err(RecordingError::UnknownRecord(tag)),
+            }
+        }
+
+        Ok(Self {
+            request_headers,
+            input: input.freeze(),
+            input_closed,
+        })
+    }
+}
+
+fn read_string(buf: &mut Bytes) -> Result<String, RecordingError> {
+    if buf.remaining() < 4 {
+        return Err(RecordingError::Truncated);
+    }
+    let len = buf.get_u32() as usize;
+    if buf.remaining() < len {
+        return Err(RecordingError::Truncated);
+    }
+    String::from_utf8(buf.split_to(len).to_vec()).map_err(|_| RecordingError::BadHeaderEncoding)
+}
+
+/// Replays a [`Recording`] on a new [`CoreVM`], one message at a time.
+///
+/// Between the steps, the VM can be inspected with [`CoreVM::snapshot`], and the handler code can be re-run using [`Replay::vm`].
+pub struct Replay {
+    vm: CoreVM,
+    messages: VecDeque<Bytes>,
+    // Whether the recorded input closing is still to be delivered to the VM
+    close_input: bool,
+}
+
+impl Replay {
+    pub fn new(recording: &Recording, options: VMOptions) -> Result<Self, Error> {
+        let vm = CoreVM::new(recording.request_headers.clone(), options)?;
+
+        // Split the input in messages, re-encoding each of them
+        let mut decoder = Decoder::new(vm.get_response_head().version);
+        decoder.push(recording.input.clone());
+        let mut messages = VecDeque::new();
+        while let Some(msg) = decoder.consume_next()? {
+            let mut buf = BytesMut::with_capacity(8 + msg.payload().len());
+            buf.put_u64(msg.header().into());
+            buf.put_slice(msg.payload());
+            messages.push_back(buf.freeze());
+        }
+        if decoder.buffered_bytes() != 0 {
+            return Err(Error::new(
+                codes::PROTOCOL_VIOLATION,
+                format!(
+                    "The recording ends with a truncated message of {} bytes",
+                    decoder.buffered_bytes()
+                ),
+            ));
+        }
+
+        Ok(Self {
+            vm,
+            messages,
+            close_input: recording.input_closed,
+        })
+    }
+
+    /// Number of recorded messages not yet fed to the VM.
+    pub fn remaining_messages(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Feeds the next recorded message to the VM. Returns false if there are no more messages.
+    ///
+    /// Once the recorded messages are exhausted, the input is closed if it was closed in the recording,
+    /// even when the recording has no messages at all.
+    pub fn step(&mut self) -> bool {
+        let fed = match self.messages.pop_front() {
+            Some(msg) => {
+                self.vm.notify_input(msg);
+                true
+            }
+            None => false,
+        };
+        if self.messages.is_empty() && mem::take(&mut self.close_input) {
+            self.vm.notify_input_closed();
+        }
+        fed
+    }
+
+    /// Feeds all the remaining recorded messages to the VM, closing the input if it was closed in the recording.
+    pub fn step_to_end(&mut self) {
+        while self.step() {}
+    }
+
+    pub fn vm(&mut self) -> &mut CoreVM {
+        &mut self.vm
+    }
+
+    pub fn into_vm(self) -> CoreVM {
+        self.vm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::service_protocol::messages::{InputEntryMessage, StartMessage};
+    use crate::service_protocol::{Encoder, Version};
+    use crate::{EntryOptions, Input, NonEmptyValue, TakeOutputResult};
+    use std::time::Duration;
+
+    fn request_headers() -> Vec<(String, String)> {
+        vec![(
+            "content-type".to_owned(),
+            Version::maximum_supported_version().to_string(),
+        )]
+    }
+
+    fn record(close_input: bool) -> Vec<u8> {
+        let encoder = Encoder::new(Version::maximum_supported_version());
+        let mut recorder = InputRecorder::new(vec![], &request_headers()).unwrap();
+        recorder
+            .record(&encoder.encode(&StartMessage {
+                id: Bytes::from_static(b"123"),
+                debug_id: "123".to_string(),
+                known_entries: 1,
+                ..Default::default()
+            }))
+            .unwrap();
+        recorder
+            .record(&encoder.encode(&InputEntryMessage {
+                value: Bytes::from_static(b"my-data"),
+                ..Default::default()
+            }))
+            .unwrap();
+        if close_input {
+            recorder.record_input_closed().unwrap();
+        }
+        recorder.into_inner()
+    }
+
+    #[test]
+    fn read_recording() {
+        let recording = Recording::read_from(record(true).as_slice()).unwrap();
+
+        assert_eq!(recording.request_headers, request_headers());
+        assert!(!recording.input.is_empty());
+        assert!(recording.input_closed);
+    }
+
+    #[test]
+    fn replay_recording() {
+        let recording = Recording::read_from(record(true).as_slice()).unwrap();
+        let mut replay = Replay::new(&recording, VMOptions::default()).unwrap();
+
+        assert_eq!(replay.remaining_messages(), 2);
+        assert!(replay.step());
+        assert!(!replay.vm().is_ready_to_execute().unwrap());
+        assert_eq!(replay.vm().snapshot().state, "WaitingReplayEntries");
+
+        assert!(replay.step());
+        assert!(!replay.step());
+        assert!(replay.vm().is_ready_to_execute().unwrap());
+
+        let vm = replay.vm();
+        let Input { input, .. } = vm.sys_input().unwrap();
+        assert_eq!(input, Bytes::from_static(b"my-data"));
+        vm.sys_write_output(NonEmptyValue::Success(input), EntryOptions::default())
+            .unwrap();
+        vm.sys_end().unwrap();
+
+        assert!(matches!(vm.take_output(), TakeOutputResult::Buffer(_)));
+    }
+
+    #[test]
+    fn replay_recording_keeps_input_open() {
+        let recording = Recording::read_from(record(false).as_slice()).unwrap();
+        assert!(!recording.input_closed);
+        let mut replay = Replay::new(&recording, VMOptions::default()).unwrap();
+        replay.step_to_end();
+
+        let vm = replay.vm();
+        vm.sys_input().unwrap();
+        let handle = vm
+            .sys_sleep(Duration::from_secs(1), EntryOptions::default())
+            .unwrap();
+        vm.notify_await_point(handle);
+
+        // The input is still open, so the VM waits for the completion instead of suspending
+        assert!(matches!(vm.take_async_result(handle), Ok(None)));
+    }
+
+    #[test]
+    fn replay_recording_without_messages() {
+        let mut recorder = InputRecorder::new(vec![], &request_headers()).unwrap();
+        recorder.record_input_closed().unwrap();
+        let recording = Recording::read_from(recorder.into_inner().as_slice()).unwrap();
+        let mut replay = Replay::new(&recording, VMOptions::default()).unwrap();
+
+        assert_eq!(replay.remaining_messages(), 0);
+        assert!(!replay.step());
+
+        // Like in the recorded run, the input is closed before the start message
+        let err = replay.vm().is_ready_to_execute().unwrap_err();
+        assert_eq!(
+            err.code(),
+            crate::vm::errors::INPUT_CLOSED_WHILE_WAITING_ENTRIES.code()
+        );
+        assert!(!replay.step());
+    }
+
+    #[test]
+    fn replay_recording_with_truncated_message() {
+        let encoder = Encoder::new(Version::maximum_supported_version());
+        let mut recorder = InputRecorder::new(vec![], &request_headers()).unwrap();
+        recorder
+            .record(&encoder.encode(&StartMessage {
+                id: Bytes::from_static(b"123"),
+                debug_id: "123".to_string(),
+                known_entries: 1,
+                ..Default::default()
+            }))
+            .unwrap();
+        let input_entry = encoder.encode(&InputEntryMessage {
+            value: Bytes::from_static(b"my-data"),
+            ..Default::default()
+        });
+        recorder
+            .record(&input_entry[..input_entry.len() - 1])
+            .unwrap();
+        let recording = Recording::read_from(recorder.into_inner().as_slice()).unwrap();
+
+        let err = Replay::new(&recording, VMOptions::default()).err().unwrap();
+        assert_eq!(err.code(), u16::from(codes::PROTOCOL_VIOLATION));
+    }
+
+    #[test]
+    fn bad_recordings() {
+        assert!(matches!(
+            Recording::decode(Bytes::from_static(b"nope")),
+            Err(RecordingError::BadMagic)
+        ));
+        assert!(matches!(
+            Recording::decode(Bytes::from_static(b"RSIR\x00\x02\x00\x00\x00\x00")),
+            Err(RecordingError::UnsupportedFormatVersion(2))
+        ));
+        assert!(matches!(
+            Recording::decode(Bytes::from_static(
+                b"RSIR\x00\x01\x00\x00\x00\x01\x00\x00\x00\x10a"
+            )),
+            Err(RecordingError::Truncated)
+        ));
+        assert!(matches!(
+            Recording::decode(Bytes::from_static(
+                b"RSIR\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x10a"
+            )),
+            Err(RecordingError::Truncated)
+        ));
+        assert!(matches!(
+            Recording::decode(Bytes::from_static(b"RSIR\x00\x01\x00\x00\x00\x00\x02")),
+            Err(RecordingError::UnknownRecord(2))
+        ));
+        assert!(matches!(
+            Recording::decode(Bytes::from_static(
+                b"RSIR\x00\x01\x00\x00\x00\x00\x01\x00\x00\x00\x00\x00"
+            )),
+            Err(RecordingError::InputAfterClose)
+        ));
+    }
+}
@@ -1,6 +1,9 @@
-use crate::EntryRetryInfo;
-use std::cmp;
+use crate::random::DeterministicRng;
+use crate::{EntryRetryInfo, Error};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 use std::time::Duration;
+use std::{cmp, fmt};
 
 /// This struct represents the policy to execute retries.
 #[derive(Debug, Clone, Default)]
@@ -70,8 +73,95 @@ pub enum RetryPolicy {
         /// Infinite retries if this field and `max_attempts` are unset.
         max_duration: Option<Duration>,
     },
+    /// # Jittered
+    ///
+    /// Randomize the retry interval of the `policy`, see [`RetryPolicy::with_jitter`].
+    Jittered {
+        /// # Policy
+        ///
+        /// Policy computing the retry interval to randomize. Only [`RetryPolicy::Exponential`] is affected by the jitter.
+        policy: Box<RetryPolicy>,
+
+        /// # Jitter
+        ///
+        /// Randomization applied to the computed retry interval, see [`Jitter`].
+        jitter: Jitter,
+    },
+    /// # Classified
+    ///
+    /// Classify the retryable failures before applying the `policy`:
+    /// failures matching any of the `terminal` rules are not retried, and are recorded as terminal failures.
+    Classified {
+        /// # Policy
+        ///
+        /// Policy to apply to the failures not matching any of the `terminal` rules.
+        policy: Box<RetryPolicy>,
+
+        /// # Terminal rules
+        ///
+        /// Rules classifying failures as terminal.
+        terminal: Vec<TerminalErrorRule>,
+    },
+}
+
+/// Rule classifying a retryable failure as terminal, see [`RetryPolicy::Classified`].
+#[derive(Clone)]
+pub enum TerminalErrorRule {
+    /// Failures with a code within this range are terminal.
+    Codes(RangeInclusive<u16>),
+    /// Failures for which this predicate returns true are terminal.
+    Predicate(Arc<dyn Fn(&Error) -> bool + Send + Sync>),
 }
 
+impl TerminalErrorRule {
+    fn matches(&self, error: &Error) -> bool {
+        match self {
+            TerminalErrorRule::Codes(codes) => codes.contains(&error.code()),
+            TerminalErrorRule::Predicate(predicate) => predicate(error),
+        }
+    }
+}
+
+impl fmt::Debug for TerminalErrorRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminalErrorRule::Codes(codes) => f.debug_tuple("Codes").field(codes).finish(),
+            TerminalErrorRule::Predicate(_) => f.write_str("Predicate"),
+        }
+    }
+}
+
+/// Randomization of the [`RetryPolicy::Exponential`] retry interval, to avoid retrying in lockstep.
+///
+/// The random values are generated deterministically from the invocation random seed and the journal entry index,
+/// so the same retry interval is computed when the invocation is replayed.
+/// In the formulas below, `interval(n) = min(initial_interval * factor^(n-1), max_interval)`
+/// and `random(a, b)` is a random duration in the range `[a, b)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Jitter {
+    /// # None
+    ///
+    /// The retry interval is `interval(n)`.
+    #[default]
+    None,
+    /// # Full
+    ///
+    /// The retry interval is `random(0, interval(n))`.
+    Full,
+    /// # Equal
+    ///
+    /// The retry interval is `interval(n) / 2 + random(0, interval(n) / 2)`.
+    Equal,
+    /// # Decorrelated
+    ///
+    /// The retry interval is `min(random(initial_interval, previous * factor), max_interval)`,
+    /// where `previous` is the retry interval of the previous attempt, or `initial_interval` for the first attempt.
+    Decorrelated,
+}
+
+/// Past this number of attempts, the jitter is computed as for the last one, to bound the random values to generate.
+const MAX_JITTER_ATTEMPTS: u32 = 1000;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub(crate) enum NextRetry {
     Retry(Option<Duration>),
@@ -107,7 +197,79 @@ impl RetryPolicy {
         }
     }
 
-    pub(crate) fn next_retry(&self, retry_info: EntryRetryInfo) -> NextRetry {
+    /// Set the [`Jitter`] of a [`RetryPolicy::Exponential`], wrapping it in [`RetryPolicy::Jittered`].
+    /// Other policies are left unchanged.
+    pub fn with_jitter(self, jitter: Jitter) -> Self {
+        match self {
+            RetryPolicy::Exponential { .. } => RetryPolicy::Jittered {
+                policy: Box::new(self),
+                jitter,
+            },
+            RetryPolicy::Jittered { policy, .. } => RetryPolicy::Jittered { policy, jitter },
+            RetryPolicy::Classified { policy, terminal } => RetryPolicy::Classified {
+                policy: Box::new(policy.with_jitter(jitter)),
+                terminal,
+            },
+            policy => policy,
+        }
+    }
+
+    /// Don't retry failures with a code within the given range, e.g. `400..=499`.
+    pub fn with_terminal_codes(self, codes: RangeInclusive<u16>) -> Self {
+        self.with_terminal_rule(TerminalErrorRule::Codes(codes))
+    }
+
+    /// Don't retry failures with the given code.
+    pub fn with_terminal_code(self, code: u16) -> Self {
+        self.with_terminal_codes(code..=code)
+    }
+
+    /// Don't retry failures for which the given predicate returns true.
+    pub fn with_terminal_predicate(
+        self,
+        predicate: impl Fn(&Error) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.with_terminal_rule(TerminalErrorRule::Predicate(Arc::new(predicate)))
+    }
+
+    pub fn with_terminal_rule(self, rule: TerminalErrorRule) -> Self {
+        match self {
+            RetryPolicy::Classified {
+                policy,
+                mut terminal,
+            } => {
+                terminal.push(rule);
+                RetryPolicy::Classified { policy, terminal }
+            }
+            policy => RetryPolicy::Classified {
+                policy: Box::new(policy),
+                terminal: vec![rule],
+            },
+        }
+    }
+
+    /// Returns true if the given failure should not be retried, regardless of the retry bounds.
+    pub(crate) fn is_terminal(&self, error: &Error) -> bool {
+        match self {
+            RetryPolicy::Classified { policy, terminal } => {
+                terminal.iter().any(|rule| rule.matches(error)) || policy.is_terminal(error)
+            }
+            RetryPolicy::Jittered { policy, .. } => policy.is_terminal(error),
+            _ => false,
+        }
+    }
+
+    /// `jitter_seed` is used to generate the random values when [`Jitter`] is enabled.
+    pub(crate) fn next_retry(&self, retry_info: EntryRetryInfo, jitter_seed: u64) -> NextRetry {
+        self.next_retry_with_jitter(retry_info, Jitter::None, jitter_seed)
+    }
+
+    fn next_retry_with_jitter(
+        &self,
+        retry_info: EntryRetryInfo,
+        jitter: Jitter,
+        jitter_seed: u64,
+    ) -> NextRetry {
         match self {
             RetryPolicy::Infinite => NextRetry::Retry(None),
             RetryPolicy::None => NextRetry::DoNotRetry,
@@ -142,15 +304,81 @@ impl RetryPolicy {
                     return NextRetry::DoNotRetry;
                 }
 
-                NextRetry::Retry(Some(cmp::min(
-                    max_interval.unwrap_or(Duration::MAX),
-                    initial_interval.mul_f32(factor.powi((retry_info.retry_count - 1) as i32)),
-                )))
+                let max_interval = max_interval.unwrap_or(Duration::MAX);
+                let interval = |attempt: u32| {
+                    let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+                    cmp::min(
+                        max_interval,
+                        saturating_mul_f32(*initial_interval, factor.powi(exponent)),
+                    )
+                };
+
+                let mut rng = DeterministicRng::new(jitter_seed);
+                NextRetry::Retry(Some(match jitter {
+                    Jitter::None => interval(retry_info.retry_count),
+                    Jitter::Full => {
+                        let random = nth_f64(&mut rng, retry_info.retry_count);
+                        saturating_mul_f64(interval(retry_info.retry_count), random)
+                    }
+                    Jitter::Equal => {
+                        let random = nth_f64(&mut rng, retry_info.retry_count);
+                        let half = interval(retry_info.retry_count) / 2;
+                        half.saturating_add(saturating_mul_f64(half, random))
+                    }
+                    Jitter::Decorrelated => {
+                        let mut previous = *initial_interval;
+                        for _ in 0..retry_info.retry_count.min(MAX_JITTER_ATTEMPTS) {
+                            let upper = saturating_mul_f32(previous, *factor);
+                            previous = cmp::min(
+                                max_interval,
+                                initial_interval.saturating_add(saturating_mul_f64(
+                                    upper.saturating_sub(*initial_interval),
+                                    rng.next_f64(),
+                                )),
+                            );
+                        }
+                        previous
+                    }
+                }))
+            }
+            RetryPolicy::Jittered { policy, jitter } => {
+                policy.next_retry_with_jitter(retry_info, *jitter, jitter_seed)
+            }
+            RetryPolicy::Classified { policy, .. } => {
+                policy.next_retry_with_jitter(retry_info, jitter, jitter_seed)
             }
         }
     }
 }
 
+/// Like [`Duration::mul_f32`], but saturating to [`Duration::MAX`] on overflow and to [`Duration::ZERO`] when `rhs` is negative or NaN.
+fn saturating_mul_f32(duration: Duration, rhs: f32) -> Duration {
+    let secs = duration.as_secs_f32() * rhs;
+    Duration::try_from_secs_f32(secs).unwrap_or(if secs > 0.0 {
+        Duration::MAX
+    } else {
+        Duration::ZERO
+    })
+}
+
+/// Like [`Duration::mul_f64`], saturating like [`saturating_mul_f32`].
+fn saturating_mul_f64(duration: Duration, rhs: f64) -> Duration {
+    let secs = duration.as_secs_f64() * rhs;
+    Duration::try_from_secs_f64(secs).unwrap_or(if secs > 0.0 {
+        Duration::MAX
+    } else {
+        Duration::ZERO
+    })
+}
+
+/// Returns the random value of the given attempt, so every retry attempt of the same entry gets a different value.
+fn nth_f64(rng: &mut DeterministicRng, attempt: u32) -> f64 {
+    for _ in 1..attempt.min(MAX_JITTER_ATTEMPTS) {
+        rng.next_u64();
+    }
+    rng.next_f64()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -166,32 +394,212 @@ mod tests {
         };
 
         assert_eq!(
-            policy.next_retry(EntryRetryInfo {
-                retry_count: 2,
-                retry_loop_duration: Duration::from_secs(1)
-            }),
+            policy.next_retry(
+                EntryRetryInfo {
+                    retry_count: 2,
+                    retry_loop_duration: Duration::from_secs(1)
+                },
+                0
+            ),
             NextRetry::Retry(Some(Duration::from_millis(100).mul_f32(2.0)))
         );
         assert_eq!(
-            policy.next_retry(EntryRetryInfo {
-                retry_count: 3,
-                retry_loop_duration: Duration::from_secs(1)
-            }),
+            policy.next_retry(
+                EntryRetryInfo {
+                    retry_count: 3,
+                    retry_loop_duration: Duration::from_secs(1)
+                },
+                0
+            ),
             NextRetry::Retry(Some(Duration::from_millis(100).mul_f32(4.0)))
         );
         assert_eq!(
-            policy.next_retry(EntryRetryInfo {
-                retry_count: 4,
-                retry_loop_duration: Duration::from_secs(1)
-            }),
+            policy.next_retry(
+                EntryRetryInfo {
+                    retry_count: 4,
+                    retry_loop_duration: Duration::from_secs(1)
+                },
+                0
+            ),
             NextRetry::Retry(Some(Duration::from_millis(500)))
         );
         assert_eq!(
-            policy.next_retry(EntryRetryInfo {
-                retry_count: 4,
-                retry_loop_duration: Duration::from_secs(10)
-            }),
+            policy.next_retry(
+                EntryRetryInfo {
+                    retry_count: 4,
+                    retry_loop_duration: Duration::from_secs(10)
+                },
+                0
+            ),
             NextRetry::DoNotRetry
         );
     }
+
+    fn exponential_with_jitter(jitter: Jitter) -> RetryPolicy {
+        RetryPolicy::exponential(
+            Duration::from_millis(100),
+            2.0,
+            None,
+            Some(Duration::from_secs(10)),
+            None,
+        )
+        .with_jitter(jitter)
+    }
+
+    fn next_retry_delay(policy: &RetryPolicy, retry_count: u32, jitter_seed: u64) -> Duration {
+        match policy.next_retry(
+            EntryRetryInfo {
+                retry_count,
+                retry_loop_duration: Duration::ZERO,
+            },
+            jitter_seed,
+        ) {
+            NextRetry::Retry(Some(d)) => d,
+            r => panic!("Unexpected next retry {r:?}"),
+        }
+    }
+
+    #[test]
+    fn test_full_jitter() {
+        let policy = exponential_with_jitter(Jitter::Full);
+        for retry_count in 1..10 {
+            for seed in 0..100 {
+                let delay = next_retry_delay(&policy, retry_count, seed);
+                assert!(
+                    delay
+                        < cmp::min(
+                            Duration::from_secs(10),
+                            Duration::from_millis(100) * 2u32.pow(retry_count - 1)
+                        )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_equal_jitter() {
+        let policy = exponential_with_jitter(Jitter::Equal);
+        for seed in 0..100 {
+            let delay = next_retry_delay(&policy, 3, seed);
+            assert!(delay >= Duration::from_millis(200) && delay < Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn test_decorrelated_jitter() {
+        let policy = exponential_with_jitter(Jitter::Decorrelated);
+        for retry_count in 1..20 {
+            for seed in 0..100 {
+                let delay = next_retry_delay(&policy, retry_count, seed);
+                assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_secs(10));
+            }
+        }
+    }
+
+    #[test]
+    fn test_jitter_is_deterministic() {
+        for jitter in [Jitter::Full, Jitter::Equal, Jitter::Decorrelated] {
+            let policy = exponential_with_jitter(jitter);
+            assert_eq!(
+                next_retry_delay(&policy, 3, 42),
+                next_retry_delay(&policy, 3, 42)
+            );
+            assert_ne!(
+                next_retry_delay(&policy, 3, 42),
+                next_retry_delay(&policy, 3, 43)
+            );
+            assert_ne!(
+                next_retry_delay(&policy, 3, 42),
+                next_retry_delay(&policy, 4, 42)
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_jitter_ignored_by_other_policies() {
+        assert!(matches!(
+            RetryPolicy::Infinite.with_jitter(Jitter::Full),
+            RetryPolicy::Infinite
+        ));
+    }
+
+    #[test]
+    fn test_terminal_rules() {
+        let policy = RetryPolicy::Infinite
+            .with_terminal_codes(400..=499)
+            .with_terminal_code(503)
+            .with_terminal_predicate(|e| e.message().contains("fatal"));
+
+        assert!(policy.is_terminal(&Error::new(400u16, "bad")));
+        assert!(policy.is_terminal(&Error::new(404u16, "not found")));
+        assert!(policy.is_terminal(&Error::new(503u16, "unavailable")));
+        assert!(policy.is_terminal(&Error::internal("fatal error")));
+        assert!(!policy.is_terminal(&Error::internal("transient error")));
+        assert!(!policy.is_terminal(&Error::new(502u16, "bad gateway")));
+        assert!(!RetryPolicy::Infinite.is_terminal(&Error::new(400u16, "bad")));
+
+        // Non terminal failures use the classified policy
+        assert_eq!(
+            policy.next_retry(
+                EntryRetryInfo {
+                    retry_count: 1,
+                    retry_loop_duration: Duration::ZERO
+                },
+                0
+            ),
+            NextRetry::Retry(None)
+        );
+    }
+
+    #[test]
+    fn test_with_jitter_on_classified_policy() {
+        let policy = exponential_with_jitter(Jitter::None)
+            .with_terminal_code(400)
+            .with_jitter(Jitter::Full);
+        assert_jitter(&policy, Jitter::Full);
+    }
+
+    #[test]
+    fn test_exponential_policy_saturates() {
+        let retry_info = |retry_count| EntryRetryInfo {
+            retry_count,
+            retry_loop_duration: Duration::ZERO,
+        };
+        let policy = |factor, jitter| {
+            RetryPolicy::exponential(Duration::from_secs(1), factor, None, None, None)
+                .with_jitter(jitter)
+        };
+
+        // retry_count == 0 must not underflow
+        assert_eq!(
+            policy(2.0, Jitter::None).next_retry(retry_info(0), 0),
+            NextRetry::Retry(Some(Duration::from_secs(1)))
+        );
+        for jitter in [Jitter::None, Jitter::Full, Jitter::Equal] {
+            assert!(matches!(
+                policy(f32::MAX, jitter).next_retry(retry_info(u32::MAX), 0),
+                NextRetry::Retry(Some(_))
+            ));
+            assert_eq!(
+                policy(-2.0, jitter).next_retry(retry_info(2), 0),
+                NextRetry::Retry(Some(Duration::ZERO))
+            );
+            assert_eq!(
+                policy(f32::NAN, jitter).next_retry(retry_info(2), 0),
+                NextRetry::Retry(Some(Duration::ZERO))
+            );
+        }
+        assert!(matches!(
+            policy(f32::MAX, Jitter::Decorrelated).next_retry(retry_info(u32::MAX), 0),
+            NextRetry::Retry(Some(_))
+        ));
+    }
+
+    fn assert_jitter(policy: &RetryPolicy, expected: Jitter) {
+        match policy {
+            RetryPolicy::Jittered { jitter, .. } => assert_eq!(*jitter, expected),
+            RetryPolicy::Classified { policy, .. } => assert_jitter(policy, expected),
+            p => panic!("Unexpected policy {p:?}"),
+        }
+    }
 }
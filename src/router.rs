@@ -0,0 +1,279 @@
+use crate::manifest::Endpoint;
+use crate::{Header, ProtocolMode, ResponseHead, Version};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+const ALLOW: &str = "allow";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    /// `GET /discover`, should be answered with [`Endpoint::handle_discovery_request`].
+    Discover,
+    /// `POST /invoke/{service}/{handler}`, should be answered constructing a [`crate::CoreVM`].
+    Invoke { service: String, handler: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RouteError {
+    #[error("no route for path '{0}'")]
+    NotFound(String),
+    #[error("method '{method}' not allowed, expected '{allowed}'")]
+    MethodNotAllowed {
+        method: String,
+        allowed: &'static str,
+    },
+}
+
+impl RouteError {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            RouteError::NotFound(_) => 404,
+            RouteError::MethodNotAllowed { .. } => 405,
+        }
+    }
+
+    /// Response head to send back when the request cannot be routed.
+    pub fn response_head(&self, router: &Router) -> ResponseHead {
+        ResponseHead {
+            status_code: self.status_code(),
+            headers: match self {
+                RouteError::NotFound(_) => vec![],
+                RouteError::MethodNotAllowed { allowed, .. } => vec![Header {
+                    key: Cow::Borrowed(ALLOW),
+                    value: Cow::Borrowed(allowed),
+                }],
+            },
+            version: Version::maximum_supported_version(),
+            protocol_mode: router.protocol_mode,
+        }
+    }
+}
+
+/// Routes the requests to the services and handlers registered in the [`Endpoint`].
+pub struct Router {
+    protocol_mode: ProtocolMode,
+    handlers: HashMap<String, HashSet<String>>,
+}
+
+impl Router {
+    pub fn new(endpoint: &Endpoint) -> Self {
+        Self {
+            protocol_mode: endpoint.protocol_mode.unwrap_or_default(),
+            handlers: endpoint
+                .services
+                .iter()
+                .map(|s| {
+                    (
+                        s.name.clone(),
+                        s.handlers.iter().map(|h| h.name.clone()).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn route(&self, method: &str, path: &str) -> Result<Route, RouteError> {
+        // The query string is not part of the route
+        let path = path.split_once('?').map_or(path, |(path, _)| path);
+        let path = normalise_path(path);
+
+        if path == "/discover" {
+            return check_method(method, "GET").map(|_| Route::Discover);
+        }
+
+        let mut segments = path.split('/').skip(1);
+        if let (Some("invoke"), Some(service), Some(handler), None) = (
+            segments.next(),
+            segments.next(),
+            segments.next(),
+            segments.next(),
+        ) {
+            if self
+                .handlers
+                .get(service)
+                .is_some_and(|handlers| handlers.contains(handler))
+            {
+                return check_method(method, "POST").map(|_| Route::Invoke {
+                    service: service.to_owned(),
+                    handler: handler.to_owned(),
+                });
+            }
+        }
+
+        Err(RouteError::NotFound(path.to_owned()))
+    }
+}
+
+fn check_method(method: &str, allowed: &'static str) -> Result<(), RouteError> {
+    if method.eq_ignore_ascii_case(allowed) {
+        Ok(())
+    } else {
+        Err(RouteError::MethodNotAllowed {
+            method: method.to_owned(),
+            allowed,
+        })
+    }
+}
+
+/// Strips any prefix before `/invoke/{service}/{handler}` or `/discover`.
+pub(crate) fn normalise_path(path: &str) -> &str {
+    let slashes: Vec<usize> = path.match_indices('/').map(|(index, _)| index).collect();
+    if slashes.len() >= 3
+        && &path[slashes[slashes.len() - 3]..slashes[slashes.len() - 2]] == "/invoke"
+    {
+        &path[slashes[slashes.len() - 3]..]
+    } else if !slashes.is_empty() && &path[slashes[slashes.len() - 1]..] == "/discover" {
+        &path[slashes[slashes.len() - 1]..]
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::manifest::{Handler, Service, ServiceType};
+
+    fn router() -> Router {
+        Router::new(&Endpoint::new(ProtocolMode::BidiStream).with_service(
+            Service::new("Greeter", ServiceType::Service).with_handler(Handler::new("greet")),
+        ))
+    }
+
+    #[test]
+    fn route() {
+        let router = router();
+
+        let requests = vec![
+            ("GET", "/discover", Ok(Route::Discover)),
+            ("GET", "/my/prefix/discover", Ok(Route::Discover)),
+            (
+                "POST",
+                "/invoke/Greeter/greet",
+                Ok(Route::Invoke {
+                    service: "Greeter".to_owned(),
+                    handler: "greet".to_owned(),
+                }),
+            ),
+            (
+                "post",
+                "/my/prefix/invoke/Greeter/greet",
+                Ok(Route::Invoke {
+                    service: "Greeter".to_owned(),
+                    handler: "greet".to_owned(),
+                }),
+            ),
+            ("GET", "/discover?foo=bar", Ok(Route::Discover)),
+            (
+                "POST",
+                "/my/prefix/invoke/Greeter/greet?x=1&y=/invoke/a/b",
+                Ok(Route::Invoke {
+                    service: "Greeter".to_owned(),
+                    handler: "greet".to_owned(),
+                }),
+            ),
+            (
+                "POST",
+                "/invoke/Greeter/unknown?x=1",
+                Err(RouteError::NotFound("/invoke/Greeter/unknown".to_owned())),
+            ),
+            (
+                "POST",
+                "/invoke/Greeter/unknown",
+                Err(RouteError::NotFound("/invoke/Greeter/unknown".to_owned())),
+            ),
+            (
+                "POST",
+                "/invoke/Unknown/greet",
+                Err(RouteError::NotFound("/invoke/Unknown/greet".to_owned())),
+            ),
+            (
+                "POST",
+                "/invoke/Greeter",
+                Err(RouteError::NotFound("/invoke/Greeter".to_owned())),
+            ),
+            (
+                "POST",
+                "/invoke/Greeter/greet/",
+                Err(RouteError::NotFound("/invoke/Greeter/greet/".to_owned())),
+            ),
+            ("GET", "/", Err(RouteError::NotFound("/".to_owned()))),
+            (
+                "POST",
+                "/discover",
+                Err(RouteError::MethodNotAllowed {
+                    method: "POST".to_owned(),
+                    allowed: "GET",
+                }),
+            ),
+            (
+                "GET",
+                "/invoke/Greeter/greet",
+                Err(RouteError::MethodNotAllowed {
+                    method: "GET".to_owned(),
+                    allowed: "POST",
+                }),
+            ),
+        ];
+
+        for (method, path, expected) in requests {
+            assert_eq!(router.route(method, path), expected, "{method} {path}");
+        }
+    }
+
+    #[test]
+    fn response_head() {
+        let router = router();
+
+        let not_found = router
+            .route("GET", "/foo")
+            .unwrap_err()
+            .response_head(&router);
+        assert_eq!(not_found.status_code, 404);
+        assert!(not_found.headers.is_empty());
+
+        let method_not_allowed = router
+            .route("GET", "/invoke/Greeter/greet")
+            .unwrap_err()
+            .response_head(&router);
+        assert_eq!(method_not_allowed.status_code, 405);
+        assert_eq!(
+            method_not_allowed.headers,
+            vec![Header {
+                key: Cow::Borrowed("allow"),
+                value: Cow::Borrowed("POST")
+            }]
+        );
+    }
+
+    #[test]
+    fn normalise_path() {
+        let paths = vec![
+            ("/invoke/a/b", "/invoke/a/b"),
+            ("/foo/invoke/a/b", "/invoke/a/b"),
+            ("/foo/bar/invoke/a/b", "/invoke/a/b"),
+            ("/discover", "/discover"),
+            ("/foo/discover", "/discover"),
+            ("/foo/bar/discover", "/discover"),
+            ("/foo", "/foo"),
+            ("/invoke", "/invoke"),
+            ("/foo/invoke", "/foo/invoke"),
+            ("/invoke/a", "/invoke/a"),
+            ("/foo/invoke/a", "/foo/invoke/a"),
+            ("", ""),
+            ("/", "/"),
+            ("//", "//"),
+            ("///", "///"),
+            ("////", "////"),
+            ("discover", "discover"),
+            ("foo/discover", "/discover"),
+            ("foo/invoke/a/b", "/invoke/a/b"),
+        ];
+
+        for (path, expected_path) in paths {
+            let actual_path = super::normalise_path(path);
+            assert_eq!(expected_path, actual_path)
+        }
+    }
+}
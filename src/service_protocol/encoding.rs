@@ -28,6 +28,14 @@ pub enum DecodingError {
     },
     #[error(transparent)]
     UnknownMessageType(#[from] UnknownMessageType),
+    #[error("message {ty:?} of {size} bytes exceeds the maximum message size of {limit} bytes")]
+    MessageTooLarge {
+        ty: MessageType,
+        size: usize,
+        limit: usize,
+    },
+    #[error("buffered {size} bytes of incomplete messages, exceeding the limit of {limit} bytes")]
+    BufferLimitExceeded { size: usize, limit: usize },
 }
 
 // --- Input protocol.message encoder
@@ -36,10 +44,11 @@ pub struct Encoder {}
 
 impl Encoder {
     pub fn new(service_protocol_version: Version) -> Self {
-        assert_eq!(
-            service_protocol_version,
-            Version::maximum_supported_version(),
-            "Encoder only supports service protocol version {:?}",
+        assert!(
+            service_protocol_version >= Version::minimum_supported_version()
+                && service_protocol_version <= Version::maximum_supported_version(),
+            "Encoder only supports service protocol version [{:?} to {:?}]",
+            Version::minimum_supported_version(),
             Version::maximum_supported_version()
         );
         Self {}
@@ -86,6 +95,14 @@ impl RawMessage {
         self.0.message_type()
     }
 
+    pub fn header(&self) -> MessageHeader {
+        self.0
+    }
+
+    pub fn payload(&self) -> &Bytes {
+        &self.1
+    }
+
     pub fn decode_to<M: RestateMessage>(self) -> Result<M, DecodingError> {
         if self.0.message_type() != M::ty() {
             return Err(DecodingError::UnexpectedMessageType {
@@ -101,39 +118,84 @@ impl RawMessage {
 pub struct Decoder {
     buf: SegmentedBuf<Bytes>,
     state: DecoderState,
+    max_message_size: Option<usize>,
+    max_buffered_bytes: Option<usize>,
 }
 
 impl Decoder {
     pub fn new(service_protocol_version: Version) -> Self {
-        assert_eq!(
-            service_protocol_version,
-            Version::maximum_supported_version(),
-            "Decoder only supports service protocol version {:?}",
+        assert!(
+            service_protocol_version >= Version::minimum_supported_version()
+                && service_protocol_version <= Version::maximum_supported_version(),
+            "Decoder only supports service protocol version [{:?} to {:?}]",
+            Version::minimum_supported_version(),
             Version::maximum_supported_version()
         );
         Self {
             buf: SegmentedBuf::new(),
             state: DecoderState::WaitingHeader,
+            max_message_size: None,
+            max_buffered_bytes: None,
         }
     }
 
+    /// Fail decoding messages whose payload is larger than `max_message_size` bytes.
+    pub fn with_max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Fail when more than `max_buffered_bytes` bytes are buffered without completing a message, as reported by [`Decoder::buffered_bytes`].
+    pub fn with_max_buffered_bytes(mut self, max_buffered_bytes: Option<usize>) -> Self {
+        self.max_buffered_bytes = max_buffered_bytes;
+        self
+    }
+
     /// Concatenate a new chunk in the internal buffer.
     pub fn push(&mut self, buf: Bytes) {
         self.buf.push(buf)
     }
 
+    /// Number of bytes of an incomplete message consumed so far.
+    pub fn buffered_bytes(&self) -> usize {
+        let header_len = match self.state {
+            DecoderState::WaitingHeader => 0,
+            DecoderState::WaitingPayload(_) => 8,
+        };
+        header_len + self.buf.remaining()
+    }
+
     /// Try to consume the next protocol.message in the internal buffer.
     pub fn consume_next(&mut self) -> Result<Option<RawMessage>, DecodingError> {
         loop {
             let remaining = self.buf.remaining();
 
             if remaining < self.state.needs_bytes() {
-                return Ok(None);
+                let buffered = self.buffered_bytes();
+                return match self.max_buffered_bytes {
+                    Some(limit) if buffered > limit => Err(DecodingError::BufferLimitExceeded {
+                        size: buffered,
+                        limit,
+                    }),
+                    _ => Ok(None),
+                };
             }
 
             if let Some(res) = self.state.decode(&mut self.buf)? {
                 return Ok(Some(res));
             }
+
+            if let (DecoderState::WaitingPayload(h), Some(limit)) =
+                (&self.state, self.max_message_size)
+            {
+                if h.frame_length() as usize > limit {
+                    return Err(DecodingError::MessageTooLarge {
+                        ty: h.message_type(),
+                        size: h.frame_length() as usize,
+                        limit,
+                    });
+                }
+            }
         }
     }
 }
@@ -177,12 +239,6 @@ mod tests {
 
     use super::*;
 
-    impl RawMessage {
-        pub fn header(&self) -> MessageHeader {
-            self.0
-        }
-    }
-
     #[test]
     fn fill_decoder_with_several_messages() {
         let encoder = Encoder::new(Version::maximum_supported_version());
@@ -286,4 +342,58 @@ mod tests {
 
         assert!(decoder.consume_next().unwrap().is_none());
     }
+
+    #[test]
+    fn decoder_rejects_message_larger_than_max_message_size() {
+        let encoder = Encoder::new(Version::maximum_supported_version());
+        let mut decoder =
+            Decoder::new(Version::maximum_supported_version()).with_max_message_size(Some(4));
+
+        let msg = encoder.encode(&messages::InputEntryMessage {
+            value: Bytes::from_static("input".as_bytes()),
+            ..messages::InputEntryMessage::default()
+        });
+        // The header is enough to reject the message
+        decoder.push(msg.slice(0..8));
+
+        assert!(matches!(
+            decoder.consume_next(),
+            Err(DecodingError::MessageTooLarge {
+                ty: MessageType::InputEntry,
+                limit: 4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn decoder_rejects_incomplete_messages_larger_than_max_buffered_bytes() {
+        let encoder = Encoder::new(Version::maximum_supported_version());
+        let mut decoder =
+            Decoder::new(Version::maximum_supported_version()).with_max_buffered_bytes(Some(10));
+
+        let msg = encoder.encode(&messages::InputEntryMessage {
+            value: Bytes::from_static("input".as_bytes()),
+            ..messages::InputEntryMessage::default()
+        });
+
+        // Complete messages don't count against the limit
+        decoder.push(msg.clone());
+        decoder.push(msg.clone());
+        assert!(decoder.consume_next().unwrap().is_some());
+        assert!(decoder.consume_next().unwrap().is_some());
+
+        // The consumed header counts against the limit too
+        decoder.push(msg.slice(0..10));
+        assert!(decoder.consume_next().unwrap().is_none());
+        assert_eq!(decoder.buffered_bytes(), 10);
+        decoder.push(msg.slice(10..11));
+        assert!(matches!(
+            decoder.consume_next(),
+            Err(DecodingError::BufferLimitExceeded {
+                size: 11,
+                limit: 10
+            })
+        ));
+    }
 }
@@ -1,5 +1,7 @@
 // This file is @generated by prost-build.
 /// Type: 0xFC00 + 2
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CombinatorEntryMessage {
     #[prost(uint32, repeated, tag = "1")]
@@ -1,9 +1,12 @@
 // This file is @generated by prost-build.
 /// Type: 0x0000 + 0
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct StartMessage {
     /// Unique id of the invocation. This id is unique across invocations and won't change when replaying the journal.
     #[prost(bytes = "bytes", tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::service_protocol::serde_base64"))]
     pub id: ::prost::bytes::Bytes,
     /// Invocation id that can be used for logging.
     /// The user can use this id to address this invocation in admin and status introspection apis.
@@ -34,17 +37,29 @@ pub struct StartMessage {
 }
 /// Nested message and enum types in `StartMessage`.
 pub mod start_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(default))]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct StateEntry {
         #[prost(bytes = "bytes", tag = "1")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         pub key: ::prost::bytes::Bytes,
         /// If value is an empty byte array,
         /// then it means the value is empty and not "missing" (e.g. empty string).
         #[prost(bytes = "bytes", tag = "2")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         pub value: ::prost::bytes::Bytes,
     }
 }
 /// Type: 0x0000 + 1
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CompletionMessage {
     #[prost(uint32, tag = "1")]
@@ -54,11 +69,16 @@ pub struct CompletionMessage {
 }
 /// Nested message and enum types in `CompletionMessage`.
 pub mod completion_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(message, tag = "13")]
         Empty(super::Empty),
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -66,6 +86,8 @@ pub mod completion_message {
 }
 /// Type: 0x0000 + 2
 /// Implementations MUST send this message when suspending an invocation.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SuspensionMessage {
     /// This list represents any of the entry_index the invocation is waiting on to progress.
@@ -79,6 +101,8 @@ pub struct SuspensionMessage {
     pub entry_indexes: ::prost::alloc::vec::Vec<u32>,
 }
 /// Type: 0x0000 + 3
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ErrorMessage {
     /// The code can be any HTTP status code, as described <https://www.iana.org/assignments/http-status-codes/http-status-codes.xhtml.>
@@ -109,6 +133,8 @@ pub struct ErrorMessage {
     pub next_retry_delay: ::core::option::Option<u64>,
 }
 /// Type: 0x0000 + 4
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct EntryAckMessage {
     #[prost(uint32, tag = "1")]
@@ -116,16 +142,21 @@ pub struct EntryAckMessage {
 }
 /// Type: 0x0000 + 5
 /// Implementations MUST send this message when the invocation lifecycle ends.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct EndMessage {}
 /// Completable: No
 /// Fallible: No
 /// Type: 0x0400 + 0
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct InputEntryMessage {
     #[prost(message, repeated, tag = "1")]
     pub headers: ::prost::alloc::vec::Vec<Header>,
     #[prost(bytes = "bytes", tag = "14")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::service_protocol::serde_base64"))]
     pub value: ::prost::bytes::Bytes,
     /// Entry name
     #[prost(string, tag = "12")]
@@ -134,6 +165,8 @@ pub struct InputEntryMessage {
 /// Completable: No
 /// Fallible: No
 /// Type: 0x0400 + 1
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OutputEntryMessage {
     /// Entry name
@@ -144,9 +177,14 @@ pub struct OutputEntryMessage {
 }
 /// Nested message and enum types in `OutputEntryMessage`.
 pub mod output_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -155,9 +193,12 @@ pub mod output_entry_message {
 /// Completable: Yes
 /// Fallible: No
 /// Type: 0x0800 + 0
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetStateEntryMessage {
     #[prost(bytes = "bytes", tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::service_protocol::serde_base64"))]
     pub key: ::prost::bytes::Bytes,
     /// Entry name
     #[prost(string, tag = "12")]
@@ -167,11 +208,16 @@ pub struct GetStateEntryMessage {
 }
 /// Nested message and enum types in `GetStateEntryMessage`.
 pub mod get_state_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(message, tag = "13")]
         Empty(super::Empty),
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -180,11 +226,15 @@ pub mod get_state_entry_message {
 /// Completable: No
 /// Fallible: No
 /// Type: 0x0800 + 1
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SetStateEntryMessage {
     #[prost(bytes = "bytes", tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::service_protocol::serde_base64"))]
     pub key: ::prost::bytes::Bytes,
     #[prost(bytes = "bytes", tag = "3")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::service_protocol::serde_base64"))]
     pub value: ::prost::bytes::Bytes,
     /// Entry name
     #[prost(string, tag = "12")]
@@ -193,9 +243,12 @@ pub struct SetStateEntryMessage {
 /// Completable: No
 /// Fallible: No
 /// Type: 0x0800 + 2
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ClearStateEntryMessage {
     #[prost(bytes = "bytes", tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::service_protocol::serde_base64"))]
     pub key: ::prost::bytes::Bytes,
     /// Entry name
     #[prost(string, tag = "12")]
@@ -204,6 +257,8 @@ pub struct ClearStateEntryMessage {
 /// Completable: No
 /// Fallible: No
 /// Type: 0x0800 + 3
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ClearAllStateEntryMessage {
     /// Entry name
@@ -213,6 +268,8 @@ pub struct ClearAllStateEntryMessage {
 /// Completable: Yes
 /// Fallible: No
 /// Type: 0x0800 + 4
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetStateKeysEntryMessage {
     /// Entry name
@@ -223,11 +280,18 @@ pub struct GetStateKeysEntryMessage {
 }
 /// Nested message and enum types in `GetStateKeysEntryMessage`.
 pub mod get_state_keys_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(default))]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct StateKeys {
         #[prost(bytes = "bytes", repeated, tag = "1")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64::repeated")
+        )]
         pub keys: ::prost::alloc::vec::Vec<::prost::bytes::Bytes>,
     }
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(message, tag = "14")]
@@ -239,6 +303,8 @@ pub mod get_state_keys_entry_message {
 /// Completable: Yes
 /// Fallible: No
 /// Type: 0x0800 + 8
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetPromiseEntryMessage {
     #[prost(string, tag = "1")]
@@ -251,9 +317,14 @@ pub struct GetPromiseEntryMessage {
 }
 /// Nested message and enum types in `GetPromiseEntryMessage`.
 pub mod get_promise_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -262,6 +333,8 @@ pub mod get_promise_entry_message {
 /// Completable: Yes
 /// Fallible: No
 /// Type: 0x0800 + 9
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PeekPromiseEntryMessage {
     #[prost(string, tag = "1")]
@@ -274,11 +347,16 @@ pub struct PeekPromiseEntryMessage {
 }
 /// Nested message and enum types in `PeekPromiseEntryMessage`.
 pub mod peek_promise_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(message, tag = "13")]
         Empty(super::Empty),
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -287,6 +365,8 @@ pub mod peek_promise_entry_message {
 /// Completable: Yes
 /// Fallible: No
 /// Type: 0x0800 + A
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CompletePromiseEntryMessage {
     #[prost(string, tag = "1")]
@@ -303,13 +383,19 @@ pub struct CompletePromiseEntryMessage {
 /// Nested message and enum types in `CompletePromiseEntryMessage`.
 pub mod complete_promise_entry_message {
     /// The value to use to complete the promise
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Completion {
         #[prost(bytes, tag = "2")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         CompletionValue(::prost::bytes::Bytes),
         #[prost(message, tag = "3")]
         CompletionFailure(super::Failure),
     }
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         /// Returns empty if value was set successfully
@@ -323,6 +409,8 @@ pub mod complete_promise_entry_message {
 /// Completable: Yes
 /// Fallible: No
 /// Type: 0x0C00 + 0
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SleepEntryMessage {
     /// Wake up time.
@@ -337,6 +425,7 @@ pub struct SleepEntryMessage {
 }
 /// Nested message and enum types in `SleepEntryMessage`.
 pub mod sleep_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(message, tag = "13")]
@@ -348,6 +437,8 @@ pub mod sleep_entry_message {
 /// Completable: Yes
 /// Fallible: Yes
 /// Type: 0x0C00 + 1
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CallEntryMessage {
     #[prost(string, tag = "1")]
@@ -355,6 +446,7 @@ pub struct CallEntryMessage {
     #[prost(string, tag = "2")]
     pub handler_name: ::prost::alloc::string::String,
     #[prost(bytes = "bytes", tag = "3")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::service_protocol::serde_base64"))]
     pub parameter: ::prost::bytes::Bytes,
     #[prost(message, repeated, tag = "4")]
     pub headers: ::prost::alloc::vec::Vec<Header>,
@@ -372,9 +464,14 @@ pub struct CallEntryMessage {
 }
 /// Nested message and enum types in `CallEntryMessage`.
 pub mod call_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -383,6 +480,8 @@ pub mod call_entry_message {
 /// Completable: No
 /// Fallible: Yes
 /// Type: 0x0C00 + 2
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OneWayCallEntryMessage {
     #[prost(string, tag = "1")]
@@ -390,6 +489,7 @@ pub struct OneWayCallEntryMessage {
     #[prost(string, tag = "2")]
     pub handler_name: ::prost::alloc::string::String,
     #[prost(bytes = "bytes", tag = "3")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::service_protocol::serde_base64"))]
     pub parameter: ::prost::bytes::Bytes,
     /// Time when this BackgroundInvoke should be executed.
     /// The time is set as duration since UNIX Epoch.
@@ -413,6 +513,8 @@ pub struct OneWayCallEntryMessage {
 /// Fallible: No
 /// Type: 0x0C00 + 3
 /// Awakeables are addressed by an identifier exposed to the user. See the spec for more details.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AwakeableEntryMessage {
     /// Entry name
@@ -423,9 +525,14 @@ pub struct AwakeableEntryMessage {
 }
 /// Nested message and enum types in `AwakeableEntryMessage`.
 pub mod awakeable_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -434,6 +541,8 @@ pub mod awakeable_entry_message {
 /// Completable: No
 /// Fallible: Yes
 /// Type: 0x0C00 + 4
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CompleteAwakeableEntryMessage {
     /// Identifier of the awakeable. See the spec for more details.
@@ -447,9 +556,14 @@ pub struct CompleteAwakeableEntryMessage {
 }
 /// Nested message and enum types in `CompleteAwakeableEntryMessage`.
 pub mod complete_awakeable_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -459,6 +573,8 @@ pub mod complete_awakeable_entry_message {
 /// Fallible: No
 /// Type: 0x0C00 + 5
 /// Flag: RequiresRuntimeAck
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RunEntryMessage {
     /// Entry name
@@ -469,9 +585,14 @@ pub struct RunEntryMessage {
 }
 /// Nested message and enum types in `RunEntryMessage`.
 pub mod run_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(bytes, tag = "14")]
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::service_protocol::serde_base64")
+        )]
         Value(::prost::bytes::Bytes),
         #[prost(message, tag = "15")]
         Failure(super::Failure),
@@ -480,6 +601,8 @@ pub mod run_entry_message {
 /// Completable: No
 /// Fallible: Yes
 /// Type: 0x0C00 + 6
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CancelInvocationEntryMessage {
     /// Entry name
@@ -490,6 +613,7 @@ pub struct CancelInvocationEntryMessage {
 }
 /// Nested message and enum types in `CancelInvocationEntryMessage`.
 pub mod cancel_invocation_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Target {
         /// Target invocation id to cancel
@@ -503,6 +627,8 @@ pub mod cancel_invocation_entry_message {
 /// Completable: Yes
 /// Fallible: Yes
 /// Type: 0x0C00 + 7
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetCallInvocationIdEntryMessage {
     /// Index of the call/one way call journal entry in this journal.
@@ -515,6 +641,7 @@ pub struct GetCallInvocationIdEntryMessage {
 }
 /// Nested message and enum types in `GetCallInvocationIdEntryMessage`.
 pub mod get_call_invocation_id_entry_message {
+    #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Result {
         #[prost(string, tag = "14")]
@@ -525,6 +652,8 @@ pub mod get_call_invocation_id_entry_message {
 }
 /// This failure object carries user visible errors,
 /// e.g. invocation failure return value or failure result of an InvokeEntryMessage.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Failure {
     /// The code can be any HTTP status code, as described <https://www.iana.org/assignments/http-status-codes/http-status-codes.xhtml.>
@@ -534,6 +663,8 @@ pub struct Failure {
     #[prost(string, tag = "2")]
     pub message: ::prost::alloc::string::String,
 }
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Header {
     #[prost(string, tag = "1")]
@@ -541,9 +672,12 @@ pub struct Header {
     #[prost(string, tag = "2")]
     pub value: ::prost::alloc::string::String,
 }
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct Empty {}
 /// Service protocol version.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum ServiceProtocolVersion {
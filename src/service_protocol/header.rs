@@ -126,7 +126,7 @@ gen_message_type_enum!(
 );
 
 impl MessageType {
-    fn has_completed_flag(&self) -> bool {
+    pub(crate) fn has_completed_flag(&self) -> bool {
         matches!(
             self,
             MessageType::GetStateEntry
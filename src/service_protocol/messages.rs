@@ -2,7 +2,7 @@ use crate::service_protocol::messages::get_state_keys_entry_message::StateKeys;
 use crate::service_protocol::{MessageHeader, MessageType};
 use crate::vm::errors::{
     DecodeGetCallInvocationIdUtf8, DecodeStateKeysProst, DecodeStateKeysUtf8,
-    EmptyGetCallInvocationId, EmptyStateKeys,
+    EmptyGetCallInvocationId, EmptyStateKeys, EntryFieldMismatch,
 };
 use crate::{Error, NonEmptyValue, Value};
 use paste::paste;
@@ -24,6 +24,9 @@ pub trait EntryMessage {
 
 pub trait EntryMessageHeaderEq {
     fn header_eq(&self, other: &Self) -> bool;
+
+    /// Fields compared by [`EntryMessageHeaderEq::header_eq`] which differ, used to describe journal mismatches.
+    fn header_diff(&self, other: &Self) -> Vec<EntryFieldMismatch>;
 }
 
 pub trait CompletableEntryMessage: RestateMessage + EntryMessage + EntryMessageHeaderEq {
@@ -50,11 +53,11 @@ macro_rules! impl_message_traits {
         impl_message_traits!($name: message);
         impl_message_traits!($name: writeable);
     };
-    ($name:ident: non_completable_entry) => {
+    ($name:ident: non_completable_entry [$($field:ident),*]) => {
         impl_message_traits!($name: message);
         impl_message_traits!($name: writeable);
         impl_message_traits!($name: entry);
-        impl_message_traits!($name: entry_header_eq);
+        impl_message_traits!($name: entry_header_eq [$($field),*]);
     };
     ($name:ident: completable_entry) => {
         impl_message_traits!($name: message);
@@ -93,10 +96,20 @@ macro_rules! impl_message_traits {
             }
         }
     };
-    ($name:ident: entry_header_eq) => {
+    ($name:ident: entry_header_eq [$($field:ident),*]) => {
         impl EntryMessageHeaderEq for paste! { [<$name Message>] } {
             fn header_eq(&self, other: &Self) -> bool {
-                self.eq(other)
+                true $(&& self.$field == other.$field)*
+            }
+
+            fn header_diff(&self, other: &Self) -> Vec<EntryFieldMismatch> {
+                let mut diff = vec![];
+                $(
+                    if self.$field != other.$field {
+                        diff.push(EntryFieldMismatch::new(stringify!($field), &self.$field, &other.$field));
+                    }
+                )*
+                diff
             }
         }
     };
@@ -118,16 +131,16 @@ impl EntryMessageHeaderEq for InputEntryMessage {
     fn header_eq(&self, _: &Self) -> bool {
         true
     }
+
+    fn header_diff(&self, _: &Self) -> Vec<EntryFieldMismatch> {
+        vec![]
+    }
 }
 
-impl_message_traits!(OutputEntry: non_completable_entry);
+impl_message_traits!(OutputEntry: non_completable_entry [name, result]);
 
 impl_message_traits!(GetStateEntry: completable_entry);
-impl EntryMessageHeaderEq for GetStateEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.key.eq(&other.key)
-    }
-}
+impl_message_traits!(GetStateEntry: entry_header_eq [key, name]);
 
 impl_message_traits!(GetStateKeysEntry: message);
 impl_message_traits!(GetStateKeysEntry: entry);
@@ -144,80 +157,38 @@ impl CompletableEntryMessage for GetStateKeysEntryMessage {
         CompletionParsingHint::StateKeys
     }
 }
-impl EntryMessageHeaderEq for GetStateKeysEntryMessage {
-    fn header_eq(&self, _: &Self) -> bool {
-        true
-    }
-}
+impl_message_traits!(GetStateKeysEntry: entry_header_eq [name]);
 
-impl_message_traits!(SetStateEntry: non_completable_entry);
+impl_message_traits!(SetStateEntry: non_completable_entry [key, value, name]);
 
-impl_message_traits!(ClearStateEntry: non_completable_entry);
+impl_message_traits!(ClearStateEntry: non_completable_entry [key, name]);
 
-impl_message_traits!(ClearAllStateEntry: non_completable_entry);
+impl_message_traits!(ClearAllStateEntry: non_completable_entry [name]);
 
 impl_message_traits!(SleepEntry: completable_entry);
-impl EntryMessageHeaderEq for SleepEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
-}
+impl_message_traits!(SleepEntry: entry_header_eq [name]);
 
 impl_message_traits!(CallEntry: completable_entry);
-impl EntryMessageHeaderEq for CallEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.service_name == other.service_name
-            && self.handler_name == other.handler_name
-            && self.key == other.key
-            && self.headers == other.headers
-            && self.parameter == other.parameter
-            && self.name == other.name
-    }
-}
+impl_message_traits!(CallEntry: entry_header_eq [service_name, handler_name, key, headers, parameter, name]);
 
 impl_message_traits!(OneWayCallEntry: message);
 impl_message_traits!(OneWayCallEntry: writeable);
 impl_message_traits!(OneWayCallEntry: entry);
-impl EntryMessageHeaderEq for OneWayCallEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.service_name == other.service_name
-            && self.handler_name == other.handler_name
-            && self.key == other.key
-            && self.headers == other.headers
-            && self.parameter == other.parameter
-            && self.name == other.name
-    }
-}
+impl_message_traits!(OneWayCallEntry: entry_header_eq [service_name, handler_name, key, headers, parameter, name]);
 
 impl_message_traits!(AwakeableEntry: completable_entry);
-impl EntryMessageHeaderEq for AwakeableEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
-}
+impl_message_traits!(AwakeableEntry: entry_header_eq [name]);
 
-impl_message_traits!(CompleteAwakeableEntry: non_completable_entry);
+impl_message_traits!(CompleteAwakeableEntry: non_completable_entry [id, name, result]);
 
 impl_message_traits!(GetPromiseEntry: completable_entry);
-impl EntryMessageHeaderEq for GetPromiseEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.key == other.key && self.name == other.name
-    }
-}
+impl_message_traits!(GetPromiseEntry: entry_header_eq [key, name]);
 
 impl_message_traits!(PeekPromiseEntry: completable_entry);
-impl EntryMessageHeaderEq for PeekPromiseEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.key == other.key && self.name == other.name
-    }
-}
+impl_message_traits!(PeekPromiseEntry: entry_header_eq [key, name]);
 
 impl_message_traits!(CompletePromiseEntry: completable_entry);
-impl EntryMessageHeaderEq for CompletePromiseEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.key == other.key && self.completion == other.completion && self.name == other.name
-    }
-}
+impl_message_traits!(CompletePromiseEntry: entry_header_eq [key, completion, name]);
 
 impl_message_traits!(RunEntry: message);
 impl_message_traits!(RunEntry: entry);
@@ -231,13 +202,9 @@ impl WriteableRestateMessage for RunEntryMessage {
         )
     }
 }
-impl EntryMessageHeaderEq for RunEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
-}
+impl_message_traits!(RunEntry: entry_header_eq [name]);
 
-impl_message_traits!(CancelInvocationEntry: non_completable_entry);
+impl_message_traits!(CancelInvocationEntry: non_completable_entry [name, target]);
 
 impl_message_traits!(GetCallInvocationIdEntry: message);
 impl_message_traits!(GetCallInvocationIdEntry: entry);
@@ -254,11 +221,7 @@ impl CompletableEntryMessage for GetCallInvocationIdEntryMessage {
         CompletionParsingHint::GetCompletionId
     }
 }
-impl EntryMessageHeaderEq for GetCallInvocationIdEntryMessage {
-    fn header_eq(&self, other: &Self) -> bool {
-        self.call_entry_index == other.call_entry_index
-    }
-}
+impl_message_traits!(GetCallInvocationIdEntry: entry_header_eq [call_entry_index, name]);
 
 impl_message_traits!(CombinatorEntry: message);
 impl_message_traits!(CombinatorEntry: entry);
@@ -276,6 +239,10 @@ impl EntryMessageHeaderEq for CombinatorEntryMessage {
     fn header_eq(&self, _: &Self) -> bool {
         true
     }
+
+    fn header_diff(&self, _: &Self) -> Vec<EntryFieldMismatch> {
+        vec![]
+    }
 }
 
 // --- Completion extraction
@@ -421,7 +388,7 @@ impl From<Failure> for crate::TerminalFailure {
 // --- Completion parsing
 
 #[derive(Debug)]
-pub(crate) enum CompletionParsingHint {
+pub enum CompletionParsingHint {
     StateKeys,
     GetCompletionId,
     /// The normal case
@@ -11,6 +11,8 @@
 mod encoding;
 mod header;
 pub mod messages;
+#[cfg(feature = "serde")]
+mod serde_base64;
 mod version;
 
 pub use encoding::{Decoder, DecodingError, Encoder, RawMessage};
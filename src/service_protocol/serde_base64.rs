@@ -0,0 +1,49 @@
+//! Serde helpers to represent protocol `bytes` fields as base64 strings, used by the generated messages.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub(crate) fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&STANDARD.encode(bytes))
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    STANDARD
+        .decode(s)
+        .map(Bytes::from)
+        .map_err(serde::de::Error::custom)
+}
+
+pub(crate) mod repeated {
+    use super::*;
+
+    use serde::ser::SerializeSeq;
+
+    pub(crate) fn serialize<S: Serializer>(
+        values: &[Bytes],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for v in values {
+            seq.serialize_element(&STANDARD.encode(v))?;
+        }
+        seq.end()
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Bytes>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|s| {
+                STANDARD
+                    .decode(s)
+                    .map(Bytes::from)
+                    .map_err(serde::de::Error::custom)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,91 @@
+//! Read-only snapshot of the [`CoreVM`](crate::CoreVM) internals, see [`CoreVM::snapshot`](crate::CoreVM::snapshot).
+//!
+//! The snapshot is meant for debugging, e.g. to dump the journal when a journal mismatch happens.
+//! Its content is not part of the stable API, and it might change between releases.
+
+use crate::error::EntryMismatchError;
+use crate::service_protocol::RawMessage;
+use crate::{Error, Value, Version};
+use bytes::Bytes;
+use prost::Message;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone)]
+pub struct VMSnapshot {
+    pub version: Version,
+    pub invocation_id: Option<String>,
+    /// Name of the current state, or `Errored` if the VM failed.
+    pub state: &'static str,
+    pub error: Option<Error>,
+    /// Index of the last entry processed by the VM, `-1` if none.
+    pub journal_index: i64,
+    /// Type of the last entry processed by the VM.
+    pub current_entry_type: String,
+    /// Name of the last entry processed by the VM.
+    pub current_entry_name: String,
+    /// Entries received from the runtime and replayed already.
+    pub replayed_entries: Vec<EntrySnapshot>,
+    /// Entries received from the runtime, not replayed yet.
+    pub entries_to_replay: Vec<EntrySnapshot>,
+    /// `None` if the VM is not executing. If the VM failed, the async results at the time of the failure.
+    pub async_results: Option<AsyncResultsSnapshot>,
+    pub eager_state: EagerStateSnapshot,
+    /// Set if the VM failed because of a journal mismatch.
+    pub journal_mismatch: Option<EntryMismatchError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntrySnapshot {
+    pub index: u32,
+    pub ty: String,
+    pub name: String,
+    /// `None` if the entry is not completable.
+    pub completed: Option<bool>,
+    /// Protobuf encoded entry message.
+    pub payload: Bytes,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AsyncResultsSnapshot {
+    /// Results ready to be taken, by entry index.
+    pub ready_results: BTreeMap<u32, Value>,
+    /// Entries waiting for their completion.
+    pub waiting_completion: Vec<u32>,
+    /// Completions received before their entry was processed.
+    pub unparsed_completions: Vec<u32>,
+    /// Results of entries waiting for the ack of the runtime.
+    pub waiting_ack: Vec<u32>,
+    pub last_acked_entry: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EagerStateSnapshot {
+    /// If true, keys missing in `values` might still exist.
+    pub is_partial: bool,
+    /// `None` means the key was cleared.
+    pub values: BTreeMap<String, Option<Bytes>>,
+    /// Keys known to exist, whose value was not fetched yet.
+    pub unknown_values: BTreeSet<String>,
+}
+
+/// Every entry message defines its name with the same tag, so we can decode it without knowing the entry type.
+#[derive(Clone, PartialEq, Message)]
+struct EntryName {
+    #[prost(string, tag = "12")]
+    name: String,
+}
+
+impl EntrySnapshot {
+    pub(crate) fn from_raw_message(index: u32, msg: &RawMessage) -> Self {
+        let header = msg.header();
+        Self {
+            index,
+            ty: format!("{:?}", header.message_type()),
+            name: EntryName::decode(msg.payload().clone())
+                .map(|n| n.name)
+                .unwrap_or_default(),
+            completed: header.completed(),
+            payload: msg.payload().clone(),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Checks that the handler code replays deterministically.
+//!
+//! [`check_replay_determinism`] first runs the handler code to completion on a [`MockRuntime`], recording the journal.
+//! Then, for every prefix of the journal, it runs the handler code again on a new [`CoreVM`],
+//! replaying the prefix and delivering the recorded completions as described by each [`CompletionsDelivery`],
+//! and asserts the handler code emits exactly the remaining recorded entries, without failing.
+
+use super::mock_runtime::{collect_output, encode_raw_message, InvocationStatus, MockRuntime};
+use crate::service_protocol::messages::{CompletionMessage, EntryAckMessage, ErrorMessage};
+use crate::service_protocol::{Encoder, MessageType, RawMessage};
+use crate::{CoreVM, VM};
+use std::fmt;
+
+/// How the recorded completions and acks are delivered to the replaying VM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionsDelivery {
+    /// All the completions and acks after the replayed entries.
+    AfterReplayedEntries,
+    /// The completion or ack of each replayed entry right after it, the others after the replayed entries.
+    AfterEachReplayedEntry,
+    /// Only the completions and acks of the replayed entries, after them. The handler code might suspend.
+    OnlyReplayedEntries,
+}
+
+const ALL_DELIVERIES: [CompletionsDelivery; 3] = [
+    CompletionsDelivery::AfterReplayedEntries,
+    CompletionsDelivery::AfterEachReplayedEntry,
+    CompletionsDelivery::OnlyReplayedEntries,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViolationKind {
+    /// The VM failed, e.g. with `JOURNAL_MISMATCH`.
+    Failed(ErrorMessage),
+    /// The entry differs from the recorded one.
+    EntryMismatch {
+        entry_index: u32,
+        expected: RawMessage,
+        actual: RawMessage,
+    },
+    /// The entry was not recorded.
+    UnexpectedEntry {
+        entry_index: u32,
+        actual: RawMessage,
+    },
+    /// The VM ended, suspended or returned before emitting the recorded entry.
+    MissingEntry {
+        entry_index: u32,
+        expected: RawMessage,
+    },
+    /// The VM emitted an unexpected message, e.g. a suspension although all the completions were delivered.
+    UnexpectedMessage(MessageType),
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViolationKind::Failed(e) => write!(f, "failed with code {}: {}", e.code, e.message),
+            ViolationKind::EntryMismatch {
+                entry_index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "entry {entry_index} is {:?} but {:?} was recorded",
+                actual.ty(),
+                expected.ty()
+            ),
+            ViolationKind::UnexpectedEntry {
+                entry_index,
+                actual,
+            } => write!(f, "unexpected entry {entry_index} {:?}", actual.ty()),
+            ViolationKind::MissingEntry {
+                entry_index,
+                expected,
+            } => write!(f, "missing entry {entry_index} {:?}", expected.ty()),
+            ViolationKind::UnexpectedMessage(ty) => write!(f, "unexpected message {ty:?}"),
+        }
+    }
+}
+
+/// The handler code didn't replay the recorded journal.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("Replaying {known_entries} known entries, delivering completions {delivery:?}: {kind}")]
+pub struct DeterminismViolation {
+    pub known_entries: u32,
+    pub delivery: CompletionsDelivery,
+    pub kind: ViolationKind,
+}
+
+/// Checks the handler code replays deterministically, see the [module documentation](self).
+///
+/// The eager state of the `runtime` is disabled, so that every state access is recorded in the journal.
+/// Panics if the handler code doesn't end on the `runtime`.
+pub fn check_replay_determinism(
+    runtime: MockRuntime,
+    mut user_code: impl FnMut(&mut CoreVM),
+) -> Result<(), DeterminismViolation> {
+    let mut runtime = runtime.with_eager_state(false);
+    let status = runtime.run(&mut user_code);
+    assert!(
+        matches!(status, InvocationStatus::Ended { .. }),
+        "The handler code should end on the runtime, but got {status:?}"
+    );
+
+    for known_entries in 1..=runtime.journal().len() as u32 {
+        for delivery in ALL_DELIVERIES {
+            check_replay(&runtime, known_entries, delivery, &mut user_code).map_err(|kind| {
+                DeterminismViolation {
+                    known_entries,
+                    delivery,
+                    kind,
+                }
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn check_replay(
+    runtime: &MockRuntime,
+    known_entries: u32,
+    delivery: CompletionsDelivery,
+    user_code: &mut impl FnMut(&mut CoreVM),
+) -> Result<(), ViolationKind> {
+    let journal = runtime.journal();
+    let encoder = Encoder::new(runtime.version());
+    let completion = |entry_index: u32| {
+        runtime
+            .completions()
+            .get(&entry_index)
+            .map(|result| {
+                encoder.encode(&CompletionMessage {
+                    entry_index,
+                    result: Some(result.clone()),
+                })
+            })
+            .or_else(|| {
+                runtime
+                    .acks()
+                    .contains(&entry_index)
+                    .then(|| encoder.encode(&EntryAckMessage { entry_index }))
+            })
+    };
+
+    let mut vm = runtime.new_vm();
+    vm.notify_input(encoder.encode(&runtime.start_message(known_entries)));
+    for (entry_index, entry) in journal[..known_entries as usize].iter().enumerate() {
+        vm.notify_input(encode_raw_message(entry));
+        if delivery == CompletionsDelivery::AfterEachReplayedEntry {
+            if let Some(msg) = completion(entry_index as u32) {
+                vm.notify_input(msg);
+            }
+        }
+    }
+    let delivered_after = match delivery {
+        CompletionsDelivery::AfterReplayedEntries => 0..journal.len() as u32,
+        CompletionsDelivery::AfterEachReplayedEntry => known_entries..journal.len() as u32,
+        CompletionsDelivery::OnlyReplayedEntries => 0..known_entries,
+    };
+    for msg in delivered_after.filter_map(completion) {
+        vm.notify_input(msg);
+    }
+    vm.notify_input_closed();
+    assert!(vm.is_ready_to_execute().unwrap());
+
+    user_code(&mut vm);
+
+    let mut entry_index = known_entries as usize;
+    for actual in collect_output(&mut vm, runtime.version()) {
+        match actual.ty() {
+            ty if ty.is_entry() => {
+                match journal.get(entry_index) {
+                    Some(expected) if *expected == actual => {}
+                    Some(expected) => {
+                        return Err(ViolationKind::EntryMismatch {
+                            entry_index: entry_index as u32,
+                            expected: expected.clone(),
+                            actual,
+                        })
+                    }
+                    None => {
+                        return Err(ViolationKind::UnexpectedEntry {
+                            entry_index: entry_index as u32,
+                            actual,
+                        })
+                    }
+                }
+                entry_index += 1;
+            }
+            MessageType::Error => {
+                return Err(ViolationKind::Failed(
+                    actual.decode_to::<ErrorMessage>().unwrap(),
+                ))
+            }
+            MessageType::Suspension if delivery == CompletionsDelivery::OnlyReplayedEntries => {
+                return Ok(())
+            }
+            MessageType::End => break,
+            ty => return Err(ViolationKind::UnexpectedMessage(ty)),
+        }
+    }
+
+    match journal.get(entry_index) {
+        Some(expected) => Err(ViolationKind::MissingEntry {
+            entry_index: entry_index as u32,
+            expected: expected.clone(),
+        }),
+        None => Ok(()),
+    }
+}
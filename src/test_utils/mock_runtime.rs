@@ -0,0 +1,494 @@
+//! In-process fake of the Restate runtime, driving a [`CoreVM`] through the whole lifecycle of an invocation.
+//!
+//! Each attempt runs on a new [`CoreVM`] in request/response mode: the [`MockRuntime`] sends the [`StartMessage`],
+//! replays the persisted journal followed by the known completions and acks, closes the input and runs the handler code.
+//! The entries emitted by the handler are then persisted and, when possible, completed:
+//!
+//! * `GetState` and `GetStateKeys` are completed with the persisted state, which `SetState`, `ClearState` and `ClearAllState` modify.
+//! * `Sleep` is completed right away, advancing the virtual clock to the wake-up time.
+//! * `Call` is completed invoking the handler registered with [`MockRuntime::with_call_handler`].
+//! * `GetPromise`, `PeekPromise` and `CompletePromise` are completed with the durable promises of the invocation.
+//! * `Run` is acked.
+//!
+//! Every other completion, e.g. the one of an `Awakeable`, must be provided with [`MockRuntime::complete_entry`].
+//!
+//! When the invocation suspends, it's resumed on a new [`CoreVM`] as soon as one of the awaited entries is completed.
+//! When the invocation fails, it's retried after the `next_retry_delay` of the [`ErrorMessage`],
+//! or [`MockRuntime::with_retry_delay`] if not provided.
+//! Delays don't actually wait, but advance the virtual clock, see [`MockRuntime::elapsed`].
+
+use crate::service_protocol::messages::{
+    complete_promise_entry_message, completion_message, get_state_keys_entry_message,
+    output_entry_message, start_message, CallEntryMessage, ClearStateEntryMessage,
+    CompletePromiseEntryMessage, CompletionMessage, Empty, EndMessage, EntryAckMessage,
+    ErrorMessage, Failure, GetPromiseEntryMessage, GetStateEntryMessage, GetStateKeysEntryMessage,
+    InputEntryMessage, OutputEntryMessage, PeekPromiseEntryMessage, RestateMessage,
+    SetStateEntryMessage, SleepEntryMessage, StartMessage, SuspensionMessage,
+};
+use crate::service_protocol::{Decoder, Encoder, MessageType, RawMessage, Version};
+use crate::{
+    CoreVM, NonEmptyValue, ProtocolMode, TakeOutputResult, TerminalFailure, VMOptions, VM,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+type CallHandler = Box<dyn FnMut(Bytes) -> Result<Bytes, TerminalFailure>>;
+
+/// Outcome of a single attempt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttemptOutcome {
+    Ended,
+    Suspended { waiting_on: Vec<u32> },
+    Failed(ErrorMessage),
+}
+
+/// Status of the invocation, returned by [`MockRuntime::run`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvocationStatus {
+    /// The invocation ended, with the result of its output entry, if any.
+    Ended {
+        output: Option<Result<Bytes, TerminalFailure>>,
+    },
+    /// The invocation is suspended waiting on entries that can be completed only with [`MockRuntime::complete_entry`].
+    Suspended { waiting_on: Vec<u32> },
+    /// The invocation failed [`MockRuntime::with_max_attempts`] times in a row.
+    GaveUp { last_error: ErrorMessage },
+}
+
+/// Fake Restate runtime, see the [module documentation](self).
+pub struct MockRuntime {
+    version: Version,
+    options: VMOptions,
+    key: String,
+    eager_state: bool,
+    retry_delay: Duration,
+    max_attempts: u32,
+    call_handlers: HashMap<(String, String), CallHandler>,
+
+    journal: Vec<RawMessage>,
+    completions: BTreeMap<u32, completion_message::Result>,
+    acks: Vec<u32>,
+    state: BTreeMap<String, Bytes>,
+    promises: HashMap<String, Result<Bytes, Failure>>,
+    promise_waiters: HashMap<String, Vec<u32>>,
+    attempts: Vec<AttemptOutcome>,
+
+    now: Duration,
+    last_stored_entry_time: Duration,
+    retry_count_since_last_stored_entry: u32,
+}
+
+impl MockRuntime {
+    /// Creates the runtime for a new invocation with the given input.
+    pub fn new(input: impl Into<Bytes>) -> Self {
+        Self {
+            version: Version::maximum_supported_version(),
+            options: VMOptions::default(),
+            key: String::new(),
+            eager_state: true,
+            retry_delay: Duration::from_secs(1),
+            max_attempts: 10,
+            call_handlers: HashMap::new(),
+            journal: vec![raw_entry(InputEntryMessage {
+                value: input.into(),
+                ..InputEntryMessage::default()
+            })],
+            completions: BTreeMap::new(),
+            acks: vec![],
+            state: BTreeMap::new(),
+            promises: HashMap::new(),
+            promise_waiters: HashMap::new(),
+            attempts: vec![],
+            now: Duration::ZERO,
+            last_stored_entry_time: Duration::ZERO,
+            retry_count_since_last_stored_entry: 0,
+        }
+    }
+
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Options of the [`CoreVM`] of each attempt. The protocol mode is always [`ProtocolMode::RequestResponse`].
+    pub fn with_vm_options(mut self, options: VMOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Key of the virtual object or workflow.
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = key.into();
+        self
+    }
+
+    pub fn with_state(mut self, key: impl Into<String>, value: impl Into<Bytes>) -> Self {
+        self.state.insert(key.into(), value.into());
+        self
+    }
+
+    /// When true, the default, the whole state is sent in the [`StartMessage`].
+    /// Otherwise no state is sent, and `GetState` entries are completed by the runtime.
+    pub fn with_eager_state(mut self, eager_state: bool) -> Self {
+        self.eager_state = eager_state;
+        self
+    }
+
+    /// Delay between attempts, when the [`ErrorMessage`] doesn't provide one.
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Max number of consecutive failed attempts in a single [`MockRuntime::run`], before giving up.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Handler invoked to complete the calls to `service`/`handler`.
+    pub fn with_call_handler(
+        mut self,
+        service: impl Into<String>,
+        handler: impl Into<String>,
+        f: impl FnMut(Bytes) -> Result<Bytes, TerminalFailure> + 'static,
+    ) -> Self {
+        self.call_handlers
+            .insert((service.into(), handler.into()), Box::new(f));
+        self
+    }
+
+    /// Completes the given entry, e.g. an `Awakeable`. The completion is sent on the next attempt.
+    pub fn complete_entry(&mut self, entry_index: u32, value: NonEmptyValue) {
+        self.completions.insert(
+            entry_index,
+            match value {
+                NonEmptyValue::Success(b) => completion_message::Result::Value(b),
+                NonEmptyValue::Failure(f) => completion_message::Result::Failure(f.into()),
+            },
+        );
+    }
+
+    /// Runs attempts of the invocation, executing `user_code` for each of them, until the invocation ends,
+    /// suspends waiting on entries the runtime can't complete, or fails [`MockRuntime::with_max_attempts`] times in a row.
+    ///
+    /// Like in [`VMTestCase::run`](super::VMTestCase::run), `user_code` must end the VM, or return after it suspended or failed.
+    pub fn run(&mut self, mut user_code: impl FnMut(&mut CoreVM)) -> InvocationStatus {
+        let mut failed_attempts = 0;
+        loop {
+            match self.run_attempt(&mut user_code) {
+                AttemptOutcome::Ended => {
+                    return InvocationStatus::Ended {
+                        output: self.output(),
+                    }
+                }
+                AttemptOutcome::Suspended { waiting_on } => {
+                    failed_attempts = 0;
+                    if !waiting_on
+                        .iter()
+                        .any(|idx| self.completions.contains_key(idx) || self.acks.contains(idx))
+                    {
+                        return InvocationStatus::Suspended { waiting_on };
+                    }
+                }
+                AttemptOutcome::Failed(error) => {
+                    failed_attempts += 1;
+                    if failed_attempts >= self.max_attempts {
+                        return InvocationStatus::GaveUp { last_error: error };
+                    }
+                    self.now += error
+                        .next_retry_delay
+                        .map(Duration::from_millis)
+                        .unwrap_or(self.retry_delay);
+                    self.retry_count_since_last_stored_entry += 1;
+                }
+            }
+        }
+    }
+
+    /// The persisted journal, starting with the input entry.
+    pub fn journal(&self) -> &[RawMessage] {
+        &self.journal
+    }
+
+    pub fn state(&self) -> &BTreeMap<String, Bytes> {
+        &self.state
+    }
+
+    /// Outcomes of all the attempts executed so far.
+    pub fn attempts(&self) -> &[AttemptOutcome] {
+        &self.attempts
+    }
+
+    /// Virtual time elapsed since the invocation started, advanced by sleeps and retry delays.
+    pub fn elapsed(&self) -> Duration {
+        self.now
+    }
+
+    pub(super) fn version(&self) -> Version {
+        self.version
+    }
+
+    pub(super) fn completions(&self) -> &BTreeMap<u32, completion_message::Result> {
+        &self.completions
+    }
+
+    pub(super) fn acks(&self) -> &[u32] {
+        &self.acks
+    }
+
+    pub(super) fn new_vm(&self) -> CoreVM {
+        CoreVM::new(
+            vec![("content-type".to_owned(), self.version.to_string())],
+            VMOptions {
+                protocol_mode: ProtocolMode::RequestResponse,
+                ..self.options.clone()
+            },
+        )
+        .expect("The VM should be created with a supported version")
+    }
+
+    pub(super) fn start_message(&self, known_entries: u32) -> StartMessage {
+        StartMessage {
+            id: Bytes::from_static(b"mock-invocation"),
+            debug_id: "inv_mock".to_owned(),
+            known_entries,
+            state_map: if self.eager_state {
+                self.state
+                    .iter()
+                    .map(|(key, value)| start_message::StateEntry {
+                        key: Bytes::copy_from_slice(key.as_bytes()),
+                        value: value.clone(),
+                    })
+                    .collect()
+            } else {
+                vec![]
+            },
+            partial_state: !self.eager_state,
+            key: self.key.clone(),
+            retry_count_since_last_stored_entry: self.retry_count_since_last_stored_entry,
+            duration_since_last_stored_entry: (self.now - self.last_stored_entry_time).as_millis()
+                as u64,
+        }
+    }
+
+    fn run_attempt(&mut self, user_code: &mut impl FnMut(&mut CoreVM)) -> AttemptOutcome {
+        let mut vm = self.new_vm();
+
+        let encoder = Encoder::new(self.version);
+        vm.notify_input(encoder.encode(&self.start_message(self.journal.len() as u32)));
+        for entry in &self.journal {
+            vm.notify_input(encode_raw_message(entry));
+        }
+        for (&entry_index, result) in &self.completions {
+            vm.notify_input(encoder.encode(&CompletionMessage {
+                entry_index,
+                result: Some(result.clone()),
+            }));
+        }
+        for &entry_index in &self.acks {
+            vm.notify_input(encoder.encode(&EntryAckMessage { entry_index }));
+        }
+        vm.notify_input_closed();
+        assert!(vm.is_ready_to_execute().unwrap());
+
+        user_code(&mut vm);
+
+        let mut outcome = None;
+        for msg in collect_output(&mut vm, self.version) {
+            match msg.ty() {
+                ty if ty.is_entry() => self.store_entry(msg),
+                MessageType::End => {
+                    msg.decode_to::<EndMessage>().unwrap();
+                    outcome = Some(AttemptOutcome::Ended);
+                }
+                MessageType::Suspension => {
+                    outcome = Some(AttemptOutcome::Suspended {
+                        waiting_on: msg.decode_to::<SuspensionMessage>().unwrap().entry_indexes,
+                    });
+                }
+                MessageType::Error => {
+                    outcome = Some(AttemptOutcome::Failed(
+                        msg.decode_to::<ErrorMessage>().unwrap(),
+                    ));
+                }
+                ty => panic!("Unexpected message {ty:?} from the VM"),
+            }
+        }
+
+        let outcome = outcome.expect("The VM should end, suspend or fail");
+        self.attempts.push(outcome.clone());
+        outcome
+    }
+
+    fn store_entry(&mut self, msg: RawMessage) {
+        let entry_index = self.journal.len() as u32;
+        self.journal.push(msg.clone());
+        self.last_stored_entry_time = self.now;
+        self.retry_count_since_last_stored_entry = 0;
+
+        match msg.ty() {
+            MessageType::GetStateEntry => {
+                let entry = msg.decode_to::<GetStateEntryMessage>().unwrap();
+                if entry.result.is_none() {
+                    let result = match self.state.get(&utf8(&entry.key)) {
+                        Some(value) => completion_message::Result::Value(value.clone()),
+                        None => completion_message::Result::Empty(Empty {}),
+                    };
+                    self.completions.insert(entry_index, result);
+                }
+            }
+            MessageType::GetStateKeysEntry => {
+                let entry = msg.decode_to::<GetStateKeysEntryMessage>().unwrap();
+                if entry.result.is_none() {
+                    let keys = get_state_keys_entry_message::StateKeys {
+                        keys: self
+                            .state
+                            .keys()
+                            .map(|k| Bytes::copy_from_slice(k.as_bytes()))
+                            .collect(),
+                    };
+                    self.completions.insert(
+                        entry_index,
+                        completion_message::Result::Value(keys.encode_to_vec().into()),
+                    );
+                }
+            }
+            MessageType::SetStateEntry => {
+                let entry = msg.decode_to::<SetStateEntryMessage>().unwrap();
+                self.state.insert(utf8(&entry.key), entry.value);
+            }
+            MessageType::ClearStateEntry => {
+                let entry = msg.decode_to::<ClearStateEntryMessage>().unwrap();
+                self.state.remove(&utf8(&entry.key));
+            }
+            MessageType::ClearAllStateEntry => self.state.clear(),
+            MessageType::SleepEntry => {
+                let entry = msg.decode_to::<SleepEntryMessage>().unwrap();
+                if entry.result.is_none() {
+                    self.now = self.now.max(Duration::from_millis(entry.wake_up_time));
+                    self.completions
+                        .insert(entry_index, completion_message::Result::Empty(Empty {}));
+                }
+            }
+            MessageType::CallEntry => {
+                let entry = msg.decode_to::<CallEntryMessage>().unwrap();
+                if let Some(handler) = self
+                    .call_handlers
+                    .get_mut(&(entry.service_name, entry.handler_name))
+                {
+                    let result = match handler(entry.parameter) {
+                        Ok(b) => completion_message::Result::Value(b),
+                        Err(f) => completion_message::Result::Failure(f.into()),
+                    };
+                    self.completions.insert(entry_index, result);
+                }
+            }
+            MessageType::GetPromiseEntry => {
+                let entry = msg.decode_to::<GetPromiseEntryMessage>().unwrap();
+                match self.promises.get(&entry.key) {
+                    Some(result) => {
+                        let result = promise_completion(result.clone());
+                        self.completions.insert(entry_index, result);
+                    }
+                    None => self
+                        .promise_waiters
+                        .entry(entry.key)
+                        .or_default()
+                        .push(entry_index),
+                }
+            }
+            MessageType::PeekPromiseEntry => {
+                let entry = msg.decode_to::<PeekPromiseEntryMessage>().unwrap();
+                let result = match self.promises.get(&entry.key) {
+                    Some(result) => promise_completion(result.clone()),
+                    None => completion_message::Result::Empty(Empty {}),
+                };
+                self.completions.insert(entry_index, result);
+            }
+            MessageType::CompletePromiseEntry => {
+                let entry = msg.decode_to::<CompletePromiseEntryMessage>().unwrap();
+                let result = if self.promises.contains_key(&entry.key) {
+                    completion_message::Result::Failure(Failure {
+                        code: 409,
+                        message: format!("promise '{}' already completed", entry.key),
+                    })
+                } else {
+                    let value = match entry.completion.expect("completion should be set") {
+                        complete_promise_entry_message::Completion::CompletionValue(b) => Ok(b),
+                        complete_promise_entry_message::Completion::CompletionFailure(f) => Err(f),
+                    };
+                    for waiter in self.promise_waiters.remove(&entry.key).unwrap_or_default() {
+                        self.completions
+                            .insert(waiter, promise_completion(value.clone()));
+                    }
+                    self.promises.insert(entry.key, value);
+                    completion_message::Result::Empty(Empty {})
+                };
+                self.completions.insert(entry_index, result);
+            }
+            MessageType::RunEntry => self.acks.push(entry_index),
+            _ => {}
+        }
+    }
+
+    fn output(&self) -> Option<Result<Bytes, TerminalFailure>> {
+        self.journal
+            .iter()
+            .rev()
+            .find(|msg| msg.ty() == OutputEntryMessage::ty())
+            .and_then(|msg| {
+                msg.clone()
+                    .decode_to::<OutputEntryMessage>()
+                    .unwrap()
+                    .result
+            })
+            .map(|result| match result {
+                output_entry_message::Result::Value(b) => Ok(b),
+                output_entry_message::Result::Failure(f) => Err(f.into()),
+            })
+    }
+}
+
+/// Encodes the message back, with its original header.
+pub(super) fn encode_raw_message(msg: &RawMessage) -> Bytes {
+    let mut buf = BytesMut::with_capacity(8 + msg.payload().len());
+    buf.put_u64(msg.header().into());
+    buf.put_slice(msg.payload());
+    buf.freeze()
+}
+
+/// Takes all the output of the VM, panicking if the user code returned without ending it.
+pub(super) fn collect_output(vm: &mut CoreVM, version: Version) -> Vec<RawMessage> {
+    let mut decoder = Decoder::new(version);
+    loop {
+        match vm.take_output() {
+            TakeOutputResult::Buffer(b) if b.is_empty() => {
+                panic!("The user code returned without ending the VM")
+            }
+            TakeOutputResult::Buffer(b) => decoder.push(b),
+            TakeOutputResult::EOF => break,
+        }
+    }
+    std::iter::from_fn(|| decoder.consume_next().unwrap()).collect()
+}
+
+fn raw_entry(entry: InputEntryMessage) -> RawMessage {
+    let encoder = Encoder::new(Version::maximum_supported_version());
+    let mut decoder = Decoder::new(Version::maximum_supported_version());
+    decoder.push(encoder.encode(&entry));
+    decoder.consume_next().unwrap().unwrap()
+}
+
+fn promise_completion(result: Result<Bytes, Failure>) -> completion_message::Result {
+    match result {
+        Ok(b) => completion_message::Result::Value(b),
+        Err(f) => completion_message::Result::Failure(f),
+    }
+}
+
+fn utf8(key: &Bytes) -> String {
+    String::from_utf8_lossy(key).into_owned()
+}
@@ -0,0 +1,231 @@
+//! Harness to test the [`CoreVM`] feeding it protocol messages and asserting on the messages it emits,
+//! available with the `test-utils` feature.
+//!
+//! ```
+//! use bytes::Bytes;
+//! use restate_sdk_shared_core::test_utils::messages::{
+//!     output_entry_message, EndMessage, OutputEntryMessage,
+//! };
+//! use restate_sdk_shared_core::test_utils::*;
+//! use restate_sdk_shared_core::{EntryOptions, NonEmptyValue, VM};
+//!
+//! let mut output = VMTestCase::new()
+//!     .input(start_message(1))
+//!     .input(input_entry_message(b"my-data"))
+//!     .run(|vm| {
+//!         let input = vm.sys_input().unwrap().input;
+//!         vm.sys_write_output(NonEmptyValue::Success(input), EntryOptions::default())
+//!             .unwrap();
+//!         vm.sys_end().unwrap();
+//!     });
+//!
+//! assert_eq!(
+//!     output.expect_next::<OutputEntryMessage>().result,
+//!     Some(output_entry_message::Result::Value(Bytes::from_static(b"my-data")))
+//! );
+//! output.expect_next::<EndMessage>();
+//! output.expect_no_more_messages();
+//! ```
+
+pub mod determinism;
+pub mod mock_runtime;
+
+use crate::service_protocol::messages::{
+    completion_message, CompletionMessage, EntryAckMessage, Failure, InputEntryMessage,
+    RestateMessage, StartMessage, WriteableRestateMessage,
+};
+use crate::{CoreVM, TakeOutputResult, VMOptions, VM};
+use bytes::Bytes;
+
+pub use crate::service_protocol::{
+    Decoder, Encoder, MessageHeader, MessageType, RawMessage, Version,
+};
+
+/// The service protocol messages.
+pub mod messages {
+    pub use crate::service_protocol::messages::{
+        call_entry_message, cancel_invocation_entry_message, complete_awakeable_entry_message,
+        complete_promise_entry_message, completion_message, get_call_invocation_id_entry_message,
+        get_promise_entry_message, get_state_entry_message, get_state_keys_entry_message,
+        output_entry_message, peek_promise_entry_message, run_entry_message, sleep_entry_message,
+        start_message, AwakeableEntryMessage, CallEntryMessage, CancelInvocationEntryMessage,
+        ClearAllStateEntryMessage, ClearStateEntryMessage, CombinatorEntryMessage,
+        CompleteAwakeableEntryMessage, CompletePromiseEntryMessage, CompletionMessage, Empty,
+        EndMessage, EntryAckMessage, ErrorMessage, Failure, GetCallInvocationIdEntryMessage,
+        GetPromiseEntryMessage, GetStateEntryMessage, GetStateKeysEntryMessage, Header,
+        InputEntryMessage, OneWayCallEntryMessage, OutputEntryMessage, PeekPromiseEntryMessage,
+        RestateMessage, RunEntryMessage, SetStateEntryMessage, SleepEntryMessage, StartMessage,
+        SuspensionMessage, WriteableRestateMessage,
+    };
+}
+
+/// All the protocol versions supported by [`CoreVM`], to parametrize tests that must behave the same way on each of them.
+pub const SUPPORTED_VERSIONS: [Version; 2] = [Version::V2, Version::V3];
+
+/// A [`CoreVM`] to feed with input messages, before running the handler code with [`VMTestCase::run`].
+pub struct VMTestCase {
+    encoder: Encoder,
+    vm: CoreVM,
+}
+
+impl Default for VMTestCase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VMTestCase {
+    pub fn new() -> Self {
+        Self::with_version(Version::maximum_supported_version())
+    }
+
+    pub fn with_version(version: Version) -> Self {
+        Self::with_version_and_vm_options(version, VMOptions::default())
+    }
+
+    pub fn with_vm_options(options: VMOptions) -> Self {
+        Self::with_version_and_vm_options(Version::maximum_supported_version(), options)
+    }
+
+    pub fn with_version_and_vm_options(version: Version, options: VMOptions) -> Self {
+        Self {
+            encoder: Encoder::new(version),
+            vm: CoreVM::new(
+                vec![("content-type".to_owned(), version.to_string())],
+                options,
+            )
+            .expect("The VM should be created with a supported version"),
+        }
+    }
+
+    /// Feeds the given message to the VM.
+    pub fn input<M: WriteableRestateMessage>(mut self, m: M) -> Self {
+        self.vm.notify_input(self.encoder.encode(&m));
+        self
+    }
+
+    /// Closes the input, then runs the `user_code`, which must end the VM, e.g. invoking [`VM::sys_end`].
+    pub fn run(mut self, user_code: impl FnOnce(&mut CoreVM)) -> OutputIterator {
+        self.vm.notify_input_closed();
+        assert!(self.vm.is_ready_to_execute().unwrap());
+
+        user_code(&mut self.vm);
+
+        OutputIterator::collect_vm(&mut self.vm)
+    }
+
+    /// Like [`VMTestCase::run`], but without closing the input.
+    /// The `user_code` can feed more messages, encoding them with the provided [`Encoder`].
+    pub fn run_without_closing_input(
+        mut self,
+        user_code: impl FnOnce(&mut CoreVM, &Encoder),
+    ) -> OutputIterator {
+        assert!(self.vm.is_ready_to_execute().unwrap());
+
+        user_code(&mut self.vm, &self.encoder);
+
+        OutputIterator::collect_vm(&mut self.vm)
+    }
+}
+
+/// The messages emitted by a VM.
+pub struct OutputIterator(Decoder);
+
+impl OutputIterator {
+    /// Takes all the output of the VM, which must be ended.
+    pub fn collect_vm(vm: &mut impl VM) -> Self {
+        let mut decoder = Decoder::new(vm.get_response_head().version);
+        while let TakeOutputResult::Buffer(b) = vm.take_output() {
+            decoder.push(b);
+        }
+        assert_eq!(vm.take_output(), TakeOutputResult::EOF);
+
+        Self(decoder)
+    }
+
+    /// Decodes the next message, panicking if it's not of type `M`.
+    pub fn next_decoded<M: RestateMessage>(&mut self) -> Option<M> {
+        self.0
+            .consume_next()
+            .unwrap()
+            .map(|msg| msg.decode_to::<M>().unwrap())
+    }
+
+    /// Like [`OutputIterator::next_decoded`], but panics if there are no more messages.
+    pub fn expect_next<M: RestateMessage>(&mut self) -> M {
+        self.next_decoded::<M>()
+            .unwrap_or_else(|| panic!("Expected {:?}, but there are no more messages", M::ty()))
+    }
+
+    pub fn expect_no_more_messages(&mut self) {
+        if let Some(msg) = self.0.consume_next().unwrap() {
+            panic!("Expected no more messages, but got {:?}", msg.ty())
+        }
+    }
+}
+
+impl Iterator for OutputIterator {
+    type Item = RawMessage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.consume_next().unwrap()
+    }
+}
+
+// --- Mocks
+
+/// Start message of the invocation with id `123`, and no eager state.
+pub fn start_message(known_entries: u32) -> StartMessage {
+    StartMessage {
+        id: Bytes::from_static(b"123"),
+        debug_id: "123".to_string(),
+        known_entries,
+        state_map: vec![],
+        partial_state: true,
+        key: "".to_string(),
+        retry_count_since_last_stored_entry: 0,
+        duration_since_last_stored_entry: 0,
+    }
+}
+
+pub fn input_entry_message(b: impl AsRef<[u8]>) -> InputEntryMessage {
+    InputEntryMessage {
+        headers: vec![],
+        value: Bytes::copy_from_slice(b.as_ref()),
+        ..InputEntryMessage::default()
+    }
+}
+
+pub fn completion_with_value(entry_index: u32, b: impl AsRef<[u8]>) -> CompletionMessage {
+    CompletionMessage {
+        entry_index,
+        result: Some(completion_message::Result::Value(Bytes::copy_from_slice(
+            b.as_ref(),
+        ))),
+    }
+}
+
+pub fn completion_with_failure(
+    entry_index: u32,
+    code: u16,
+    message: impl Into<String>,
+) -> CompletionMessage {
+    CompletionMessage {
+        entry_index,
+        result: Some(completion_message::Result::Failure(Failure {
+            code: code as u32,
+            message: message.into(),
+        })),
+    }
+}
+
+pub fn completion_with_empty(entry_index: u32) -> CompletionMessage {
+    CompletionMessage {
+        entry_index,
+        result: Some(completion_message::Result::Empty(messages::Empty {})),
+    }
+}
+
+pub fn entry_ack(entry_index: u32) -> EntryAckMessage {
+    EntryAckMessage { entry_index }
+}
@@ -16,7 +16,7 @@ fn greeter_target() -> Target {
 
 #[test]
 fn dont_await_call() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -28,10 +28,17 @@ fn dont_await_call() {
             vm.sys_input().unwrap();
 
             let _ = vm
-                .sys_call(greeter_target(), Bytes::from_static(b"Francesco"))
-                .unwrap();
-            vm.sys_write_output(NonEmptyValue::Success(Bytes::from_static(b"Whatever")))
+                .sys_call(
+                    greeter_target(),
+                    Bytes::from_static(b"Francesco"),
+                    EntryOptions::default(),
+                )
                 .unwrap();
+            vm.sys_write_output(
+                NonEmptyValue::Success(Bytes::from_static(b"Whatever")),
+                EntryOptions::default(),
+            )
+            .unwrap();
             vm.sys_end().unwrap()
         });
 
@@ -62,7 +69,7 @@ fn dont_await_call() {
 
 #[test]
 fn dont_await_call_dont_notify_input_closed() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -73,10 +80,17 @@ fn dont_await_call_dont_notify_input_closed() {
         .run_without_closing_input(|vm, _| {
             vm.sys_input().unwrap();
             let _ = vm
-                .sys_call(greeter_target(), Bytes::from_static(b"Francesco"))
-                .unwrap();
-            vm.sys_write_output(NonEmptyValue::Success(Bytes::from_static(b"Whatever")))
+                .sys_call(
+                    greeter_target(),
+                    Bytes::from_static(b"Francesco"),
+                    EntryOptions::default(),
+                )
                 .unwrap();
+            vm.sys_write_output(
+                NonEmptyValue::Success(Bytes::from_static(b"Whatever")),
+                EntryOptions::default(),
+            )
+            .unwrap();
             vm.sys_end().unwrap()
         });
 
@@ -105,6 +119,60 @@ fn dont_await_call_dont_notify_input_closed() {
     assert_eq!(output.next(), None);
 }
 
+#[derive(Debug)]
+pub(super) struct FirstCompleted(pub(super) Vec<AsyncResultHandle>);
+
+impl AsyncResultCombinator for FirstCompleted {
+    fn try_complete(
+        &self,
+        tracker: &mut AsyncResultAccessTracker,
+    ) -> Option<Vec<AsyncResultHandle>> {
+        self.0
+            .iter()
+            .find(|h| tracker.get_state(**h) != AsyncResultState::NotReady)
+            .map(|h| vec![*h])
+    }
+}
+
+#[test]
+fn combinator_unsupported_on_v2() {
+    let mut output = VMTestCase::with_version(Version::V2)
+        .input(start_message(1))
+        .input(input_entry_message(b"my-data"))
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            let h1 = vm
+                .sys_call(
+                    greeter_target(),
+                    Bytes::from_static(b"Francesco"),
+                    EntryOptions::default(),
+                )
+                .unwrap();
+
+            assert_that!(
+                vm.sys_try_complete_combinator(FirstCompleted(vec![h1])),
+                err(eq_vm_error(
+                    vm::errors::UnsupportedFeatureForNegotiatedVersion::new(
+                        "combinators",
+                        Version::V2,
+                        Version::V3
+                    )
+                    .into()
+                ))
+            );
+        });
+
+    let _ = output.next_decoded::<CallEntryMessage>().unwrap();
+    assert_that!(
+        output.next_decoded::<ErrorMessage>().unwrap(),
+        pat!(ErrorMessage {
+            code: eq(u16::from(vm::errors::codes::UNSUPPORTED_FEATURE) as u32)
+        })
+    );
+    assert_eq!(output.next(), None);
+}
+
 mod notify_await_point {
     use super::*;
 
@@ -112,7 +180,7 @@ mod notify_await_point {
 
     #[test]
     fn await_twice_the_same_handle() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"123"),
                 debug_id: "123".to_string(),
@@ -127,7 +195,7 @@ mod notify_await_point {
             .run_without_closing_input(|vm, _| {
                 vm.sys_input().unwrap();
 
-                let (_, h) = vm.sys_awakeable().unwrap();
+                let (_, h) = vm.sys_awakeable(EntryOptions::default()).unwrap();
 
                 vm.notify_await_point(h);
                 vm.notify_await_point(h);
@@ -150,7 +218,7 @@ mod notify_await_point {
 
     #[test]
     fn await_two_handles_at_same_time() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"123"),
                 debug_id: "123".to_string(),
@@ -165,8 +233,8 @@ mod notify_await_point {
             .run_without_closing_input(|vm, _| {
                 vm.sys_input().unwrap();
 
-                let (_, h1) = vm.sys_awakeable().unwrap();
-                let (_, h2) = vm.sys_awakeable().unwrap();
+                let (_, h1) = vm.sys_awakeable(EntryOptions::default()).unwrap();
+                let (_, h2) = vm.sys_awakeable(EntryOptions::default()).unwrap();
 
                 vm.notify_await_point(h1);
                 // This should transition the state machine to error
@@ -206,10 +274,18 @@ mod reverse_await_order {
         vm.sys_input().unwrap();
 
         let h1 = vm
-            .sys_call(greeter_target(), Bytes::from_static(b"Francesco"))
+            .sys_call(
+                greeter_target(),
+                Bytes::from_static(b"Francesco"),
+                EntryOptions::default(),
+            )
             .unwrap();
         let h2 = vm
-            .sys_call(greeter_target(), Bytes::from_static(b"Till"))
+            .sys_call(
+                greeter_target(),
+                Bytes::from_static(b"Till"),
+                EntryOptions::default(),
+            )
             .unwrap();
 
         vm.notify_await_point(h2);
@@ -219,7 +295,8 @@ mod reverse_await_order {
         }
         let_assert!(Some(Value::Success(h2_value)) = h2_result.unwrap());
 
-        vm.sys_state_set("A2".to_owned(), h2_value.clone()).unwrap();
+        vm.sys_state_set("A2".to_owned(), h2_value.clone(), EntryOptions::default())
+            .unwrap();
 
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
@@ -228,16 +305,17 @@ mod reverse_await_order {
         }
         let_assert!(Some(Value::Success(h1_value)) = h1_result.unwrap());
 
-        vm.sys_write_output(NonEmptyValue::Success(Bytes::from(
-            [&h1_value[..], b"-", &h2_value[..]].concat(),
-        )))
+        vm.sys_write_output(
+            NonEmptyValue::Success(Bytes::from([&h1_value[..], b"-", &h2_value[..]].concat())),
+            EntryOptions::default(),
+        )
         .unwrap();
         vm.sys_end().unwrap()
     }
 
     #[test]
     fn none_completed() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -278,7 +356,7 @@ mod reverse_await_order {
 
     #[test]
     fn a1_and_a2_completed_later() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -346,7 +424,7 @@ mod reverse_await_order {
 
     #[test]
     fn a2_and_a1_completed_later() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -414,7 +492,7 @@ mod reverse_await_order {
 
     #[test]
     fn only_a2_completed() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -469,7 +547,7 @@ mod reverse_await_order {
 
     #[test]
     fn only_a1_completed() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
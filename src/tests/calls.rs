@@ -2,12 +2,11 @@ use super::*;
 
 use crate::service_protocol::messages::*;
 use assert2::let_assert;
-use googletest::prelude::*;
 use test_log::test;
 
 #[test]
 fn call_then_get_invocation_id_then_cancel_invocation() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(Version::V3)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .input(CompletionMessage {
@@ -28,11 +27,15 @@ fn call_then_get_invocation_id_then_cancel_invocation() {
                         idempotency_key: None,
                     },
                     Bytes::new(),
+                    EntryOptions::default(),
                 )
                 .unwrap();
 
             let invocation_id_handle = vm
-                .sys_get_call_invocation_id(GetInvocationIdTarget::CallEntry(call_handle))
+                .sys_get_call_invocation_id(
+                    GetInvocationIdTarget::CallEntry(call_handle),
+                    EntryOptions::default(),
+                )
                 .unwrap();
             vm.notify_await_point(invocation_id_handle);
             let_assert!(
@@ -41,10 +44,16 @@ fn call_then_get_invocation_id_then_cancel_invocation() {
             );
             assert_eq!(invocation_id, "my-id");
 
-            vm.sys_cancel_invocation(CancelInvocationTarget::CallEntry(call_handle))
-                .unwrap();
-            vm.sys_cancel_invocation(CancelInvocationTarget::InvocationId(invocation_id.clone()))
-                .unwrap();
+            vm.sys_cancel_invocation(
+                CancelInvocationTarget::CallEntry(call_handle),
+                EntryOptions::default(),
+            )
+            .unwrap();
+            vm.sys_cancel_invocation(
+                CancelInvocationTarget::InvocationId(invocation_id.clone()),
+                EntryOptions::default(),
+            )
+            .unwrap();
 
             vm.sys_end().unwrap();
         });
@@ -94,7 +103,7 @@ fn call_then_get_invocation_id_then_cancel_invocation() {
 
 #[test]
 fn send_then_get_invocation_id_then_cancel_invocation() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(Version::V3)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .input(CompletionMessage {
@@ -116,11 +125,15 @@ fn send_then_get_invocation_id_then_cancel_invocation() {
                     },
                     Bytes::new(),
                     None,
+                    EntryOptions::default(),
                 )
                 .unwrap();
 
             let invocation_id_handle = vm
-                .sys_get_call_invocation_id(GetInvocationIdTarget::SendEntry(send_handle))
+                .sys_get_call_invocation_id(
+                    GetInvocationIdTarget::SendEntry(send_handle),
+                    EntryOptions::default(),
+                )
                 .unwrap();
             vm.notify_await_point(invocation_id_handle);
             let_assert!(
@@ -129,10 +142,16 @@ fn send_then_get_invocation_id_then_cancel_invocation() {
             );
             assert_eq!(invocation_id, "my-id");
 
-            vm.sys_cancel_invocation(CancelInvocationTarget::SendEntry(send_handle))
-                .unwrap();
-            vm.sys_cancel_invocation(CancelInvocationTarget::InvocationId(invocation_id.clone()))
-                .unwrap();
+            vm.sys_cancel_invocation(
+                CancelInvocationTarget::SendEntry(send_handle),
+                EntryOptions::default(),
+            )
+            .unwrap();
+            vm.sys_cancel_invocation(
+                CancelInvocationTarget::InvocationId(invocation_id.clone()),
+                EntryOptions::default(),
+            )
+            .unwrap();
 
             vm.sys_end().unwrap();
         });
@@ -179,3 +198,83 @@ fn send_then_get_invocation_id_then_cancel_invocation() {
     );
     assert_eq!(output.next(), None);
 }
+
+#[test]
+fn get_invocation_id_unsupported_on_v2() {
+    let mut output = VMTestCase::with_version(Version::V2)
+        .input(start_message(1))
+        .input(input_entry_message(b"my-data"))
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            let call_handle = vm
+                .sys_call(
+                    Target {
+                        service: "MySvc".to_string(),
+                        handler: "MyHandler".to_string(),
+                        key: None,
+                        idempotency_key: None,
+                    },
+                    Bytes::new(),
+                    EntryOptions::default(),
+                )
+                .unwrap();
+
+            assert_that!(
+                vm.sys_get_call_invocation_id(
+                    GetInvocationIdTarget::CallEntry(call_handle),
+                    EntryOptions::default()
+                ),
+                err(eq_vm_error(
+                    vm::errors::UnsupportedFeatureForNegotiatedVersion::new(
+                        "get call invocation id",
+                        Version::V2,
+                        Version::V3
+                    )
+                    .into()
+                ))
+            );
+        });
+
+    let _ = output.next_decoded::<CallEntryMessage>().unwrap();
+    assert_that!(
+        output.next_decoded::<ErrorMessage>().unwrap(),
+        pat!(ErrorMessage {
+            code: eq(u16::from(vm::errors::codes::UNSUPPORTED_FEATURE) as u32)
+        })
+    );
+    assert_eq!(output.next(), None);
+}
+
+#[test]
+fn cancel_invocation_unsupported_on_v2() {
+    let mut output = VMTestCase::with_version(Version::V2)
+        .input(start_message(1))
+        .input(input_entry_message(b"my-data"))
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            assert_that!(
+                vm.sys_cancel_invocation(
+                    CancelInvocationTarget::InvocationId("my-id".to_owned()),
+                    EntryOptions::default()
+                ),
+                err(eq_vm_error(
+                    vm::errors::UnsupportedFeatureForNegotiatedVersion::new(
+                        "cancel invocation",
+                        Version::V2,
+                        Version::V3
+                    )
+                    .into()
+                ))
+            );
+        });
+
+    assert_that!(
+        output.next_decoded::<ErrorMessage>().unwrap(),
+        pat!(ErrorMessage {
+            code: eq(u16::from(vm::errors::codes::UNSUPPORTED_FEATURE) as u32)
+        })
+    );
+    assert_eq!(output.next(), None);
+}
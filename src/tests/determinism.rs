@@ -0,0 +1,179 @@
+use super::*;
+
+use crate::test_utils::determinism::{
+    check_replay_determinism, CompletionsDelivery, DeterminismViolation, ViolationKind,
+};
+use crate::test_utils::mock_runtime::MockRuntime;
+use std::cell::Cell;
+use std::time::Duration;
+use test_log::test;
+
+fn greeter_target() -> Target {
+    Target {
+        service: "Greeter".to_owned(),
+        handler: "greet".to_owned(),
+        key: None,
+        idempotency_key: None,
+    }
+}
+
+fn runtime() -> MockRuntime {
+    MockRuntime::new(Bytes::from_static(b"Till"))
+        .with_version(VERSION)
+        .with_key("my-key")
+        .with_state("STATE", Bytes::from_static(b"Francesco"))
+        .with_call_handler("Greeter", "greet", |input| {
+            Ok([b"Hello ", input.as_ref()].concat().into())
+        })
+}
+
+#[test]
+fn deterministic_handler() {
+    check_replay_determinism(runtime(), |vm| {
+        let input = vm.sys_input().unwrap().input;
+
+        let h = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
+        let Some(Value::Success(previous)) = await_handle(vm, h) else {
+            return;
+        };
+        vm.sys_state_set("STATE".to_owned(), input.clone(), EntryOptions::default())
+            .unwrap();
+
+        let h = vm
+            .sys_call(greeter_target(), previous, EntryOptions::default())
+            .unwrap();
+        let Some(Value::Success(greeting)) = await_handle(vm, h) else {
+            return;
+        };
+
+        let suffix = match vm.sys_run_enter("my-side-effect".to_owned()).unwrap() {
+            RunEnterResult::Executed(NonEmptyValue::Success(value)) => value,
+            RunEnterResult::Executed(NonEmptyValue::Failure(f)) => panic!("Unexpected {f:?}"),
+            RunEnterResult::NotExecuted(_) => {
+                let h = vm
+                    .sys_run_exit(
+                        RunExitResult::Success(Bytes::from_static(b"!")),
+                        RetryPolicy::default(),
+                    )
+                    .unwrap()
+                    .handle()
+                    .unwrap();
+                let Some(Value::Success(value)) = await_handle(vm, h) else {
+                    return;
+                };
+                value
+            }
+        };
+
+        let h = vm
+            .sys_sleep(Duration::from_secs(1), EntryOptions::default())
+            .unwrap();
+        if await_handle(vm, h).is_none() {
+            return;
+        }
+
+        end_with_output(vm, [greeting, suffix].concat().into());
+    })
+    .unwrap();
+}
+
+#[test]
+fn handler_changing_the_call_target() {
+    let calls = Cell::new(0);
+
+    let violation = check_replay_determinism(runtime(), |vm| {
+        let input = vm.sys_input().unwrap().input;
+
+        // The two attempts on the runtime call Greeter, the replays call Greeter2
+        calls.set(calls.get() + 1);
+        let mut target = greeter_target();
+        if calls.get() > 2 {
+            target.service = "Greeter2".to_owned();
+        }
+
+        let Ok(h) = vm.sys_call(target, input, EntryOptions::default()) else {
+            return;
+        };
+        let Some(Value::Success(greeting)) = await_handle(vm, h) else {
+            return;
+        };
+
+        end_with_output(vm, greeting);
+    })
+    .unwrap_err();
+
+    assert_that!(
+        violation,
+        pat!(DeterminismViolation {
+            known_entries: eq(1),
+            delivery: eq(CompletionsDelivery::AfterReplayedEntries),
+            kind: matches_pattern!(ViolationKind::EntryMismatch { entry_index: eq(1) })
+        })
+    );
+}
+
+#[test]
+fn handler_skipping_an_entry_on_replay() {
+    let executions = Cell::new(0);
+
+    let violation = check_replay_determinism(runtime(), |vm| {
+        vm.sys_input().unwrap();
+
+        executions.set(executions.get() + 1);
+        if executions.get() == 1 {
+            vm.sys_state_clear("STATE".to_owned(), EntryOptions::default())
+                .unwrap();
+        }
+
+        end_with_output(vm, Bytes::new());
+    })
+    .unwrap_err();
+
+    assert_that!(
+        violation,
+        pat!(DeterminismViolation {
+            known_entries: eq(1),
+            kind: matches_pattern!(ViolationKind::EntryMismatch { entry_index: eq(1) })
+        })
+    );
+
+    assert_eq!(
+        violation.to_string(),
+        "Replaying 1 known entries, delivering completions AfterReplayedEntries: entry 1 is OutputEntry but ClearStateEntry was recorded"
+    );
+}
+
+#[test]
+fn handler_diverging_during_replay() {
+    let executions = Cell::new(0);
+
+    let violation = check_replay_determinism(runtime(), |vm| {
+        vm.sys_input().unwrap();
+
+        // Diverge only when replaying the clear state entry, after the run on the runtime
+        // and the 3 replays of the input entry
+        executions.set(executions.get() + 1);
+        let key = if executions.get() <= 4 { "A" } else { "B" };
+        if vm
+            .sys_state_clear(key.to_owned(), EntryOptions::default())
+            .is_err()
+        {
+            return;
+        }
+
+        end_with_output(vm, Bytes::new());
+    })
+    .unwrap_err();
+
+    assert_that!(
+        violation,
+        pat!(DeterminismViolation {
+            known_entries: eq(2),
+            kind: matches_pattern!(ViolationKind::Failed(pat!(ErrorMessage {
+                code: eq(u16::from(vm::errors::codes::JOURNAL_MISMATCH) as u32)
+            })))
+        })
+    );
+}
@@ -1,15 +1,18 @@
 use super::*;
 
+use crate::error::EntryFieldMismatch;
 use crate::service_protocol::messages::{
-    ErrorMessage, GetStateEntryMessage, InputEntryMessage, OneWayCallEntryMessage, StartMessage,
+    EntryMessageHeaderEq, ErrorMessage, GetStateEntryMessage, InputEntryMessage,
+    OneWayCallEntryMessage, SleepEntryMessage, StartMessage, WriteableRestateMessage,
 };
+use assert2::let_assert;
 use std::fmt;
 use test_log::test;
 
 #[test]
 fn got_closed_stream_before_end_of_replay() {
-    let mut vm = CoreVM::mock_init(Version::maximum_supported_version());
-    let encoder = Encoder::new(Version::maximum_supported_version());
+    let mut vm = CoreVM::mock_init(VERSION);
+    let encoder = Encoder::new(VERSION);
 
     vm.notify_input(encoder.encode(&StartMessage {
         id: Bytes::from_static(b"123"),
@@ -37,6 +40,120 @@ fn got_closed_stream_before_end_of_replay() {
     assert_eq!(output.next(), None);
 }
 
+#[test]
+fn duplicate_completion() {
+    let mut vm = CoreVM::mock_init(VERSION);
+    let encoder = Encoder::new(VERSION);
+
+    vm.notify_input(encoder.encode(&start_message(1)));
+    vm.notify_input(encoder.encode(&input_entry_message(b"my-data")));
+    vm.notify_input(encoder.encode(&completion_with_value(1, b"Francesco")));
+    vm.notify_input(encoder.encode(&completion_with_value(1, b"Till")));
+    vm.notify_input_closed();
+
+    assert_that!(
+        vm.is_ready_to_execute(),
+        err(pat!(Error {
+            code: eq(u16::from(vm::errors::codes::PROTOCOL_VIOLATION)),
+            message: eq("Received a second completion for entry 1"),
+        }))
+    );
+
+    let mut output = OutputIterator::collect_vm(&mut vm);
+    assert_that!(
+        output.next_decoded::<ErrorMessage>().unwrap(),
+        pat!(ErrorMessage {
+            code: eq(u16::from(vm::errors::codes::PROTOCOL_VIOLATION) as u32),
+            message: eq("Received a second completion for entry 1"),
+        })
+    );
+    assert_eq!(output.next(), None);
+}
+
+fn assert_protocol_violation(vm: &mut CoreVM, expected_message: &str) {
+    assert_that!(
+        vm.is_ready_to_execute(),
+        err(pat!(Error {
+            code: eq(u16::from(vm::errors::codes::PROTOCOL_VIOLATION)),
+            message: eq(expected_message),
+        }))
+    );
+
+    let mut output = OutputIterator::collect_vm(vm);
+    assert_that!(
+        output.next_decoded::<ErrorMessage>().unwrap(),
+        pat!(ErrorMessage {
+            code: eq(u16::from(vm::errors::codes::PROTOCOL_VIOLATION) as u32),
+            message: eq(expected_message),
+        })
+    );
+    assert_eq!(output.next(), None);
+}
+
+#[test]
+fn message_larger_than_max_message_size() {
+    let mut vm = CoreVM::mock_init_with_options(
+        VERSION,
+        VMOptions {
+            max_message_size: Some(16),
+            ..VMOptions::default()
+        },
+    );
+    let encoder = Encoder::new(VERSION);
+
+    vm.notify_input(encoder.encode(&start_message(1)));
+    vm.notify_input(encoder.encode(&input_entry_message([0; 32])));
+    vm.notify_input_closed();
+
+    assert_protocol_violation(
+        &mut vm,
+        "message InputEntry of 34 bytes exceeds the maximum message size of 16 bytes",
+    );
+}
+
+#[test]
+fn incomplete_message_larger_than_max_buffered_bytes() {
+    let mut vm = CoreVM::mock_init_with_options(
+        VERSION,
+        VMOptions {
+            max_buffered_bytes: Some(16),
+            ..VMOptions::default()
+        },
+    );
+    let encoder = Encoder::new(VERSION);
+
+    vm.notify_input(encoder.encode(&start_message(1)));
+    let input = encoder.encode(&input_entry_message([0; 32]));
+    vm.notify_input(input.slice(0..10));
+    vm.notify_input(input.slice(10..30));
+    // Input received after the failure is discarded
+    vm.notify_input(input.slice(30..));
+
+    assert_protocol_violation(
+        &mut vm,
+        "buffered 30 bytes of incomplete messages, exceeding the limit of 16 bytes",
+    );
+}
+
+#[test]
+fn known_entries_larger_than_max_replay_entries() {
+    let mut vm = CoreVM::mock_init_with_options(
+        VERSION,
+        VMOptions {
+            max_replay_entries: Some(10),
+            ..VMOptions::default()
+        },
+    );
+    let encoder = Encoder::new(VERSION);
+
+    vm.notify_input(encoder.encode(&start_message(1_000_000)));
+
+    assert_protocol_violation(
+        &mut vm,
+        "The runtime asked to replay 1000000 entries, exceeding the limit of 10 entries",
+    );
+}
+
 #[test]
 fn get_state_entry_mismatch() {
     test_entry_mismatch(
@@ -48,7 +165,7 @@ fn get_state_entry_mismatch() {
             key: Bytes::from_static(b"another-key"),
             ..Default::default()
         },
-        |vm| vm.sys_state_get("another-key".to_owned()),
+        |vm| vm.sys_state_get("another-key".to_owned(), EntryOptions::default()),
     );
 }
 
@@ -79,17 +196,73 @@ fn one_way_call_entry_mismatch() {
                 },
                 Bytes::from_static(b"456"),
                 None,
+                EntryOptions::default(),
             )
         },
     );
 }
 
-fn test_entry_mismatch<M: WriteableRestateMessage + Clone, T: fmt::Debug>(
+#[test]
+fn sleep_entry_name_mismatch() {
+    test_entry_mismatch(
+        SleepEntryMessage {
+            wake_up_time: 10,
+            name: "my-sleep".to_owned(),
+            ..Default::default()
+        },
+        SleepEntryMessage {
+            wake_up_time: 10,
+            name: "another-sleep".to_owned(),
+            ..Default::default()
+        },
+        |vm| {
+            vm.sys_sleep(
+                Duration::from_millis(10),
+                EntryOptions::named("another-sleep"),
+            )
+        },
+    );
+}
+
+#[test]
+fn error_message_reports_entry_name() {
+    let mut output = VMTestCase::with_version(VERSION)
+        .input(StartMessage {
+            id: Bytes::from_static(b"123"),
+            debug_id: "123".to_string(),
+            known_entries: 2,
+            partial_state: true,
+            ..Default::default()
+        })
+        .input(InputEntryMessage::default())
+        .input(GetStateEntryMessage {
+            key: Bytes::from_static(b"my-key"),
+            ..Default::default()
+        })
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            assert!(vm
+                .sys_state_get("another-key".to_owned(), EntryOptions::named("my-get"))
+                .is_err());
+        });
+
+    assert_that!(
+        output.next_decoded::<ErrorMessage>().unwrap(),
+        pat!(ErrorMessage {
+            related_entry_index: some(eq(1)),
+            related_entry_name: some(eq("my-get")),
+        })
+    );
+    assert_eq!(output.next(), None);
+}
+
+fn test_entry_mismatch<M: WriteableRestateMessage + EntryMessageHeaderEq + Clone, T: fmt::Debug>(
     expected: M,
     actual: M,
     user_code: impl FnOnce(&mut CoreVM) -> Result<T, Error>,
 ) {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -109,14 +282,109 @@ fn test_entry_mismatch<M: WriteableRestateMessage + Clone, T: fmt::Debug>(
             assert_that!(
                 user_code(vm),
                 err(eq_vm_error(
-                    vm::errors::EntryMismatchError::new(expected.clone(), actual.clone(),).into()
+                    vm::errors::EntryMismatchError::new(1, &expected, &actual).into()
                 ))
             );
         });
 
     assert_that!(
         output.next_decoded::<ErrorMessage>().unwrap(),
-        error_message_as_vm_error(vm::errors::EntryMismatchError::new(expected, actual,).into())
+        error_message_as_vm_error(
+            vm::errors::EntryMismatchError::new(1, &expected, &actual).into()
+        )
     );
     assert_eq!(output.next(), None);
 }
+
+#[test]
+fn entry_mismatch_reports_field_diff() {
+    let parameter = Bytes::from(vec![b'a'; 1024]);
+    VMTestCase::with_version(VERSION)
+        .input(start_message(2))
+        .input(input_entry_message(b"my-data"))
+        .input(OneWayCallEntryMessage {
+            service_name: "greeter".to_owned(),
+            handler_name: "greet".to_owned(),
+            key: "my-key".to_owned(),
+            parameter: parameter.clone(),
+            ..Default::default()
+        })
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            let_assert!(
+                Err(error) = vm.sys_send(
+                    Target {
+                        service: "greeter".to_owned(),
+                        handler: "greet-again".to_owned(),
+                        key: Some("my-key".to_owned()),
+                        idempotency_key: None,
+                    },
+                    parameter,
+                    None,
+                    EntryOptions::default(),
+                )
+            );
+            assert_eq!(
+                error.message(),
+                "Entry 1 of type OneWayCallEntry doesn't match the expected entry, mismatching fields: [handler_name]"
+            );
+            assert_eq!(
+                error.description(),
+                "handler_name: replayed \"greet\", but the handler produced \"greet-again\""
+            );
+
+            let_assert!(Some(mismatch) = vm.snapshot().journal_mismatch);
+            assert_eq!(mismatch.entry_index, 1);
+            assert_eq!(
+                mismatch.fields,
+                vec![EntryFieldMismatch {
+                    field: "handler_name",
+                    actual: "\"greet\"".to_owned(),
+                    expected: "\"greet-again\"".to_owned(),
+                }]
+            );
+        });
+}
+
+#[test]
+fn entry_mismatch_truncates_large_fields() {
+    VMTestCase::with_version(VERSION)
+        .input(start_message(2))
+        .input(input_entry_message(b"my-data"))
+        .input(OneWayCallEntryMessage {
+            service_name: "greeter".to_owned(),
+            handler_name: "greet".to_owned(),
+            parameter: Bytes::from(vec![b'a'; 1024]),
+            ..Default::default()
+        })
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            assert!(vm
+                .sys_send(
+                    Target {
+                        service: "greeter".to_owned(),
+                        handler: "greet".to_owned(),
+                        key: None,
+                        idempotency_key: None,
+                    },
+                    Bytes::from(vec![b'b'; 1024]),
+                    None,
+                    EntryOptions::default(),
+                )
+                .is_err());
+
+            let_assert!(Some(mismatch) = vm.snapshot().journal_mismatch);
+            let_assert!([field] = mismatch.fields.as_slice());
+            assert_eq!(field.field, "parameter");
+            assert!(field.actual.starts_with("b\"aaaa"));
+            assert!(field.actual.ends_with(")"));
+            assert!(field.actual.len() < 100);
+            assert!(field.expected.starts_with("b\"bbbb"));
+            assert_ne!(
+                field.actual.rsplit_once("hash").unwrap().1,
+                field.expected.rsplit_once("hash").unwrap().1
+            );
+        });
+}
@@ -11,7 +11,7 @@ use test_log::test;
 
 #[test]
 fn just_replay() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -33,11 +33,14 @@ fn just_replay() {
         .run(|vm| {
             vm.sys_input().unwrap();
 
-            let handle = vm.sys_state_get("Personaggio".to_owned()).unwrap();
+            let handle = vm
+                .sys_state_get("Personaggio".to_owned(), EntryOptions::default())
+                .unwrap();
             let_assert!(Some(Value::Success(b)) = vm.take_async_result(handle).unwrap());
             assert_eq!(b, b"Pippo".to_vec());
 
-            vm.sys_write_output(NonEmptyValue::Success(b)).unwrap();
+            vm.sys_write_output(NonEmptyValue::Success(b), EntryOptions::default())
+                .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -59,13 +62,15 @@ fn just_replay() {
 
 #[test]
 fn suspend() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .run(|vm| {
             vm.sys_input().unwrap();
 
-            let handle = vm.sys_state_get("Personaggio".to_owned()).unwrap();
+            let handle = vm
+                .sys_state_get("Personaggio".to_owned(), EntryOptions::default())
+                .unwrap();
 
             // The callback should be completed immediately
             vm.notify_await_point(handle);
@@ -93,7 +98,7 @@ fn suspend() {
 
 #[test]
 fn completed() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .input(CompletionMessage {
@@ -105,13 +110,16 @@ fn completed() {
         .run(|vm| {
             vm.sys_input().unwrap();
 
-            let handle = vm.sys_state_get("Personaggio".to_owned()).unwrap();
+            let handle = vm
+                .sys_state_get("Personaggio".to_owned(), EntryOptions::default())
+                .unwrap();
 
             vm.notify_await_point(handle);
             let_assert!(Some(Value::Success(b)) = vm.take_async_result(handle).unwrap());
             assert_eq!(b, b"Pippo".to_vec());
 
-            vm.sys_write_output(NonEmptyValue::Success(b)).unwrap();
+            vm.sys_write_output(NonEmptyValue::Success(b), EntryOptions::default())
+                .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -140,7 +148,7 @@ fn completed() {
 
 #[test]
 fn completed_with_eager_state() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -155,13 +163,16 @@ fn completed_with_eager_state() {
         .run(|vm| {
             vm.sys_input().unwrap();
 
-            let handle = vm.sys_state_get("Personaggio".to_owned()).unwrap();
+            let handle = vm
+                .sys_state_get("Personaggio".to_owned(), EntryOptions::default())
+                .unwrap();
 
             vm.notify_await_point(handle);
             let_assert!(Some(Value::Success(b)) = vm.take_async_result(handle).unwrap());
             assert_eq!(b, b"Francesco".to_vec());
 
-            vm.sys_write_output(NonEmptyValue::Success(b)).unwrap();
+            vm.sys_write_output(NonEmptyValue::Success(b), EntryOptions::default())
+                .unwrap();
             vm.sys_end().unwrap();
         });
 
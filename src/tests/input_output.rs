@@ -10,13 +10,14 @@ fn echo_handler(vm: &mut CoreVM) {
     let_assert!(Input { input, .. } = vm.sys_input().unwrap());
     assert_eq!(input, b"my-data".to_vec());
 
-    vm.sys_write_output(NonEmptyValue::Success(input)).unwrap();
+    vm.sys_write_output(NonEmptyValue::Success(input), EntryOptions::default())
+        .unwrap();
     vm.sys_end().unwrap();
 }
 
 #[test]
 fn echo() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -48,7 +49,7 @@ fn echo() {
 
 #[test]
 fn headers() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -74,8 +75,11 @@ fn headers() {
                 })]
             );
 
-            vm.sys_write_output(NonEmptyValue::Success(Bytes::default()))
-                .unwrap();
+            vm.sys_write_output(
+                NonEmptyValue::Success(Bytes::default()),
+                EntryOptions::default(),
+            )
+            .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -95,7 +99,7 @@ fn headers() {
 
 #[test]
 fn replay_output_too() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
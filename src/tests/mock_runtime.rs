@@ -0,0 +1,250 @@
+use super::*;
+
+use crate::test_utils::mock_runtime::{AttemptOutcome, InvocationStatus, MockRuntime};
+use std::time::Duration;
+use test_log::test;
+
+#[test]
+fn lazy_state_and_sleep_resume_after_suspension() {
+    let mut runtime = MockRuntime::new(Bytes::from_static(b"Till"))
+        .with_version(VERSION)
+        .with_key("my-key")
+        .with_state("STATE", Bytes::from_static(b"Francesco"))
+        .with_eager_state(false);
+
+    let status = runtime.run(|vm| {
+        let input = vm.sys_input().unwrap().input;
+
+        let h = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
+        let Some(Value::Success(previous)) = await_handle(vm, h) else {
+            return;
+        };
+        vm.sys_state_set("STATE".to_owned(), input, EntryOptions::default())
+            .unwrap();
+
+        let h = vm
+            .sys_sleep(Duration::from_secs(60), EntryOptions::default())
+            .unwrap();
+        if await_handle(vm, h).is_none() {
+            return;
+        }
+
+        end_with_output(vm, previous);
+    });
+
+    assert_eq!(
+        status,
+        InvocationStatus::Ended {
+            output: Some(Ok(Bytes::from_static(b"Francesco")))
+        }
+    );
+    assert_eq!(
+        runtime.attempts(),
+        [
+            AttemptOutcome::Suspended {
+                waiting_on: vec![1]
+            },
+            AttemptOutcome::Suspended {
+                waiting_on: vec![3]
+            },
+            AttemptOutcome::Ended
+        ]
+    );
+    assert_eq!(
+        runtime.journal().iter().map(|e| e.ty()).collect::<Vec<_>>(),
+        [
+            MessageType::InputEntry,
+            MessageType::GetStateEntry,
+            MessageType::SetStateEntry,
+            MessageType::SleepEntry,
+            MessageType::OutputEntry
+        ]
+    );
+    assert_eq!(
+        runtime.state().get("STATE"),
+        Some(&Bytes::from_static(b"Till"))
+    );
+    assert_eq!(runtime.elapsed(), Duration::from_secs(60));
+}
+
+#[test]
+fn call_and_awakeable_completed_externally() {
+    let mut runtime = MockRuntime::new(Bytes::from_static(b"Till"))
+        .with_version(VERSION)
+        .with_call_handler("Greeter", "greet", |input| {
+            Ok([b"Hello ", input.as_ref()].concat().into())
+        });
+
+    let mut user_code = |vm: &mut CoreVM| {
+        let input = vm.sys_input().unwrap().input;
+
+        let h = vm
+            .sys_call(
+                Target {
+                    service: "Greeter".to_owned(),
+                    handler: "greet".to_owned(),
+                    key: None,
+                    idempotency_key: None,
+                },
+                input,
+                EntryOptions::default(),
+            )
+            .unwrap();
+        let Some(Value::Success(greeting)) = await_handle(vm, h) else {
+            return;
+        };
+
+        let (_, h) = vm.sys_awakeable(EntryOptions::default()).unwrap();
+        let Some(Value::Success(suffix)) = await_handle(vm, h) else {
+            return;
+        };
+
+        end_with_output(vm, [greeting, suffix].concat().into());
+    };
+
+    assert_eq!(
+        runtime.run(&mut user_code),
+        InvocationStatus::Suspended {
+            waiting_on: vec![2]
+        }
+    );
+
+    runtime.complete_entry(2, NonEmptyValue::Success(Bytes::from_static(b"!")));
+    assert_eq!(
+        runtime.run(&mut user_code),
+        InvocationStatus::Ended {
+            output: Some(Ok(Bytes::from_static(b"Hello Till!")))
+        }
+    );
+    assert_eq!(runtime.attempts().len(), 3);
+}
+
+#[test]
+fn run_retried_after_next_retry_delay() {
+    let mut runtime = MockRuntime::new(Bytes::from_static(b"my-data")).with_version(VERSION);
+
+    let status = runtime.run(|vm| {
+        vm.sys_input().unwrap();
+
+        let retry_count = match vm.sys_run_enter("my-side-effect".to_owned()).unwrap() {
+            RunEnterResult::NotExecuted(retry_info) => retry_info.retry_count,
+            RunEnterResult::Executed(NonEmptyValue::Success(value)) => {
+                return end_with_output(vm, value);
+            }
+            RunEnterResult::Executed(NonEmptyValue::Failure(f)) => panic!("Unexpected {f:?}"),
+        };
+        let result = if retry_count < 2 {
+            RunExitResult::RetryableFailure {
+                attempt_duration: Duration::ZERO,
+                error: Error::internal("my-error"),
+            }
+        } else {
+            RunExitResult::Success(Bytes::from_static(b"done"))
+        };
+        let h = match vm
+            .sys_run_exit(
+                result,
+                RetryPolicy::FixedDelay {
+                    interval: Duration::from_secs(5),
+                    max_attempts: None,
+                    max_duration: None,
+                },
+            )
+            .unwrap()
+        {
+            RunExitOutcome::Retry { .. } => return,
+            outcome => outcome.handle().unwrap(),
+        };
+        let Some(Value::Success(value)) = await_handle(vm, h) else {
+            return;
+        };
+
+        end_with_output(vm, value);
+    });
+
+    assert_eq!(
+        status,
+        InvocationStatus::Ended {
+            output: Some(Ok(Bytes::from_static(b"done")))
+        }
+    );
+    assert_that!(
+        runtime.attempts().to_vec(),
+        elements_are![
+            matches_pattern!(AttemptOutcome::Failed(pat!(ErrorMessage {
+                message: eq("my-error"),
+                next_retry_delay: some(eq(5000))
+            }))),
+            matches_pattern!(AttemptOutcome::Failed(anything())),
+            eq(AttemptOutcome::Suspended {
+                waiting_on: vec![1]
+            }),
+            eq(AttemptOutcome::Ended)
+        ]
+    );
+    assert_eq!(runtime.elapsed(), Duration::from_secs(10));
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+    let mut runtime = MockRuntime::new(Bytes::new())
+        .with_version(VERSION)
+        .with_max_attempts(3)
+        .with_retry_delay(Duration::from_secs(2));
+
+    let status = runtime.run(|vm| {
+        vm.sys_input().unwrap();
+        vm.notify_error(Error::internal("my-error"), None);
+    });
+
+    assert_that!(
+        status,
+        pat!(InvocationStatus::GaveUp {
+            last_error: pat!(ErrorMessage {
+                message: eq("my-error"),
+            })
+        })
+    );
+    assert_eq!(runtime.attempts().len(), 3);
+    assert_eq!(runtime.elapsed(), Duration::from_secs(4));
+}
+
+#[test]
+fn promise_completed_by_the_same_invocation() {
+    let mut runtime = MockRuntime::new(Bytes::new())
+        .with_version(VERSION)
+        .with_key("my-workflow");
+
+    let status = runtime.run(|vm| {
+        vm.sys_input().unwrap();
+
+        let h = vm
+            .sys_complete_promise(
+                "my-promise".to_owned(),
+                NonEmptyValue::Success(Bytes::from_static(b"resolved")),
+                EntryOptions::default(),
+            )
+            .unwrap();
+        if await_handle(vm, h).is_none() {
+            return;
+        }
+
+        let h = vm
+            .sys_get_promise("my-promise".to_owned(), EntryOptions::default())
+            .unwrap();
+        let Some(Value::Success(value)) = await_handle(vm, h) else {
+            return;
+        };
+
+        end_with_output(vm, value);
+    });
+
+    assert_eq!(
+        status,
+        InvocationStatus::Ended {
+            output: Some(Ok(Bytes::from_static(b"resolved")))
+        }
+    );
+}
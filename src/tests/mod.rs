@@ -1,21 +1,58 @@
-mod async_result;
-mod calls;
-mod failures;
-mod get_state;
-mod input_output;
-mod promise;
-mod run;
-mod sleep;
-mod state;
-mod suspensions;
+// Every test module is compiled once per supported protocol version, with `VERSION` set to it.
+#[path = "."]
+#[allow(clippy::duplicate_mod)]
+mod v2 {
+    use super::*;
+
+    const VERSION: Version = Version::V2;
+
+    mod async_result;
+    mod calls;
+    mod determinism;
+    mod failures;
+    mod get_state;
+    mod input_output;
+    mod mock_runtime;
+    mod promise;
+    mod random;
+    mod run;
+    mod sleep;
+    mod snapshot;
+    mod state;
+    mod suspensions;
+}
+
+#[path = "."]
+#[allow(clippy::duplicate_mod)]
+mod v3 {
+    use super::*;
+
+    const VERSION: Version = Version::V3;
+
+    mod async_result;
+    mod calls;
+    mod determinism;
+    mod failures;
+    mod get_state;
+    mod input_output;
+    mod mock_runtime;
+    mod promise;
+    mod random;
+    mod run;
+    mod sleep;
+    mod snapshot;
+    mod state;
+    mod suspensions;
+}
 
 use super::*;
 
 use crate::service_protocol::messages::{
-    output_entry_message, run_entry_message, ErrorMessage, InputEntryMessage, OutputEntryMessage,
-    RestateMessage, RunEntryMessage, StartMessage, SuspensionMessage, WriteableRestateMessage,
+    output_entry_message, run_entry_message, ErrorMessage, OutputEntryMessage, RunEntryMessage,
+    SuspensionMessage,
 };
-use crate::service_protocol::{messages, Decoder, Encoder, RawMessage, Version};
+use crate::service_protocol::{messages, Version};
+use crate::test_utils::*;
 use bytes::Bytes;
 use googletest::prelude::*;
 use std::result::Result;
@@ -25,9 +62,13 @@ use test_log::test;
 
 impl CoreVM {
     fn mock_init(version: Version) -> CoreVM {
+        Self::mock_init_with_options(version, VMOptions::default())
+    }
+
+    fn mock_init_with_options(version: Version, options: VMOptions) -> CoreVM {
         let vm = CoreVM::new(
             vec![("content-type".to_owned(), version.to_string())],
-            VMOptions::default(),
+            options,
         )
         .unwrap();
 
@@ -43,72 +84,20 @@ impl CoreVM {
     }
 }
 
-struct VMTestCase {
-    encoder: Encoder,
-    vm: CoreVM,
-}
-
-impl VMTestCase {
-    fn new() -> Self {
-        Self {
-            encoder: Encoder::new(Version::maximum_supported_version()),
-            vm: CoreVM::mock_init(Version::maximum_supported_version()),
-        }
-    }
-
-    fn input<M: WriteableRestateMessage>(mut self, m: M) -> Self {
-        self.vm.notify_input(self.encoder.encode(&m));
-        self
-    }
-
-    fn run(mut self, user_code: impl FnOnce(&mut CoreVM)) -> OutputIterator {
-        self.vm.notify_input_closed();
-        assert!(self.vm.is_ready_to_execute().unwrap());
-
-        user_code(&mut self.vm);
-
-        OutputIterator::collect_vm(&mut self.vm)
-    }
-
-    fn run_without_closing_input(
-        mut self,
-        user_code: impl FnOnce(&mut CoreVM, &Encoder),
-    ) -> OutputIterator {
-        assert!(self.vm.is_ready_to_execute().unwrap());
-
-        user_code(&mut self.vm, &self.encoder);
-
-        OutputIterator::collect_vm(&mut self.vm)
+/// Awaits the handle, returning None if the VM suspended.
+pub fn await_handle(vm: &mut CoreVM, handle: AsyncResultHandle) -> Option<Value> {
+    vm.notify_await_point(handle);
+    match vm.take_async_result(handle) {
+        Ok(Some(value)) => Some(value),
+        Err(SuspendedOrVMError::Suspended(_)) => None,
+        res => panic!("Unexpected async result {res:?}"),
     }
 }
 
-struct OutputIterator(Decoder);
-
-impl OutputIterator {
-    fn collect_vm(vm: &mut impl VM) -> Self {
-        let mut decoder = Decoder::new(Version::maximum_supported_version());
-        while let TakeOutputResult::Buffer(b) = vm.take_output() {
-            decoder.push(b);
-        }
-        assert_eq!(vm.take_output(), TakeOutputResult::EOF);
-
-        Self(decoder)
-    }
-
-    fn next_decoded<M: RestateMessage>(&mut self) -> Option<M> {
-        self.0
-            .consume_next()
-            .unwrap()
-            .map(|msg| msg.decode_to::<M>().unwrap())
-    }
-}
-
-impl Iterator for OutputIterator {
-    type Item = RawMessage;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.consume_next().unwrap()
-    }
+pub fn end_with_output(vm: &mut CoreVM, output: Bytes) {
+    vm.sys_write_output(NonEmptyValue::Success(output), EntryOptions::default())
+        .unwrap();
+    vm.sys_end().unwrap();
 }
 
 // --- Matchers
@@ -186,34 +175,24 @@ pub fn is_output_with_failure(
     })
 }
 
-// --- Mocks
-
-pub fn start_message(known_entries: u32) -> StartMessage {
-    StartMessage {
-        id: Bytes::from_static(b"123"),
-        debug_id: "123".to_string(),
-        known_entries,
-        state_map: vec![],
-        partial_state: true,
-        key: "".to_string(),
-        retry_count_since_last_stored_entry: 0,
-        duration_since_last_stored_entry: 0,
-    }
-}
-
-pub fn input_entry_message(b: impl AsRef<[u8]>) -> InputEntryMessage {
-    InputEntryMessage {
-        headers: vec![],
-        value: Bytes::copy_from_slice(b.as_ref()),
-        ..InputEntryMessage::default()
+#[test]
+fn take_output_on_newly_initialized_vm() {
+    for version in SUPPORTED_VERSIONS {
+        let mut vm = CoreVM::mock_init(version);
+        assert_that!(
+            vm.take_output(),
+            eq(TakeOutputResult::Buffer(Bytes::default()))
+        );
     }
 }
 
 #[test]
-fn take_output_on_newly_initialized_vm() {
-    let mut vm = CoreVM::mock_init(Version::maximum_supported_version());
-    assert_that!(
-        vm.take_output(),
-        eq(TakeOutputResult::Buffer(Bytes::default()))
-    );
+fn reject_unsupported_version() {
+    let err = CoreVM::new(
+        vec![("content-type".to_owned(), Version::V1.to_string())],
+        VMOptions::default(),
+    )
+    .unwrap_err();
+
+    assert_eq!(err.code(), u16::from(error::codes::UNSUPPORTED_MEDIA_TYPE));
 }
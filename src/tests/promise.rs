@@ -10,7 +10,9 @@ mod get_promise {
     fn handler(vm: &mut CoreVM) {
         vm.sys_input().unwrap();
 
-        let h1 = vm.sys_get_promise("my-prom".to_owned()).unwrap();
+        let h1 = vm
+            .sys_get_promise("my-prom".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -26,13 +28,14 @@ mod get_promise {
             v => panic!("Unexpected value {v:?}"),
         };
 
-        vm.sys_write_output(output).unwrap();
+        vm.sys_write_output(output, EntryOptions::default())
+            .unwrap();
         vm.sys_end().unwrap();
     }
 
     #[test]
     fn completed_with_success() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -74,7 +77,7 @@ mod get_promise {
     }
     #[test]
     fn completed_with_failure() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -126,7 +129,9 @@ mod peek_promise {
     fn handler(vm: &mut CoreVM) {
         vm.sys_input().unwrap();
 
-        let h1 = vm.sys_peek_promise("my-prom".to_owned()).unwrap();
+        let h1 = vm
+            .sys_peek_promise("my-prom".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -140,13 +145,14 @@ mod peek_promise {
             v => panic!("Unexpected value {v:?}"),
         };
 
-        vm.sys_write_output(output).unwrap();
+        vm.sys_write_output(output, EntryOptions::default())
+            .unwrap();
         vm.sys_end().unwrap();
     }
 
     #[test]
     fn completed_with_success() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -188,7 +194,7 @@ mod peek_promise {
 
     #[test]
     fn completed_with_failure() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -232,7 +238,7 @@ mod peek_promise {
 
     #[test]
     fn completed_with_null() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -284,7 +290,7 @@ mod complete_promise {
             vm.sys_input().unwrap();
 
             let h1 = vm
-                .sys_complete_promise("my-prom".to_owned(), result)
+                .sys_complete_promise("my-prom".to_owned(), result, EntryOptions::default())
                 .unwrap();
             vm.notify_await_point(h1);
             let h1_result = vm.take_async_result(h1);
@@ -299,14 +305,15 @@ mod complete_promise {
                 v => panic!("Unexpected value {v:?}"),
             };
 
-            vm.sys_write_output(NonEmptyValue::Success(output)).unwrap();
+            vm.sys_write_output(NonEmptyValue::Success(output), EntryOptions::default())
+                .unwrap();
             vm.sys_end().unwrap();
         }
     }
 
     #[test]
     fn resolve_promise_succeeds() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -351,7 +358,7 @@ mod complete_promise {
 
     #[test]
     fn resolve_promise_fails() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -399,7 +406,7 @@ mod complete_promise {
 
     #[test]
     fn reject_promise_succeeds() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -448,7 +455,7 @@ mod complete_promise {
 
     #[test]
     fn reject_promise_fails() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -0,0 +1,108 @@
+use super::*;
+
+use crate::service_protocol::messages::{InputEntryMessage, SetStateEntryMessage, StartMessage};
+use assert2::let_assert;
+use test_log::test;
+
+fn generate(vm: &mut CoreVM) -> (u64, f64, String) {
+    let rng = vm.sys_random().unwrap();
+    (rng.next_u64(), rng.next_f64(), rng.next_uuid_v4())
+}
+
+#[test]
+fn seeded_with_input_random_seed() {
+    VMTestCase::with_version(VERSION)
+        .input(StartMessage {
+            id: Bytes::from_static(b"123"),
+            debug_id: "123".to_string(),
+            known_entries: 1,
+            ..Default::default()
+        })
+        .input(input_entry_message(b"my-data"))
+        .run(|vm| {
+            let_assert!(Input { random_seed, .. } = vm.sys_input().unwrap());
+
+            let mut expected = DeterministicRng::new(random_seed);
+            let rng = vm.sys_random().unwrap();
+            assert_eq!(rng.next_u64(), expected.next_u64());
+            assert_eq!(rng.next_u64(), expected.next_u64());
+
+            vm.sys_end().unwrap();
+        });
+}
+
+#[test]
+fn stable_across_replays() {
+    let mut first_attempt = None;
+    VMTestCase::with_version(VERSION)
+        .input(StartMessage {
+            id: Bytes::from_static(b"123"),
+            debug_id: "123".to_string(),
+            known_entries: 1,
+            ..Default::default()
+        })
+        .input(input_entry_message(b"my-data"))
+        .run(|vm| {
+            vm.sys_input().unwrap();
+            first_attempt = Some(generate(vm));
+            vm.sys_state_set(
+                "my-key".to_owned(),
+                Bytes::from_static(b"my-value"),
+                EntryOptions::default(),
+            )
+            .unwrap();
+            vm.sys_end().unwrap();
+        });
+
+    let mut second_attempt = None;
+    VMTestCase::with_version(VERSION)
+        .input(StartMessage {
+            id: Bytes::from_static(b"123"),
+            debug_id: "123".to_string(),
+            known_entries: 2,
+            ..Default::default()
+        })
+        .input(input_entry_message(b"my-data"))
+        .input(SetStateEntryMessage {
+            key: Bytes::from_static(b"my-key"),
+            value: Bytes::from_static(b"my-value"),
+            ..SetStateEntryMessage::default()
+        })
+        .run(|vm| {
+            vm.sys_input().unwrap();
+            // Generated while replaying
+            second_attempt = Some(generate(vm));
+            vm.sys_state_set(
+                "my-key".to_owned(),
+                Bytes::from_static(b"my-value"),
+                EntryOptions::default(),
+            )
+            .unwrap();
+            vm.sys_end().unwrap();
+        });
+
+    assert_eq!(first_attempt, second_attempt);
+}
+
+#[test]
+fn different_invocations_generate_different_values() {
+    let generate_for_id = |id: &'static [u8]| {
+        let mut values = None;
+        VMTestCase::with_version(VERSION)
+            .input(StartMessage {
+                id: Bytes::from_static(id),
+                debug_id: "123".to_string(),
+                known_entries: 1,
+                ..Default::default()
+            })
+            .input(InputEntryMessage::default())
+            .run(|vm| {
+                vm.sys_input().unwrap();
+                values = Some(generate(vm));
+                vm.sys_end().unwrap();
+            });
+        values.unwrap()
+    };
+
+    assert_ne!(generate_for_id(b"123"), generate_for_id(b"456"));
+}
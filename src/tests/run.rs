@@ -9,7 +9,7 @@ use test_log::test;
 
 #[test]
 fn run_guard() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -29,7 +29,41 @@ fn run_guard() {
                 RunEnterResult::NotExecuted { .. } = vm.sys_run_enter("".to_owned()).unwrap()
             );
             assert_that!(
-                vm.sys_state_get("Personaggio".to_owned()),
+                vm.sys_state_get("Personaggio".to_owned(), EntryOptions::default()),
+                err(eq_vm_error(vm::errors::INSIDE_RUN))
+            );
+        });
+
+    assert_that!(
+        output.next_decoded::<ErrorMessage>().unwrap(),
+        error_message_as_vm_error(vm::errors::INSIDE_RUN)
+    );
+    assert_eq!(output.next(), None);
+}
+
+#[test]
+fn random_guard() {
+    let mut output = VMTestCase::with_version(VERSION)
+        .input(StartMessage {
+            id: Bytes::from_static(b"123"),
+            debug_id: "123".to_string(),
+            known_entries: 1,
+            partial_state: false,
+            ..Default::default()
+        })
+        .input(InputEntryMessage {
+            headers: vec![],
+            value: Bytes::from_static(b"my-data"),
+            ..InputEntryMessage::default()
+        })
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            let_assert!(
+                RunEnterResult::NotExecuted { .. } = vm.sys_run_enter("".to_owned()).unwrap()
+            );
+            assert_that!(
+                vm.sys_random().map(|rng| rng.next_u64()),
                 err(eq_vm_error(vm::errors::INSIDE_RUN))
             );
         });
@@ -43,7 +77,7 @@ fn run_guard() {
 
 #[test]
 fn exit_without_enter() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -77,7 +111,7 @@ fn exit_without_enter() {
 
 #[test]
 fn enter_then_exit_then_suspend() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -97,12 +131,14 @@ fn enter_then_exit_then_suspend() {
                 RunEnterResult::NotExecuted { .. } =
                     vm.sys_run_enter("my-side-effect".to_owned()).unwrap()
             );
-            let handle = vm
-                .sys_run_exit(
-                    RunExitResult::Success(Bytes::from_static(b"123")),
-                    RetryPolicy::default(),
-                )
-                .unwrap();
+            let_assert!(
+                RunExitOutcome::Recorded(handle) = vm
+                    .sys_run_exit(
+                        RunExitResult::Success(Bytes::from_static(b"123")),
+                        RetryPolicy::default(),
+                    )
+                    .unwrap()
+            );
             vm.notify_await_point(handle);
 
             // Not yet closed, we could still receive the ack here
@@ -129,7 +165,7 @@ fn enter_then_exit_then_suspend() {
 
 #[test]
 fn enter_then_exit_then_ack() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -149,12 +185,14 @@ fn enter_then_exit_then_ack() {
                 RunEnterResult::NotExecuted { .. } =
                     vm.sys_run_enter("my-side-effect".to_owned()).unwrap()
             );
-            let handle = vm
-                .sys_run_exit(
-                    RunExitResult::Success(Bytes::from_static(b"123")),
-                    RetryPolicy::default(),
-                )
-                .unwrap();
+            let_assert!(
+                RunExitOutcome::Recorded(handle) = vm
+                    .sys_run_exit(
+                        RunExitResult::Success(Bytes::from_static(b"123")),
+                        RetryPolicy::default(),
+                    )
+                    .unwrap()
+            );
             vm.notify_await_point(handle);
 
             // Send the ack and close the input
@@ -166,7 +204,8 @@ fn enter_then_exit_then_ack() {
             let_assert!(Value::Success(s) = result);
 
             // Write the result as output
-            vm.sys_write_output(NonEmptyValue::Success(s)).unwrap();
+            vm.sys_write_output(NonEmptyValue::Success(s), EntryOptions::default())
+                .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -187,7 +226,7 @@ fn enter_then_exit_then_ack() {
 
 #[test]
 fn enter_then_exit_then_ack_with_failure() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"123"),
             debug_id: "123".to_string(),
@@ -207,15 +246,17 @@ fn enter_then_exit_then_ack_with_failure() {
                 RunEnterResult::NotExecuted { .. } =
                     vm.sys_run_enter("my-side-effect".to_owned()).unwrap()
             );
-            let handle = vm
-                .sys_run_exit(
-                    RunExitResult::TerminalFailure(TerminalFailure {
-                        code: 500,
-                        message: "my-failure".to_string(),
-                    }),
-                    RetryPolicy::default(),
-                )
-                .unwrap();
+            let_assert!(
+                RunExitOutcome::Recorded(handle) = vm
+                    .sys_run_exit(
+                        RunExitResult::TerminalFailure(TerminalFailure {
+                            code: 500,
+                            message: "my-failure".to_string(),
+                        }),
+                        RetryPolicy::default(),
+                    )
+                    .unwrap()
+            );
             vm.notify_await_point(handle);
 
             // Send the ack and close the input
@@ -227,7 +268,8 @@ fn enter_then_exit_then_ack_with_failure() {
             let_assert!(Value::Failure(f) = result);
 
             // Write the result as output
-            vm.sys_write_output(NonEmptyValue::Failure(f)).unwrap();
+            vm.sys_write_output(NonEmptyValue::Failure(f), EntryOptions::default())
+                .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -251,7 +293,7 @@ fn enter_then_exit_then_ack_with_failure() {
 
 #[test]
 fn replay() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(start_message(2))
         .input(input_entry_message(b"my-data"))
         .input(RunEntryMessage {
@@ -267,7 +309,8 @@ fn replay() {
             );
 
             // Write the result as output
-            vm.sys_write_output(NonEmptyValue::Success(s)).unwrap();
+            vm.sys_write_output(NonEmptyValue::Success(s), EntryOptions::default())
+                .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -281,7 +324,7 @@ fn replay() {
 
 #[test]
 fn enter_then_notify_error() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .run(|vm| {
@@ -318,12 +361,14 @@ mod consecutive_run {
 
         // First run
         let_assert!(RunEnterResult::NotExecuted { .. } = vm.sys_run_enter("".to_owned()).unwrap());
-        let h1 = vm
-            .sys_run_exit(
-                RunExitResult::Success(Bytes::from_static(b"Francesco")),
-                RetryPolicy::default(),
-            )
-            .unwrap();
+        let_assert!(
+            RunExitOutcome::Recorded(h1) = vm
+                .sys_run_exit(
+                    RunExitResult::Success(Bytes::from_static(b"Francesco")),
+                    RetryPolicy::default(),
+                )
+                .unwrap()
+        );
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -333,14 +378,16 @@ mod consecutive_run {
 
         // Second run
         let_assert!(RunEnterResult::NotExecuted { .. } = vm.sys_run_enter("".to_owned()).unwrap());
-        let h2 = vm
-            .sys_run_exit(
-                RunExitResult::Success(Bytes::from(
-                    String::from_utf8_lossy(&h1_value).to_uppercase(),
-                )),
-                RetryPolicy::default(),
-            )
-            .unwrap();
+        let_assert!(
+            RunExitOutcome::Recorded(h2) = vm
+                .sys_run_exit(
+                    RunExitResult::Success(Bytes::from(
+                        String::from_utf8_lossy(&h1_value).to_uppercase(),
+                    )),
+                    RetryPolicy::default(),
+                )
+                .unwrap()
+        );
         vm.notify_await_point(h2);
         let h2_result = vm.take_async_result(h2);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h2_result {
@@ -349,18 +396,21 @@ mod consecutive_run {
         let_assert!(Some(Value::Success(h2_value)) = h2_result.unwrap());
 
         // Write the result as output
-        vm.sys_write_output(NonEmptyValue::Success(
-            format!("Hello {}", String::from_utf8(h2_value.to_vec()).unwrap())
-                .into_bytes()
-                .into(),
-        ))
+        vm.sys_write_output(
+            NonEmptyValue::Success(
+                format!("Hello {}", String::from_utf8(h2_value.to_vec()).unwrap())
+                    .into_bytes()
+                    .into(),
+            ),
+            EntryOptions::default(),
+        )
         .unwrap();
         vm.sys_end().unwrap();
     }
 
     #[test]
     fn without_acks_suspends() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -394,7 +444,7 @@ mod consecutive_run {
     }
     #[test]
     fn ack_on_first_side_effect_will_suspend() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -438,7 +488,7 @@ mod consecutive_run {
     }
     #[test]
     fn ack_on_first_and_second_side_effect_will_resume() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -496,8 +546,9 @@ mod retry_policy {
         duration_since_last_stored_entry: Duration,
         attempt_duration: Duration,
         retry_policy: RetryPolicy,
+        classified_as_terminal: bool,
     ) {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 retry_count_since_last_stored_entry,
                 duration_since_last_stored_entry: duration_since_last_stored_entry.as_millis()
@@ -512,7 +563,7 @@ mod retry_policy {
                     RunEnterResult::NotExecuted { .. } =
                         vm.sys_run_enter("my-side-effect".to_owned()).unwrap()
                 );
-                let handle = vm
+                let outcome = vm
                     .sys_run_exit(
                         RunExitResult::RetryableFailure {
                             error: Error::internal("my-error"),
@@ -521,6 +572,15 @@ mod retry_policy {
                         retry_policy,
                     )
                     .unwrap();
+                if classified_as_terminal {
+                    assert!(matches!(
+                        outcome,
+                        RunExitOutcome::ClassifiedAsTerminal { .. }
+                    ));
+                } else {
+                    assert!(matches!(outcome, RunExitOutcome::GaveUp { .. }));
+                }
+                let_assert!(Some(handle) = outcome.handle());
 
                 vm.notify_await_point(handle);
                 let handle_result = vm.take_async_result(handle);
@@ -530,11 +590,14 @@ mod retry_policy {
                 let_assert!(Some(value) = handle_result.unwrap());
 
                 // Write the result as output
-                vm.sys_write_output(match value {
-                    Value::Success(s) => NonEmptyValue::Success(s),
-                    Value::Failure(f) => NonEmptyValue::Failure(f),
-                    v => panic!("Unexpected value {v:?}"),
-                })
+                vm.sys_write_output(
+                    match value {
+                        Value::Success(s) => NonEmptyValue::Success(s),
+                        Value::Failure(f) => NonEmptyValue::Failure(f),
+                        v => panic!("Unexpected value {v:?}"),
+                    },
+                    EntryOptions::default(),
+                )
                 .unwrap();
                 vm.sys_end().unwrap();
             });
@@ -561,7 +624,7 @@ mod retry_policy {
         retry_policy: RetryPolicy,
         next_retry_interval: Option<Duration>,
     ) {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 retry_count_since_last_stored_entry,
                 duration_since_last_stored_entry: duration_since_last_stored_entry.as_millis()
@@ -575,15 +638,25 @@ mod retry_policy {
                     RunEnterResult::NotExecuted { .. } =
                         vm.sys_run_enter("my-side-effect".to_owned()).unwrap()
                 );
-                assert!(vm
-                    .sys_run_exit(
-                        RunExitResult::RetryableFailure {
-                            error: Error::internal("my-error"),
-                            attempt_duration
-                        },
-                        retry_policy
-                    )
-                    .is_err());
+                let_assert!(
+                    RunExitOutcome::Retry {
+                        next_retry_delay,
+                        ..
+                    } = vm
+                        .sys_run_exit(
+                            RunExitResult::RetryableFailure {
+                                error: Error::internal("my-error")
+                                    .with_description("my-description"),
+                                attempt_duration
+                            },
+                            retry_policy
+                        )
+                        .unwrap()
+                );
+                assert_eq!(next_retry_delay, next_retry_interval);
+
+                // The attempt is failed
+                assert!(vm.sys_input().is_err());
             });
 
         assert_that!(
@@ -591,6 +664,7 @@ mod retry_policy {
             pat!(ErrorMessage {
                 code: eq(500),
                 message: eq("my-error".to_string()),
+                description: eq("my-description".to_string()),
                 next_retry_delay: eq(next_retry_interval.map(|d| d.as_millis() as u64))
             })
         );
@@ -610,7 +684,7 @@ mod retry_policy {
 
     #[test]
     fn exit_with_retryable_error_retry_policy_none() {
-        test_should_stop_retrying(0, Duration::ZERO, Duration::ZERO, RetryPolicy::None)
+        test_should_stop_retrying(0, Duration::ZERO, Duration::ZERO, RetryPolicy::None, false)
     }
 
     #[test]
@@ -628,6 +702,73 @@ mod retry_policy {
         );
     }
 
+    #[test]
+    fn exit_with_retryable_error_saturates_retry_info() {
+        test_should_continue_retrying(
+            u32::MAX,
+            Duration::from_millis(u64::MAX),
+            Duration::MAX,
+            RetryPolicy::Infinite,
+            None,
+        );
+    }
+
+    #[test]
+    fn exit_with_retryable_error_retry_policy_exponential_with_jitter() {
+        let retry_policy = RetryPolicy::exponential(
+            Duration::from_millis(100),
+            2.0,
+            None,
+            Some(Duration::from_secs(10)),
+            None,
+        )
+        .with_jitter(Jitter::Full);
+
+        // The run entry is the entry 1, retried for the 3rd time
+        let crate::retries::NextRetry::Retry(next_retry_interval) = retry_policy.next_retry(
+            EntryRetryInfo {
+                retry_count: 3,
+                retry_loop_duration: Duration::from_secs(2),
+            },
+            crate::random::compute_random_seed(b"123") ^ 1,
+        ) else {
+            panic!("Expected to retry")
+        };
+        assert!(next_retry_interval.unwrap() < Duration::from_millis(400));
+
+        test_should_continue_retrying(
+            2,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            retry_policy,
+            next_retry_interval,
+        );
+    }
+
+    #[test]
+    fn exit_with_retryable_error_classified_as_terminal() {
+        test_should_stop_retrying(
+            0,
+            Duration::ZERO,
+            Duration::ZERO,
+            RetryPolicy::Infinite.with_terminal_codes(500..=599),
+            true,
+        )
+    }
+
+    #[test]
+    fn exit_with_retryable_error_not_classified_as_terminal() {
+        test_should_continue_retrying(
+            0,
+            Duration::ZERO,
+            Duration::ZERO,
+            RetryPolicy::fixed_delay(Duration::from_secs(1), None, None)
+                .with_terminal_codes(400..=499)
+                .with_terminal_predicate(|e| e.message() == "my-fatal-error"),
+            Some(Duration::from_secs(1)),
+        )
+    }
+
     #[test]
     fn exit_with_retryable_error_retry_policy_exhausted_max_duration() {
         test_should_stop_retrying(
@@ -639,6 +780,7 @@ mod retry_policy {
                 max_attempts: None,
                 max_duration: Some(Duration::from_secs(2)),
             },
+            false,
         );
     }
 
@@ -653,12 +795,13 @@ mod retry_policy {
                 max_attempts: Some(10),
                 max_duration: None,
             },
+            false,
         );
     }
 
     #[test]
     fn retry_info_is_zero_when_entry_is_the_one_after_the_first_new_entry() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 retry_count_since_last_stored_entry: 10,
                 duration_since_last_stored_entry: Duration::from_secs(10).as_millis() as u64,
@@ -669,7 +812,7 @@ mod retry_policy {
                 vm.sys_input().unwrap();
 
                 // Just create another journal entry
-                vm.sys_awakeable().unwrap();
+                vm.sys_awakeable(EntryOptions::default()).unwrap();
 
                 // Now try to enter run
                 let_assert!(
@@ -682,19 +825,28 @@ mod retry_policy {
                 assert_eq!(retry_info.retry_count, 0);
                 assert_eq!(retry_info.retry_loop_duration, Duration::ZERO);
 
-                assert!(vm
-                    .sys_run_exit(
-                        RunExitResult::RetryableFailure {
-                            error: Error::internal("my-error"),
-                            attempt_duration: Duration::from_millis(99)
-                        },
-                        RetryPolicy::FixedDelay {
-                            interval: Duration::from_secs(1),
-                            max_attempts: Some(2),
-                            max_duration: Some(Duration::from_millis(100)),
-                        }
-                    )
-                    .is_err());
+                let_assert!(
+                    RunExitOutcome::Retry { retry_info, .. } = vm
+                        .sys_run_exit(
+                            RunExitResult::RetryableFailure {
+                                error: Error::internal("my-error"),
+                                attempt_duration: Duration::from_millis(99)
+                            },
+                            RetryPolicy::FixedDelay {
+                                interval: Duration::from_secs(1),
+                                max_attempts: Some(2),
+                                max_duration: Some(Duration::from_millis(100)),
+                            }
+                        )
+                        .unwrap()
+                );
+                assert_eq!(
+                    retry_info,
+                    EntryRetryInfo {
+                        retry_count: 1,
+                        retry_loop_duration: Duration::from_millis(99)
+                    }
+                );
             });
 
         let _ = output.next_decoded::<AwakeableEntryMessage>().unwrap();
@@ -7,7 +7,7 @@ use test_log::test;
 
 #[test]
 fn sleep_suspends() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"abc"),
             debug_id: "abc".to_owned(),
@@ -22,7 +22,9 @@ fn sleep_suspends() {
         .run(|vm| {
             vm.sys_input().unwrap();
 
-            let h1 = vm.sys_sleep(Duration::from_secs(1)).unwrap();
+            let h1 = vm
+                .sys_sleep(Duration::from_secs(1), EntryOptions::default())
+                .unwrap();
             vm.notify_await_point(h1);
             let h1_result = vm.take_async_result(h1);
             if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -30,8 +32,11 @@ fn sleep_suspends() {
             }
             let_assert!(Some(Value::Void) = h1_result.unwrap());
 
-            vm.sys_write_output(NonEmptyValue::Success(Bytes::default()))
-                .unwrap();
+            vm.sys_write_output(
+                NonEmptyValue::Success(Bytes::default()),
+                EntryOptions::default(),
+            )
+            .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -47,7 +52,7 @@ fn sleep_suspends() {
 
 #[test]
 fn sleep_completed() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"abc"),
             debug_id: "abc".to_owned(),
@@ -67,7 +72,9 @@ fn sleep_completed() {
         .run(|vm| {
             vm.sys_input().unwrap();
 
-            let h1 = vm.sys_sleep(Duration::from_secs(1)).unwrap();
+            let h1 = vm
+                .sys_sleep(Duration::from_secs(1), EntryOptions::default())
+                .unwrap();
             vm.notify_await_point(h1);
             let h1_result = vm.take_async_result(h1);
             if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -75,8 +82,11 @@ fn sleep_completed() {
             }
             let_assert!(Some(Value::Void) = h1_result.unwrap());
 
-            vm.sys_write_output(NonEmptyValue::Success(Bytes::default()))
-                .unwrap();
+            vm.sys_write_output(
+                NonEmptyValue::Success(Bytes::default()),
+                EntryOptions::default(),
+            )
+            .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -96,7 +106,7 @@ fn sleep_completed() {
 
 #[test]
 fn sleep_still_sleeping() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(StartMessage {
             id: Bytes::from_static(b"abc"),
             debug_id: "abc".to_owned(),
@@ -115,7 +125,9 @@ fn sleep_still_sleeping() {
         .run(|vm| {
             vm.sys_input().unwrap();
 
-            let h1 = vm.sys_sleep(Duration::from_secs(1)).unwrap();
+            let h1 = vm
+                .sys_sleep(Duration::from_secs(1), EntryOptions::default())
+                .unwrap();
             vm.notify_await_point(h1);
             let h1_result = vm.take_async_result(h1);
             if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -123,8 +135,11 @@ fn sleep_still_sleeping() {
             }
             let_assert!(Some(Value::Void) = h1_result.unwrap());
 
-            vm.sys_write_output(NonEmptyValue::Success(Bytes::default()))
-                .unwrap();
+            vm.sys_write_output(
+                NonEmptyValue::Success(Bytes::default()),
+                EntryOptions::default(),
+            )
+            .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -136,3 +151,53 @@ fn sleep_still_sleeping() {
     );
     assert_eq!(output.next(), None);
 }
+
+#[test]
+fn sleep_with_name() {
+    let mut output = VMTestCase::with_version(VERSION)
+        .input(start_message(1))
+        .input(input_entry_message(b"Till"))
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            let h1 = vm
+                .sys_sleep(Duration::from_secs(1), EntryOptions::named("my-sleep"))
+                .unwrap();
+            vm.notify_await_point(h1);
+            assert_that!(vm.take_async_result(h1), err(is_suspended()));
+        });
+
+    assert_that!(
+        output.next_decoded::<SleepEntryMessage>().unwrap(),
+        pat!(SleepEntryMessage {
+            name: eq("my-sleep")
+        })
+    );
+    let _ = output.next_decoded::<SuspensionMessage>().unwrap();
+    assert_eq!(output.next(), None);
+}
+
+#[test]
+fn sleep_duration_out_of_range() {
+    let mut output = VMTestCase::with_version(VERSION)
+        .input(start_message(1))
+        .input(input_entry_message(b"Till"))
+        .run(|vm| {
+            vm.sys_input().unwrap();
+
+            assert_that!(
+                vm.sys_sleep(Duration::MAX, EntryOptions::default()),
+                err(eq_vm_error(vm::errors::DURATION_OUT_OF_RANGE))
+            );
+
+            // The VM is still usable
+            end_with_output(vm, Bytes::default());
+        });
+
+    assert_that!(
+        output.next_decoded::<OutputEntryMessage>().unwrap(),
+        is_output_with_success(b"")
+    );
+    let _ = output.next_decoded::<EndMessage>().unwrap();
+    assert_eq!(output.next(), None);
+}
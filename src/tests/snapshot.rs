@@ -0,0 +1,170 @@
+use super::*;
+
+use crate::service_protocol::messages::{
+    get_state_entry_message, start_message::StateEntry, GetStateEntryMessage, SetStateEntryMessage,
+    SleepEntryMessage, StartMessage,
+};
+use crate::snapshot::EntrySnapshot;
+use assert2::let_assert;
+use test_log::test;
+
+#[test]
+fn snapshot_while_replaying() {
+    let mut vm = CoreVM::mock_init(VERSION);
+    let encoder = Encoder::new(VERSION);
+
+    vm.notify_input(encoder.encode(&StartMessage {
+        id: Bytes::from_static(b"123"),
+        debug_id: "123".to_string(),
+        known_entries: 3,
+        state_map: vec![StateEntry {
+            key: Bytes::from_static(b"my-key"),
+            value: Bytes::from_static(b"my-value"),
+        }],
+        partial_state: true,
+        ..Default::default()
+    }));
+    vm.notify_input(encoder.encode(&input_entry_message(b"my-data")));
+    vm.notify_input(encoder.encode(&SetStateEntryMessage {
+        key: Bytes::from_static(b"other-key"),
+        value: Bytes::from_static(b"other-value"),
+        name: "my-set".to_owned(),
+    }));
+    vm.notify_input(encoder.encode(&GetStateEntryMessage {
+        key: Bytes::from_static(b"my-key"),
+        result: Some(get_state_entry_message::Result::Value(Bytes::from_static(
+            b"my-value",
+        ))),
+        name: "my-get".to_owned(),
+    }));
+    assert!(vm.is_ready_to_execute().unwrap());
+
+    vm.sys_input().unwrap();
+
+    let snapshot = vm.snapshot();
+    assert_eq!(snapshot.invocation_id.as_deref(), Some("123"));
+    assert_eq!(snapshot.state, "Replaying");
+    assert!(snapshot.error.is_none());
+    assert_eq!(snapshot.journal_index, 0);
+    assert_eq!(snapshot.current_entry_type, "InputEntry");
+
+    assert_that!(
+        snapshot.replayed_entries,
+        elements_are![pat!(EntrySnapshot {
+            index: eq(0),
+            ty: eq("InputEntry"),
+        })]
+    );
+    assert_that!(
+        snapshot.entries_to_replay,
+        elements_are![
+            pat!(EntrySnapshot {
+                index: eq(1),
+                ty: eq("SetStateEntry"),
+                name: eq("my-set"),
+                completed: none(),
+            }),
+            pat!(EntrySnapshot {
+                index: eq(2),
+                ty: eq("GetStateEntry"),
+                name: eq("my-get"),
+                completed: some(eq(true)),
+            })
+        ]
+    );
+
+    let_assert!(Some(async_results) = snapshot.async_results);
+    assert!(async_results.ready_results.is_empty());
+
+    assert!(snapshot.eager_state.is_partial);
+    assert_eq!(
+        snapshot.eager_state.values.get("my-key"),
+        Some(&Some(Bytes::from_static(b"my-value")))
+    );
+}
+
+#[test]
+fn snapshot_while_processing() {
+    VMTestCase::with_version(VERSION)
+        .input(start_message(1))
+        .input(input_entry_message(b"my-data"))
+        .run(|vm| {
+            vm.sys_input().unwrap();
+            vm.sys_state_clear("my-key".to_owned(), EntryOptions::default())
+                .unwrap();
+            vm.sys_sleep(Duration::from_secs(1), EntryOptions::named("my-sleep"))
+                .unwrap();
+
+            let snapshot = vm.snapshot();
+            assert_eq!(snapshot.state, "Processing");
+            assert_eq!(snapshot.journal_index, 2);
+            assert_eq!(snapshot.current_entry_type, "SleepEntry");
+            assert_eq!(snapshot.current_entry_name, "my-sleep");
+            assert!(snapshot.entries_to_replay.is_empty());
+
+            let_assert!(Some(async_results) = snapshot.async_results);
+            assert_eq!(async_results.waiting_completion, vec![2]);
+
+            assert_eq!(snapshot.eager_state.values.get("my-key"), Some(&None));
+
+            vm.sys_end().unwrap();
+        });
+}
+
+#[test]
+fn snapshot_after_error() {
+    VMTestCase::with_version(VERSION)
+        .input(start_message(3))
+        .input(input_entry_message(b"my-data"))
+        .input(SleepEntryMessage {
+            wake_up_time: 1,
+            name: "my-sleep".to_owned(),
+            ..Default::default()
+        })
+        .input(SetStateEntryMessage {
+            key: Bytes::from_static(b"my-key"),
+            value: Bytes::from_static(b"my-value"),
+            ..Default::default()
+        })
+        .input(completion_with_empty(1))
+        .run(|vm| {
+            vm.sys_input().unwrap();
+            assert!(vm
+                .sys_sleep(
+                    Duration::from_millis(1),
+                    EntryOptions::named("another-sleep")
+                )
+                .is_err());
+
+            let snapshot = vm.snapshot();
+            assert_eq!(snapshot.state, "Errored");
+            let_assert!(Some(error) = snapshot.error);
+            assert_eq!(error.code(), u16::from(vm::errors::codes::JOURNAL_MISMATCH));
+            assert_eq!(snapshot.current_entry_name, "another-sleep");
+
+            // The journal and the async results survive the failure
+            assert_that!(
+                snapshot.replayed_entries,
+                elements_are![
+                    pat!(EntrySnapshot {
+                        index: eq(0),
+                        ty: eq("InputEntry"),
+                    }),
+                    pat!(EntrySnapshot {
+                        index: eq(1),
+                        ty: eq("SleepEntry"),
+                        name: eq("my-sleep"),
+                    })
+                ]
+            );
+            assert_that!(
+                snapshot.entries_to_replay,
+                elements_are![pat!(EntrySnapshot {
+                    index: eq(2),
+                    ty: eq("SetStateEntry"),
+                })]
+            );
+            let_assert!(Some(async_results) = snapshot.async_results);
+            assert_eq!(async_results.unparsed_completions, vec![1]);
+        });
+}
@@ -1,15 +1,38 @@
+use super::VERSION;
 use crate::service_protocol::messages::{start_message::StateEntry, *};
 use crate::tests::VMTestCase;
-use crate::{CoreVM, NonEmptyValue, SuspendedOrVMError, Value, VM};
+use crate::{CoreVM, EntryOptions, NonEmptyValue, SuspendedOrVMError, Value, VM};
 use assert2::let_assert;
 use bytes::Bytes;
 
+fn get_state_entry(
+    key: &'static [u8],
+    result: Option<get_state_entry_message::Result>,
+) -> GetStateEntryMessage {
+    GetStateEntryMessage {
+        key: Bytes::from_static(key),
+        result,
+        ..Default::default()
+    }
+}
+
+fn get_state_entry_with_value(key: &'static [u8], value: &'static [u8]) -> GetStateEntryMessage {
+    get_state_entry(
+        key,
+        Some(get_state_entry_message::Result::Value(Bytes::from_static(
+            value,
+        ))),
+    )
+}
+
 /// Normal state
 
 fn get_state_handler(vm: &mut CoreVM) {
     vm.sys_input().unwrap();
 
-    let h1 = vm.sys_state_get("STATE".to_owned()).unwrap();
+    let h1 = vm
+        .sys_state_get("STATE".to_owned(), EntryOptions::default())
+        .unwrap();
 
     vm.notify_await_point(h1);
     let h1_result = vm.take_async_result(h1);
@@ -21,16 +44,18 @@ fn get_state_handler(vm: &mut CoreVM) {
         Value::Void => "Unknown".to_owned(),
         Value::Success(s) => String::from_utf8(s.to_vec()).unwrap(),
         Value::Failure(f) => {
-            vm.sys_write_output(NonEmptyValue::Failure(f)).unwrap();
+            vm.sys_write_output(NonEmptyValue::Failure(f), EntryOptions::default())
+                .unwrap();
             vm.sys_end().unwrap();
             return;
         }
         _ => panic!("Unexpected variants"),
     };
 
-    vm.sys_write_output(NonEmptyValue::Success(Bytes::copy_from_slice(
-        str_result.as_bytes(),
-    )))
+    vm.sys_write_output(
+        NonEmptyValue::Success(Bytes::copy_from_slice(str_result.as_bytes())),
+        EntryOptions::default(),
+    )
     .unwrap();
     vm.sys_end().unwrap()
 }
@@ -42,7 +67,7 @@ mod only_lazy_state {
 
     #[test]
     fn entry_already_completed() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -80,7 +105,7 @@ mod only_lazy_state {
     }
     #[test]
     fn entry_already_completed_empty() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -116,7 +141,7 @@ mod only_lazy_state {
     }
     #[test]
     fn new_entry() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -148,7 +173,7 @@ mod only_lazy_state {
     }
     #[test]
     fn entry_not_completed_on_replay() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -177,7 +202,7 @@ mod only_lazy_state {
     }
     #[test]
     fn entry_on_replay_completed_later() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -218,7 +243,7 @@ mod only_lazy_state {
     }
     #[test]
     fn new_entry_completed_later() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -262,7 +287,7 @@ mod only_lazy_state {
     }
     #[test]
     fn replay_failed_get_state_entry() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -302,7 +327,7 @@ mod only_lazy_state {
     }
     #[test]
     fn complete_failing_get_state_entry() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -358,7 +383,9 @@ mod eager {
     fn get_empty_state_handler(vm: &mut CoreVM) {
         vm.sys_input().unwrap();
 
-        let h1 = vm.sys_state_get("STATE".to_owned()).unwrap();
+        let h1 = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
 
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
@@ -370,23 +397,25 @@ mod eager {
             Value::Void => "true".to_owned(),
             Value::Success(_) => "false".to_owned(),
             Value::Failure(f) => {
-                vm.sys_write_output(NonEmptyValue::Failure(f)).unwrap();
+                vm.sys_write_output(NonEmptyValue::Failure(f), EntryOptions::default())
+                    .unwrap();
                 vm.sys_end().unwrap();
                 return;
             }
             _ => panic!("Unexpected variants"),
         };
 
-        vm.sys_write_output(NonEmptyValue::Success(Bytes::copy_from_slice(
-            str_result.as_bytes(),
-        )))
+        vm.sys_write_output(
+            NonEmptyValue::Success(Bytes::copy_from_slice(str_result.as_bytes())),
+            EntryOptions::default(),
+        )
         .unwrap();
         vm.sys_end().unwrap()
     }
 
     #[test]
     fn get_empty_with_complete_state() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -422,7 +451,7 @@ mod eager {
 
     #[test]
     fn get_empty_with_partial_state() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -452,7 +481,7 @@ mod eager {
 
     #[test]
     fn get_empty_resume_with_partial_state() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -486,7 +515,7 @@ mod eager {
 
     #[test]
     fn get_with_complete_state() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -529,7 +558,7 @@ mod eager {
 
     #[test]
     fn get_with_partial_state() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -573,7 +602,7 @@ mod eager {
 
     #[test]
     fn get_with_partial_state_without_the_state_entry() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -603,7 +632,9 @@ mod eager {
     fn append_state_handler(vm: &mut CoreVM) {
         let input = vm.sys_input().unwrap().input;
 
-        let h1 = vm.sys_state_get("STATE".to_owned()).unwrap();
+        let h1 = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -615,7 +646,8 @@ mod eager {
             }
             Value::Success(s) => s,
             Value::Failure(f) => {
-                vm.sys_write_output(NonEmptyValue::Failure(f)).unwrap();
+                vm.sys_write_output(NonEmptyValue::Failure(f), EntryOptions::default())
+                    .unwrap();
                 vm.sys_end().unwrap();
                 return;
             }
@@ -625,10 +657,13 @@ mod eager {
         vm.sys_state_set(
             "STATE".to_owned(),
             Bytes::from([get_result.clone(), input.clone()].concat()),
+            EntryOptions::default(),
         )
         .unwrap();
 
-        let h2 = vm.sys_state_get("STATE".to_owned()).unwrap();
+        let h2 = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h2);
         let h2_result = vm.take_async_result(h2);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h2_result {
@@ -640,21 +675,25 @@ mod eager {
             }
             Value::Success(s) => s,
             Value::Failure(f) => {
-                vm.sys_write_output(NonEmptyValue::Failure(f)).unwrap();
+                vm.sys_write_output(NonEmptyValue::Failure(f), EntryOptions::default())
+                    .unwrap();
                 vm.sys_end().unwrap();
                 return;
             }
             _ => panic!("Unexpected variants"),
         };
 
-        vm.sys_write_output(NonEmptyValue::Success(second_get_result))
-            .unwrap();
+        vm.sys_write_output(
+            NonEmptyValue::Success(second_get_result),
+            EntryOptions::default(),
+        )
+        .unwrap();
         vm.sys_end().unwrap()
     }
 
     #[test]
     fn append_with_state_in_the_state_map() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -719,7 +758,7 @@ mod eager {
 
     #[test]
     fn append_with_partial_state_on_the_first_get() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -783,7 +822,9 @@ mod eager {
     fn get_and_clear_state_handler(vm: &mut CoreVM) {
         vm.sys_input().unwrap();
 
-        let h1 = vm.sys_state_get("STATE".to_owned()).unwrap();
+        let h1 = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -795,16 +836,20 @@ mod eager {
             }
             Value::Success(s) => s,
             Value::Failure(f) => {
-                vm.sys_write_output(NonEmptyValue::Failure(f)).unwrap();
+                vm.sys_write_output(NonEmptyValue::Failure(f), EntryOptions::default())
+                    .unwrap();
                 vm.sys_end().unwrap();
                 return;
             }
             _ => panic!("Unexpected variants"),
         };
 
-        vm.sys_state_clear("STATE".to_owned()).unwrap();
+        vm.sys_state_clear("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
 
-        let h2 = vm.sys_state_get("STATE".to_owned()).unwrap();
+        let h2 = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h2);
         let h2_result = vm.take_async_result(h2);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h2_result {
@@ -812,14 +857,17 @@ mod eager {
         }
         let_assert!(Ok(Some(Value::Void)) = h2_result);
 
-        vm.sys_write_output(NonEmptyValue::Success(first_get_result))
-            .unwrap();
+        vm.sys_write_output(
+            NonEmptyValue::Success(first_get_result),
+            EntryOptions::default(),
+        )
+        .unwrap();
         vm.sys_end().unwrap()
     }
 
     #[test]
     fn get_and_clear_state_with_state_in_the_state_map() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -881,7 +929,7 @@ mod eager {
 
     #[test]
     fn get_and_clear_state_with_partial_state_on_the_first_get() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -942,7 +990,9 @@ mod eager {
     fn get_and_clear_all_state_handler(vm: &mut CoreVM) {
         vm.sys_input().unwrap();
 
-        let h1 = vm.sys_state_get("STATE".to_owned()).unwrap();
+        let h1 = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
         if let Err(SuspendedOrVMError::Suspended(_)) = &h1_result {
@@ -954,31 +1004,39 @@ mod eager {
             }
             Value::Success(s) => s,
             Value::Failure(f) => {
-                vm.sys_write_output(NonEmptyValue::Failure(f)).unwrap();
+                vm.sys_write_output(NonEmptyValue::Failure(f), EntryOptions::default())
+                    .unwrap();
                 vm.sys_end().unwrap();
                 return;
             }
             _ => panic!("Unexpected variants"),
         };
 
-        vm.sys_state_clear_all().unwrap();
+        vm.sys_state_clear_all(EntryOptions::default()).unwrap();
 
-        let h2 = vm.sys_state_get("STATE".to_owned()).unwrap();
+        let h2 = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h2);
         let_assert!(Ok(Some(Value::Void)) = vm.take_async_result(h2));
 
-        let h3 = vm.sys_state_get("ANOTHER_STATE".to_owned()).unwrap();
+        let h3 = vm
+            .sys_state_get("ANOTHER_STATE".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h3);
         let_assert!(Ok(Some(Value::Void)) = vm.take_async_result(h3));
 
-        vm.sys_write_output(NonEmptyValue::Success(first_get_result))
-            .unwrap();
+        vm.sys_write_output(
+            NonEmptyValue::Success(first_get_result),
+            EntryOptions::default(),
+        )
+        .unwrap();
         vm.sys_end().unwrap()
     }
 
     #[test]
     fn get_clear_all_with_state_in_the_state_map() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1051,7 +1109,7 @@ mod eager {
 
     #[test]
     fn get_clear_all_with_partial_state_on_the_first_get() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1117,22 +1175,29 @@ mod eager {
     fn consecutive_get_with_empty_handler(vm: &mut CoreVM) {
         vm.sys_input().unwrap();
 
-        let h1 = vm.sys_state_get("key-0".to_owned()).unwrap();
+        let h1 = vm
+            .sys_state_get("key-0".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h1);
         let_assert!(Ok(Some(Value::Void)) = vm.take_async_result(h1));
 
-        let h2 = vm.sys_state_get("key-0".to_owned()).unwrap();
+        let h2 = vm
+            .sys_state_get("key-0".to_owned(), EntryOptions::default())
+            .unwrap();
         vm.notify_await_point(h2);
         let_assert!(Ok(Some(Value::Void)) = vm.take_async_result(h2));
 
-        vm.sys_write_output(NonEmptyValue::Success(Bytes::default()))
-            .unwrap();
+        vm.sys_write_output(
+            NonEmptyValue::Success(Bytes::default()),
+            EntryOptions::default(),
+        )
+        .unwrap();
         vm.sys_end().unwrap()
     }
 
     #[test]
     fn consecutive_get_with_empty() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1174,7 +1239,7 @@ mod eager {
 
     #[test]
     fn consecutive_get_with_empty_run_with_replay_of_the_first_get() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1223,7 +1288,7 @@ mod state_keys {
     fn get_state_keys_handler(vm: &mut CoreVM) {
         vm.sys_input().unwrap();
 
-        let h1 = vm.sys_state_get_keys().unwrap();
+        let h1 = vm.sys_state_get_keys(EntryOptions::default()).unwrap();
 
         vm.notify_await_point(h1);
         let h1_result = vm.take_async_result(h1);
@@ -1237,13 +1302,14 @@ mod state_keys {
             _ => panic!("Unexpected variants"),
         };
 
-        vm.sys_write_output(output).unwrap();
+        vm.sys_write_output(output, EntryOptions::default())
+            .unwrap();
         vm.sys_end().unwrap()
     }
 
     #[test]
     fn entry_already_completed() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1283,7 +1349,7 @@ mod state_keys {
     }
     #[test]
     fn new_entry() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1320,7 +1386,7 @@ mod state_keys {
         }
         .encode_to_vec()
         .into();
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1367,7 +1433,7 @@ mod state_keys {
         }
         .encode_to_vec()
         .into();
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1403,7 +1469,7 @@ mod state_keys {
     }
     #[test]
     fn new_entry_completed_with_eager_state() {
-        let mut output = VMTestCase::new()
+        let mut output = VMTestCase::with_version(VERSION)
             .input(StartMessage {
                 id: Bytes::from_static(b"abc"),
                 debug_id: "abc".to_owned(),
@@ -1457,3 +1523,862 @@ mod state_keys {
         assert_eq!(output.next(), None);
     }
 }
+
+mod read_through {
+    use super::*;
+
+    use crate::service_protocol::messages::get_state_keys_entry_message::StateKeys;
+    use crate::service_protocol::Encoder;
+    use crate::test_utils::{
+        completion_with_value, input_entry_message, start_message, OutputIterator,
+    };
+    use crate::tests::end_with_output;
+    use googletest::prelude::*;
+    use prost::Message;
+    use test_log::test;
+
+    fn get_state(vm: &mut CoreVM, key: &str) -> Value {
+        let h = vm
+            .sys_state_get(key.to_owned(), EntryOptions::default())
+            .unwrap();
+        vm.notify_await_point(h);
+        vm.take_async_result(h).unwrap().unwrap()
+    }
+
+    #[test]
+    fn lazy_get_result_is_cached() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(1))
+            .input(input_entry_message(b"my-data"))
+            .input(completion_with_value(1, b"Francesco"))
+            .run(|vm| {
+                vm.sys_input().unwrap();
+
+                assert_eq!(
+                    get_state(vm, "STATE"),
+                    Value::Success(Bytes::from_static(b"Francesco"))
+                );
+                // Completed with the result of the first get
+                assert_eq!(
+                    get_state(vm, "STATE"),
+                    Value::Success(Bytes::from_static(b"Francesco"))
+                );
+
+                end_with_output(vm, Bytes::new());
+            });
+
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry(b"STATE", None)
+        );
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry_with_value(b"STATE", b"Francesco")
+        );
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn replayed_get_result_is_cached() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(2))
+            .input(input_entry_message(b"my-data"))
+            .input(get_state_entry_with_value(b"STATE", b"Francesco"))
+            .run(|vm| {
+                vm.sys_input().unwrap();
+
+                get_state(vm, "STATE");
+                assert_eq!(
+                    get_state(vm, "STATE"),
+                    Value::Success(Bytes::from_static(b"Francesco"))
+                );
+
+                end_with_output(vm, Bytes::new());
+            });
+
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry_with_value(b"STATE", b"Francesco")
+        );
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn lazy_get_result_completed_after_set_is_discarded() {
+        let mut vm = CoreVM::mock_init(VERSION);
+        let encoder = Encoder::new(VERSION);
+        vm.notify_input(encoder.encode(&start_message(1)));
+        vm.notify_input(encoder.encode(&input_entry_message(b"my-data")));
+        assert!(vm.is_ready_to_execute().unwrap());
+
+        vm.sys_input().unwrap();
+        let h1 = vm
+            .sys_state_get("STATE".to_owned(), EntryOptions::default())
+            .unwrap();
+        vm.sys_state_set(
+            "STATE".to_owned(),
+            Bytes::from_static(b"Till"),
+            EntryOptions::default(),
+        )
+        .unwrap();
+
+        // The completion of the first get is received after the set
+        vm.notify_input(encoder.encode(&completion_with_value(1, b"Francesco")));
+        assert_eq!(
+            vm.take_async_result(h1).unwrap(),
+            Some(Value::Success(Bytes::from_static(b"Francesco")))
+        );
+        assert_eq!(
+            get_state(&mut vm, "STATE"),
+            Value::Success(Bytes::from_static(b"Till"))
+        );
+
+        end_with_output(&mut vm, Bytes::new());
+
+        let mut output = OutputIterator::collect_vm(&mut vm);
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry(b"STATE", None)
+        );
+        output.next_decoded::<SetStateEntryMessage>().unwrap();
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry_with_value(b"STATE", b"Till")
+        );
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn lazy_get_keys_result_is_cached() {
+        let state_keys: Bytes = StateKeys {
+            keys: vec![Bytes::from_static(b"A")],
+        }
+        .encode_to_vec()
+        .into();
+
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(1))
+            .input(input_entry_message(b"my-data"))
+            .input(CompletionMessage {
+                entry_index: 1,
+                result: Some(completion_message::Result::Value(state_keys)),
+            })
+            .run(|vm| {
+                vm.sys_input().unwrap();
+
+                let h = vm.sys_state_get_keys(EntryOptions::default()).unwrap();
+                vm.notify_await_point(h);
+                vm.take_async_result(h).unwrap().unwrap();
+
+                // B is not listed, so it's empty
+                assert_eq!(get_state(vm, "B"), Value::Void);
+                vm.sys_state_set(
+                    "C".to_owned(),
+                    Bytes::from_static(b"Till"),
+                    EntryOptions::default(),
+                )
+                .unwrap();
+                let h = vm.sys_state_get_keys(EntryOptions::default()).unwrap();
+                vm.notify_await_point(h);
+                assert_eq!(
+                    vm.take_async_result(h).unwrap(),
+                    Some(Value::StateKeys(vec!["A".to_owned(), "C".to_owned()]))
+                );
+
+                // The value of A is still unknown, so it's fetched
+                let h = vm
+                    .sys_state_get("A".to_owned(), EntryOptions::default())
+                    .unwrap();
+                vm.notify_await_point(h);
+                vm.notify_input_closed();
+                assert_that!(
+                    vm.take_async_result(h),
+                    err(pat!(SuspendedOrVMError::Suspended(_)))
+                );
+            });
+
+        assert_eq!(
+            output.next_decoded::<GetStateKeysEntryMessage>().unwrap(),
+            GetStateKeysEntryMessage::default()
+        );
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            GetStateEntryMessage {
+                key: Bytes::from_static(b"B"),
+                result: Some(get_state_entry_message::Result::Empty(Empty::default())),
+                ..Default::default()
+            }
+        );
+        output.next_decoded::<SetStateEntryMessage>().unwrap();
+        assert_that!(
+            output.next_decoded::<GetStateKeysEntryMessage>().unwrap(),
+            pat!(GetStateKeysEntryMessage {
+                result: some(pat!(get_state_keys_entry_message::Result::Value(pat!(
+                    StateKeys {
+                        keys: unordered_elements_are!(
+                            eq(Bytes::from_static(b"A")),
+                            eq(Bytes::from_static(b"C"))
+                        )
+                    }
+                ))))
+            })
+        );
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry(b"A", None)
+        );
+        output.next_decoded::<SuspensionMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+}
+
+mod get_many {
+    use super::super::async_result::FirstCompleted;
+    use super::*;
+
+    use crate::service_protocol::Version;
+    use crate::test_utils::{
+        completion_with_empty, completion_with_failure, completion_with_value, input_entry_message,
+        start_message,
+    };
+    use crate::tests::{end_with_output, eq_vm_error};
+    use crate::vm::errors::EMPTY_STATE_GET_MANY_KEYS;
+    use googletest::prelude::*;
+    use std::collections::BTreeMap;
+    use test_log::test;
+
+    fn get_many_handler(vm: &mut CoreVM) {
+        vm.sys_input().unwrap();
+
+        let h = vm
+            .sys_state_get_many(
+                vec![
+                    "A".to_owned(),
+                    "B".to_owned(),
+                    "C".to_owned(),
+                    "A".to_owned(),
+                ],
+                EntryOptions::default(),
+            )
+            .unwrap();
+        vm.notify_await_point(h);
+        let output = match vm.take_async_result(h) {
+            Err(SuspendedOrVMError::Suspended(_)) => return,
+            Ok(Some(Value::StateValues(values))) => format!("{values:?}"),
+            Ok(Some(Value::Failure(f))) => f.message,
+            res => panic!("Unexpected result {res:?}"),
+        };
+
+        end_with_output(vm, Bytes::from(output));
+    }
+
+    fn start_with_a_in_state_map() -> StartMessage {
+        StartMessage {
+            state_map: vec![StateEntry {
+                key: Bytes::from_static(b"A"),
+                value: Bytes::from_static(b"Francesco"),
+            }],
+            ..start_message(1)
+        }
+    }
+
+    #[test]
+    fn eager_and_lazy_keys() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_with_a_in_state_map())
+            .input(input_entry_message(b"my-data"))
+            .input(completion_with_value(1, b"Till"))
+            .input(completion_with_empty(2))
+            .run(get_many_handler);
+
+        // A is resolved from the eager state, without an entry
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry(b"B", None)
+        );
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry(b"C", None)
+        );
+        assert_eq!(
+            output.next_decoded::<OutputEntryMessage>().unwrap(),
+            OutputEntryMessage {
+                result: Some(output_entry_message::Result::Value(Bytes::from(format!(
+                    "{:?}",
+                    BTreeMap::from([
+                        ("A".to_owned(), Some(Bytes::from_static(b"Francesco"))),
+                        ("B".to_owned(), Some(Bytes::from_static(b"Till"))),
+                        ("C".to_owned(), None),
+                    ])
+                )))),
+                ..Default::default()
+            }
+        );
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn suspends_on_the_lazy_keys() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_with_a_in_state_map())
+            .input(input_entry_message(b"my-data"))
+            .input(completion_with_value(1, b"Till"))
+            .run(get_many_handler);
+
+        output.next_decoded::<GetStateEntryMessage>().unwrap();
+        output.next_decoded::<GetStateEntryMessage>().unwrap();
+        assert_eq!(
+            output.next_decoded::<SuspensionMessage>().unwrap(),
+            SuspensionMessage {
+                entry_indexes: vec![2]
+            }
+        );
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn all_keys_in_eager_state() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(StartMessage {
+                state_map: vec![StateEntry {
+                    key: Bytes::from_static(b"A"),
+                    value: Bytes::from_static(b"Francesco"),
+                }],
+                partial_state: false,
+                ..start_message(1)
+            })
+            .input(input_entry_message(b"my-data"))
+            .run(get_many_handler);
+
+        assert_eq!(
+            output.next_decoded::<OutputEntryMessage>().unwrap(),
+            OutputEntryMessage {
+                result: Some(output_entry_message::Result::Value(Bytes::from(format!(
+                    "{:?}",
+                    BTreeMap::from([
+                        ("A".to_owned(), Some(Bytes::from_static(b"Francesco"))),
+                        ("B".to_owned(), None),
+                        ("C".to_owned(), None),
+                    ])
+                )))),
+                ..Default::default()
+            }
+        );
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn replay_with_failure() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(3))
+            .input(input_entry_message(b"my-data"))
+            .input(get_state_entry(
+                b"A",
+                Some(get_state_entry_message::Result::Empty(Empty::default())),
+            ))
+            .input(get_state_entry(b"B", None))
+            .input(completion_with_failure(2, 500, "my-failure"))
+            .input(completion_with_empty(3))
+            .run(get_many_handler);
+
+        assert_eq!(
+            output.next_decoded::<GetStateEntryMessage>().unwrap(),
+            get_state_entry(b"C", None)
+        );
+        assert_eq!(
+            output.next_decoded::<OutputEntryMessage>().unwrap(),
+            OutputEntryMessage {
+                result: Some(output_entry_message::Result::Value(Bytes::from_static(
+                    b"my-failure"
+                ))),
+                ..Default::default()
+            }
+        );
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn combinator_waits_for_all_the_keys() {
+        let mut output = VMTestCase::with_version(Version::V3)
+            .input(start_message(1))
+            .input(input_entry_message(b"my-data"))
+            .input(completion_with_value(1, b"Francesco"))
+            .run_without_closing_input(|vm, encoder| {
+                vm.sys_input().unwrap();
+
+                let h = vm
+                    .sys_state_get_many(
+                        vec!["A".to_owned(), "B".to_owned()],
+                        EntryOptions::default(),
+                    )
+                    .unwrap();
+                assert_eq!(
+                    vm.sys_try_complete_combinator(FirstCompleted(vec![h]))
+                        .unwrap(),
+                    None
+                );
+
+                vm.notify_input(encoder.encode(&completion_with_empty(2)));
+                assert!(vm
+                    .sys_try_complete_combinator(FirstCompleted(vec![h]))
+                    .unwrap()
+                    .is_some());
+
+                end_with_output(vm, Bytes::new());
+            });
+
+        output.next_decoded::<GetStateEntryMessage>().unwrap();
+        output.next_decoded::<GetStateEntryMessage>().unwrap();
+        assert_eq!(
+            output.next_decoded::<CombinatorEntryMessage>().unwrap(),
+            CombinatorEntryMessage {
+                // Handles of sys_state_get_many are allocated from u32::MAX
+                completed_entries_order: vec![u32::MAX],
+                ..Default::default()
+            }
+        );
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn empty_keys() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(1))
+            .input(input_entry_message(b"my-data"))
+            .run(|vm| {
+                vm.sys_input().unwrap();
+
+                assert_that!(
+                    vm.sys_state_get_many(vec![], EntryOptions::default()),
+                    err(eq_vm_error(EMPTY_STATE_GET_MANY_KEYS))
+                );
+
+                // The invocation can go on
+                end_with_output(vm, Bytes::new());
+            });
+
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+}
+
+mod write_validation {
+    use super::*;
+
+    use crate::test_utils::{input_entry_message, start_message};
+    use crate::tests::{end_with_output, is_output_with_failure};
+    use crate::{Error, TerminalFailure, VMOptions, VMResult};
+    use googletest::prelude::*;
+    use test_log::test;
+
+    fn options() -> VMOptions {
+        VMOptions {
+            max_state_key_length: Some(8),
+            max_state_value_size: Some(4),
+            reserved_state_key_prefixes: vec!["_sdk.".to_owned()],
+            ..VMOptions::default()
+        }
+    }
+
+    fn test_rejected_write(
+        write: impl FnOnce(&mut CoreVM) -> VMResult<()>,
+        expected_message: &str,
+    ) {
+        let mut output = VMTestCase::with_version_and_vm_options(VERSION, options())
+            .input(start_message(1))
+            .input(input_entry_message(b"my-data"))
+            .run(|vm| {
+                vm.sys_input().unwrap();
+
+                let error = write(vm).unwrap_err();
+                assert_that!(
+                    error,
+                    pat!(Error {
+                        code: eq(400),
+                        message: eq(expected_message)
+                    })
+                );
+
+                // The VM is not failed, so the SDK can end the invocation with a terminal failure
+                vm.sys_write_output(
+                    NonEmptyValue::Failure(TerminalFailure {
+                        code: error.code(),
+                        message: error.message().to_owned(),
+                    }),
+                    EntryOptions::default(),
+                )
+                .unwrap();
+                vm.sys_end().unwrap();
+            });
+
+        // Nothing else was written to the journal, and no ErrorMessage asks the runtime to retry
+        assert_that!(
+            output.next_decoded::<OutputEntryMessage>().unwrap(),
+            is_output_with_failure(400, expected_message)
+        );
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn key_too_long() {
+        test_rejected_write(
+            |vm| {
+                vm.sys_state_set(
+                    "my-long-key".to_owned(),
+                    Bytes::from_static(b"1"),
+                    EntryOptions::default(),
+                )
+            },
+            "State key of 11 bytes exceeds the maximum length of 8 bytes",
+        );
+    }
+
+    #[test]
+    fn value_too_large() {
+        test_rejected_write(
+            |vm| {
+                vm.sys_state_set(
+                    "my-key".to_owned(),
+                    Bytes::from_static(b"Francesco"),
+                    EntryOptions::default(),
+                )
+            },
+            "Value of state key 'my-key' of 9 bytes exceeds the maximum size of 4 bytes",
+        );
+    }
+
+    #[test]
+    fn clear_reserved_prefix() {
+        test_rejected_write(
+            |vm| vm.sys_state_clear("_sdk.key".to_owned(), EntryOptions::default()),
+            "State key '_sdk.key' starts with the prefix '_sdk.', reserved for the SDK",
+        );
+    }
+
+    #[test]
+    fn valid_writes() {
+        let mut output = VMTestCase::with_version_and_vm_options(VERSION, options())
+            .input(start_message(1))
+            .input(input_entry_message(b"my-data"))
+            .run(|vm| {
+                vm.sys_input().unwrap();
+
+                vm.sys_state_set(
+                    "my-key".to_owned(),
+                    Bytes::from_static(b"Till"),
+                    EntryOptions::default(),
+                )
+                .unwrap();
+                vm.sys_state_clear("sdk.key".to_owned(), EntryOptions::default())
+                    .unwrap();
+
+                end_with_output(vm, Bytes::new());
+            });
+
+        output.next_decoded::<SetStateEntryMessage>().unwrap();
+        output.next_decoded::<ClearStateEntryMessage>().unwrap();
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+}
+
+mod state_changes {
+    use super::*;
+
+    use crate::test_utils::{input_entry_message, start_message};
+    use crate::tests::end_with_output;
+    use crate::StateChanges;
+    use std::collections::BTreeMap;
+    use test_log::test;
+
+    #[test]
+    fn includes_replayed_changes() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(2))
+            .input(input_entry_message(b"my-data"))
+            .input(SetStateEntryMessage {
+                key: Bytes::from_static(b"A"),
+                value: Bytes::from_static(b"Francesco"),
+                ..Default::default()
+            })
+            .run(|vm| {
+                vm.sys_input().unwrap();
+                assert_eq!(vm.state_changes(), StateChanges::default());
+
+                vm.sys_state_set(
+                    "A".to_owned(),
+                    Bytes::from_static(b"Francesco"),
+                    EntryOptions::default(),
+                )
+                .unwrap();
+                vm.sys_state_clear("B".to_owned(), EntryOptions::default())
+                    .unwrap();
+                assert_eq!(
+                    vm.state_changes(),
+                    StateChanges {
+                        cleared_all: false,
+                        values: BTreeMap::from([
+                            ("A".to_owned(), Some(Bytes::from_static(b"Francesco"))),
+                            ("B".to_owned(), None),
+                        ]),
+                    }
+                );
+
+                vm.sys_state_clear_all(EntryOptions::default()).unwrap();
+                vm.sys_state_set(
+                    "C".to_owned(),
+                    Bytes::from_static(b"Till"),
+                    EntryOptions::default(),
+                )
+                .unwrap();
+                assert_eq!(
+                    vm.state_changes(),
+                    StateChanges {
+                        cleared_all: true,
+                        values: BTreeMap::from([(
+                            "C".to_owned(),
+                            Some(Bytes::from_static(b"Till"))
+                        )]),
+                    }
+                );
+
+                end_with_output(vm, Bytes::new());
+            });
+
+        output.next_decoded::<ClearStateEntryMessage>().unwrap();
+        output.next_decoded::<ClearAllStateEntryMessage>().unwrap();
+        output.next_decoded::<SetStateEntryMessage>().unwrap();
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn rejected_writes_are_not_changes() {
+        VMTestCase::with_version(VERSION)
+            .input(start_message(1))
+            .input(input_entry_message(b"my-data"))
+            .run(|vm| {
+                vm.sys_input().unwrap();
+                vm.sys_state_set(
+                    "A".to_owned(),
+                    Bytes::from_static(b"Francesco"),
+                    EntryOptions::default(),
+                )
+                .unwrap();
+
+                vm.sys_run_enter("my-run".to_owned()).unwrap();
+                assert!(vm
+                    .sys_state_set(
+                        "B".to_owned(),
+                        Bytes::from_static(b"Till"),
+                        EntryOptions::default(),
+                    )
+                    .is_err());
+                assert!(vm.sys_state_clear_all(EntryOptions::default()).is_err());
+
+                assert_eq!(
+                    vm.state_changes(),
+                    StateChanges {
+                        cleared_all: false,
+                        values: BTreeMap::from([(
+                            "A".to_owned(),
+                            Some(Bytes::from_static(b"Francesco"))
+                        )]),
+                    }
+                );
+            });
+    }
+
+    #[test]
+    fn reads_are_not_changes() {
+        VMTestCase::with_version(VERSION)
+            .input(StartMessage {
+                state_map: vec![StateEntry {
+                    key: Bytes::from_static(b"A"),
+                    value: Bytes::from_static(b"Francesco"),
+                }],
+                ..start_message(1)
+            })
+            .input(input_entry_message(b"my-data"))
+            .run(|vm| {
+                vm.sys_input().unwrap();
+                vm.sys_state_get("A".to_owned(), EntryOptions::default())
+                    .unwrap();
+                assert_eq!(vm.state_changes(), StateChanges::default());
+
+                end_with_output(vm, Bytes::new());
+            });
+    }
+}
+
+mod state_keys_with_prefix {
+    use super::*;
+
+    use crate::service_protocol::messages::get_state_keys_entry_message::StateKeys;
+    use crate::test_utils::{input_entry_message, start_message};
+    use crate::tests::end_with_output;
+    use crate::StateKeysQuery;
+    use prost::Message;
+    use test_log::test;
+
+    fn state_keys(keys: &[&'static str]) -> Vec<Bytes> {
+        keys.iter()
+            .map(|k| Bytes::from_static(k.as_bytes()))
+            .collect()
+    }
+
+    const KEYS: [&str; 4] = ["user/1", "order/3", "order/1", "order/2"];
+
+    fn get_order_keys(vm: &mut CoreVM, start_after: Option<&str>) -> Value {
+        let h = vm
+            .sys_state_get_keys_with_prefix(
+                StateKeysQuery {
+                    prefix: "order/".to_owned(),
+                    start_after: start_after.map(ToOwned::to_owned),
+                    limit: Some(2),
+                },
+                EntryOptions::default(),
+            )
+            .unwrap();
+        vm.notify_await_point(h);
+        vm.take_async_result(h).unwrap().unwrap()
+    }
+
+    fn paginate_order_keys_handler(vm: &mut CoreVM) {
+        vm.sys_input().unwrap();
+
+        assert_eq!(
+            get_order_keys(vm, None),
+            Value::StateKeys(vec!["order/1".to_owned(), "order/2".to_owned()])
+        );
+        assert_eq!(
+            get_order_keys(vm, Some("order/2")),
+            Value::StateKeys(vec!["order/3".to_owned()])
+        );
+
+        end_with_output(vm, Bytes::new());
+    }
+
+    fn get_state_keys_entry(keys: &[&'static str]) -> GetStateKeysEntryMessage {
+        GetStateKeysEntryMessage {
+            result: Some(get_state_keys_entry_message::Result::Value(StateKeys {
+                keys: state_keys(keys),
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn completion_with_state_keys(entry_index: u32, keys: &[&'static str]) -> CompletionMessage {
+        CompletionMessage {
+            entry_index,
+            result: Some(completion_message::Result::Value(
+                StateKeys {
+                    keys: state_keys(keys),
+                }
+                .encode_to_vec()
+                .into(),
+            )),
+        }
+    }
+
+    #[test]
+    fn filter_eager_state() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(StartMessage {
+                state_map: KEYS
+                    .iter()
+                    .map(|k| StateEntry {
+                        key: Bytes::from_static(k.as_bytes()),
+                        value: Bytes::from_static(b"value"),
+                    })
+                    .collect(),
+                partial_state: false,
+                ..start_message(1)
+            })
+            .input(input_entry_message(b"my-data"))
+            .run(paginate_order_keys_handler);
+
+        // Only the pages are journaled
+        assert_eq!(
+            output.next_decoded::<GetStateKeysEntryMessage>().unwrap(),
+            get_state_keys_entry(&["order/1", "order/2"])
+        );
+        assert_eq!(
+            output.next_decoded::<GetStateKeysEntryMessage>().unwrap(),
+            get_state_keys_entry(&["order/3"])
+        );
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn filter_fetched_state_keys() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(1))
+            .input(input_entry_message(b"my-data"))
+            .input(completion_with_state_keys(1, &KEYS))
+            .run(paginate_order_keys_handler);
+
+        assert_eq!(
+            output.next_decoded::<GetStateKeysEntryMessage>().unwrap(),
+            GetStateKeysEntryMessage::default()
+        );
+        // The second page is answered from the fetched keys
+        assert_eq!(
+            output.next_decoded::<GetStateKeysEntryMessage>().unwrap(),
+            get_state_keys_entry(&["order/3"])
+        );
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn filter_replayed_state_keys() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(3))
+            .input(input_entry_message(b"my-data"))
+            .input(get_state_keys_entry(&KEYS))
+            .input(get_state_keys_entry(&KEYS))
+            .run(paginate_order_keys_handler);
+
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn replayed_pages_are_not_folded() {
+        let mut output = VMTestCase::with_version(VERSION)
+            .input(start_message(2))
+            .input(input_entry_message(b"my-data"))
+            .input(get_state_keys_entry(&["order/1", "order/2"]))
+            .input(completion_with_state_keys(2, &KEYS))
+            .run(paginate_order_keys_handler);
+
+        // The replayed page doesn't contain all the keys, so the second page needs a fetch
+        assert_eq!(
+            output.next_decoded::<GetStateKeysEntryMessage>().unwrap(),
+            GetStateKeysEntryMessage::default()
+        );
+        output.next_decoded::<OutputEntryMessage>().unwrap();
+        output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+}
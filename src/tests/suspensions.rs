@@ -1,20 +1,22 @@
 use super::*;
 
 use crate::service_protocol::messages::{
-    completion_message, AwakeableEntryMessage, CompletionMessage, EndMessage, GetStateEntryMessage,
-    SuspensionMessage,
+    completion_message, AwakeableEntryMessage, CallEntryMessage, CompletionMessage, EndMessage,
+    GetStateEntryMessage, OutputEntryMessage, SleepEntryMessage, SuspensionMessage,
 };
 use test_log::test;
 
 #[test]
 fn suspension_should_be_triggered_in_notify_input_closed() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .run_without_closing_input(|vm, _| {
             let _ = vm.sys_input().unwrap();
 
-            let handle = vm.sys_state_get("Personaggio".to_owned()).unwrap();
+            let handle = vm
+                .sys_state_get("Personaggio".to_owned(), EntryOptions::default())
+                .unwrap();
 
             // Also take_async_result returns Ok(None)
             assert_that!(vm.take_async_result(handle), ok(none()));
@@ -47,14 +49,14 @@ fn suspension_should_be_triggered_in_notify_input_closed() {
 
 #[test]
 fn suspension_should_be_triggered_with_correct_entry() {
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .run_without_closing_input(|vm, _| {
             vm.sys_input().unwrap();
 
-            let (_, _h1) = vm.sys_awakeable().unwrap();
-            let (_, h2) = vm.sys_awakeable().unwrap();
+            let (_, _h1) = vm.sys_awakeable(EntryOptions::default()).unwrap();
+            let (_, h2) = vm.sys_awakeable(EntryOptions::default()).unwrap();
 
             // Also take_async_result returns Ok(None)
             assert_that!(vm.take_async_result(h2), ok(none()));
@@ -89,14 +91,14 @@ fn suspension_should_be_triggered_with_correct_entry() {
 fn when_notify_completion_then_notify_await_point_then_notify_input_closed_then_no_suspension() {
     let completion = Bytes::from_static(b"completion");
 
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .run_without_closing_input(|vm, encoder| {
             vm.sys_input().unwrap();
 
-            let (_, _h1) = vm.sys_awakeable().unwrap();
-            let (_, h2) = vm.sys_awakeable().unwrap();
+            let (_, _h1) = vm.sys_awakeable(EntryOptions::default()).unwrap();
+            let (_, h2) = vm.sys_awakeable(EntryOptions::default()).unwrap();
 
             // Also take_async_result returns Ok(None)
             assert_that!(vm.take_async_result(h2), ok(none()));
@@ -117,8 +119,11 @@ fn when_notify_completion_then_notify_await_point_then_notify_input_closed_then_
                 ok(some(eq(Value::Success(completion.clone()))))
             );
 
-            vm.sys_write_output(NonEmptyValue::Success(completion.clone()))
-                .unwrap();
+            vm.sys_write_output(
+                NonEmptyValue::Success(completion.clone()),
+                EntryOptions::default(),
+            )
+            .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -145,14 +150,14 @@ fn when_notify_completion_then_notify_await_point_then_notify_input_closed_then_
 fn when_notify_await_point_then_notify_completion_then_notify_input_closed_then_no_suspension() {
     let completion = Bytes::from_static(b"completion");
 
-    let mut output = VMTestCase::new()
+    let mut output = VMTestCase::with_version(VERSION)
         .input(start_message(1))
         .input(input_entry_message(b"my-data"))
         .run_without_closing_input(|vm, encoder| {
             vm.sys_input().unwrap();
 
-            let (_, _h1) = vm.sys_awakeable().unwrap();
-            let (_, h2) = vm.sys_awakeable().unwrap();
+            let (_, _h1) = vm.sys_awakeable(EntryOptions::default()).unwrap();
+            let (_, h2) = vm.sys_awakeable(EntryOptions::default()).unwrap();
 
             // Also take_async_result returns Ok(None)
             assert_that!(vm.take_async_result(h2), ok(none()));
@@ -171,8 +176,11 @@ fn when_notify_await_point_then_notify_completion_then_notify_input_closed_then_
                 ok(some(eq(Value::Success(completion.clone()))))
             );
 
-            vm.sys_write_output(NonEmptyValue::Success(completion.clone()))
-                .unwrap();
+            vm.sys_write_output(
+                NonEmptyValue::Success(completion.clone()),
+                EntryOptions::default(),
+            )
+            .unwrap();
             vm.sys_end().unwrap();
         });
 
@@ -194,3 +202,142 @@ fn when_notify_await_point_then_notify_completion_then_notify_input_closed_then_
     );
     assert_eq!(output.next(), None);
 }
+
+mod request_response {
+    use super::*;
+
+    use test_log::test;
+
+    fn request_response_options() -> VMOptions {
+        VMOptions {
+            protocol_mode: ProtocolMode::RequestResponse,
+            ..VMOptions::default()
+        }
+    }
+
+    #[test]
+    fn wait_input_closed_before_executing() {
+        let mut vm = CoreVM::mock_init_with_options(VERSION, request_response_options());
+        let encoder = Encoder::new(VERSION);
+        assert_eq!(
+            vm.get_response_head().protocol_mode,
+            ProtocolMode::RequestResponse
+        );
+
+        vm.notify_input(encoder.encode(&start_message(1)));
+        vm.notify_input(encoder.encode(&input_entry_message(b"my-data")));
+
+        // All the known entries were received, but the input is not fully buffered yet
+        assert!(!vm.is_ready_to_execute().unwrap());
+
+        vm.notify_input_closed();
+        assert!(vm.is_ready_to_execute().unwrap());
+    }
+
+    fn vm_with_open_input() -> CoreVM {
+        let mut vm = CoreVM::mock_init_with_options(VERSION, request_response_options());
+        let encoder = Encoder::new(VERSION);
+        vm.notify_input(encoder.encode(&start_message(2)));
+        vm.notify_input(encoder.encode(&input_entry_message(b"my-data")));
+        vm.notify_input(encoder.encode(&SleepEntryMessage {
+            wake_up_time: 1721123699086,
+            ..Default::default()
+        }));
+        vm
+    }
+
+    #[test]
+    fn fail_syscalls_while_input_is_open() {
+        let mut vm = vm_with_open_input();
+
+        assert_that!(
+            vm.sys_input(),
+            err(eq_vm_error(vm::errors::INPUT_OPEN_IN_REQUEST_RESPONSE_MODE))
+        );
+    }
+
+    #[test]
+    fn fail_await_points_while_input_is_open() {
+        let mut vm = vm_with_open_input();
+
+        let h = AsyncResultHandle::from(1);
+        vm.notify_await_point(h);
+        assert_that!(
+            vm.take_async_result(h),
+            err(pat!(SuspendedOrVMError::VM(eq_vm_error(
+                vm::errors::INPUT_OPEN_IN_REQUEST_RESPONSE_MODE
+            ))))
+        );
+    }
+
+    #[test]
+    fn suspend_at_first_unresolved_await_point() {
+        let mut output =
+            VMTestCase::with_version_and_vm_options(VERSION, request_response_options())
+                .input(start_message(1))
+                .input(input_entry_message(b"my-data"))
+                .run(|vm| {
+                    vm.sys_input().unwrap();
+
+                    let h = vm
+                        .sys_call(
+                            Target {
+                                service: "MySvc".to_string(),
+                                handler: "MyHandler".to_string(),
+                                key: None,
+                                idempotency_key: None,
+                            },
+                            Bytes::new(),
+                            EntryOptions::default(),
+                        )
+                        .unwrap();
+
+                    // Suspends right away, the input is known to be closed already
+                    vm.notify_await_point(h);
+                    assert_that!(vm.take_async_result(h), err(is_suspended()));
+                });
+
+        let _ = output.next_decoded::<CallEntryMessage>().unwrap();
+        assert_that!(
+            output.next_decoded::<SuspensionMessage>().unwrap(),
+            suspended_with_index(1)
+        );
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn use_completions_in_request_body() {
+        let mut output =
+            VMTestCase::with_version_and_vm_options(VERSION, request_response_options())
+                .input(start_message(2))
+                .input(input_entry_message(b"my-data"))
+                .input(SleepEntryMessage {
+                    wake_up_time: 1721123699086,
+                    ..Default::default()
+                })
+                .input(CompletionMessage {
+                    entry_index: 1,
+                    result: Some(completion_message::Result::Empty(Default::default())),
+                })
+                .run(|vm| {
+                    vm.sys_input().unwrap();
+
+                    let h = vm
+                        .sys_sleep(Duration::from_secs(1), EntryOptions::default())
+                        .unwrap();
+                    vm.notify_await_point(h);
+                    assert_that!(vm.take_async_result(h), ok(some(eq(Value::Void))));
+
+                    vm.sys_write_output(
+                        NonEmptyValue::Success(Bytes::new()),
+                        EntryOptions::default(),
+                    )
+                    .unwrap();
+                    vm.sys_end().unwrap();
+                });
+
+        let _ = output.next_decoded::<OutputEntryMessage>().unwrap();
+        let _ = output.next_decoded::<EndMessage>().unwrap();
+        assert_eq!(output.next(), None);
+    }
+}
@@ -1,12 +1,18 @@
+use crate::random::DeterministicRng;
 use crate::service_protocol::messages::{
     completion_message, CompletionParsingHint, EntryMessage, RestateMessage,
     WriteableRestateMessage,
 };
-use crate::service_protocol::{Encoder, MessageType, Version};
-use crate::{AsyncResultHandle, AsyncResultState, EntryRetryInfo, Error, VMOptions, Value};
+use crate::service_protocol::{Encoder, MessageType, RawMessage, Version};
+use crate::snapshot::{AsyncResultsSnapshot, EagerStateSnapshot};
+use crate::vm::errors::{codes, EntryMismatchError, INPUT_OPEN_IN_REQUEST_RESPONSE_MODE};
+use crate::{
+    AsyncResultHandle, AsyncResultState, EntryRetryInfo, Error, ProtocolMode, StateChanges,
+    StateKeysQuery, VMOptions, Value,
+};
 use bytes::Bytes;
 use bytes_utils::SegmentedBuf;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::time::Duration;
 
 #[derive(Clone, Debug)]
@@ -36,7 +42,9 @@ impl Journal {
         self.index.map(|u| u as i64).unwrap_or(-1)
     }
 
+    /// Index of the current entry. Must be invoked only after a journal transition.
     pub(crate) fn expect_index(&self) -> u32 {
+        // Every caller runs after Journal::transition, during or after the input entry.
         self.index.expect("index was initialized")
     }
 }
@@ -87,21 +95,112 @@ enum UnparsedCompletionOrParsingHint {
     ParsingHint(CompletionParsingHint),
 }
 
+#[derive(Debug)]
+struct StateGetMany {
+    // Keys resolved from the eager state
+    values: BTreeMap<String, Option<Bytes>>,
+    // Keys and get state entries waiting for the runtime
+    entries: Vec<(String, u32)>,
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct AsyncResultsState {
     unparsed_completions_or_parsing_hints: HashMap<u32, UnparsedCompletionOrParsingHint>,
     ready_results: HashMap<u32, Value>,
     last_acked_entry: u32,
     waiting_ack_results: VecDeque<(u32, Value)>,
+    // Results of sys_state_get_many by handle. These handles aren't journal entries, they're allocated from u32::MAX downward
+    state_get_many: HashMap<u32, StateGetMany>,
+    state_get_many_handles: u32,
+    // Queries of sys_state_get_keys_with_prefix, by entry index
+    state_keys_queries: HashMap<u32, StateKeysQuery>,
 }
 
 impl AsyncResultsState {
     pub(crate) fn has_ready_result(&self, index: u32) -> bool {
-        self.ready_results.contains_key(&index)
+        match self.state_get_many.get(&index) {
+            Some(get_many) => get_many
+                .entries
+                .iter()
+                .all(|(_, idx)| self.ready_results.contains_key(idx)),
+            None => self.ready_results.contains_key(&index),
+        }
+    }
+
+    pub(crate) fn peek_ready_result(&self, index: u32) -> Option<&Value> {
+        self.ready_results.get(&index)
+    }
+
+    pub(crate) fn take_ready_result(&mut self, index: u32) -> Result<Option<Value>, Error> {
+        if !self.has_ready_result(index) {
+            return Ok(None);
+        }
+        if let Some(query) = self.state_keys_queries.remove(&index) {
+            return Ok(self.ready_results.remove(&index).map(|value| match value {
+                Value::StateKeys(keys) => Value::StateKeys(query.apply(keys)),
+                v => v,
+            }));
+        }
+        let Some(StateGetMany {
+            mut values,
+            entries,
+        }) = self.state_get_many.remove(&index)
+        else {
+            return Ok(self.ready_results.remove(&index));
+        };
+
+        let mut failure = None;
+        for (key, idx) in entries {
+            match self.ready_results.remove(&idx) {
+                Some(Value::Void) => {
+                    values.insert(key, None);
+                }
+                Some(Value::Success(v)) => {
+                    values.insert(key, Some(v));
+                }
+                Some(Value::Failure(f)) => {
+                    failure.get_or_insert(f);
+                }
+                v => {
+                    return Err(Error::internal(format!(
+                        "Unexpected result {v:?} of the get state entry {idx} for key '{key}'"
+                    )))
+                }
+            }
+        }
+        Ok(Some(match failure {
+            Some(f) => Value::Failure(f),
+            None => Value::StateValues(values),
+        }))
+    }
+
+    /// Entries to wait for before the result at `index` is ready.
+    pub(crate) fn awaited_entries(&self, index: u32) -> Vec<u32> {
+        match self.state_get_many.get(&index) {
+            Some(get_many) => get_many
+                .entries
+                .iter()
+                .map(|(_, idx)| *idx)
+                .filter(|idx| !self.ready_results.contains_key(idx))
+                .collect(),
+            None => vec![index],
+        }
     }
 
-    pub(crate) fn take_ready_result(&mut self, index: u32) -> Option<Value> {
-        self.ready_results.remove(&index)
+    pub(crate) fn insert_state_keys_query(&mut self, index: u32, query: StateKeysQuery) {
+        self.state_keys_queries.insert(index, query);
+    }
+
+    pub(crate) fn insert_state_get_many(
+        &mut self,
+        values: BTreeMap<String, Option<Bytes>>,
+        entries: Vec<(String, u32)>,
+    ) -> u32 {
+        let handle = u32::MAX - self.state_get_many_handles;
+        self.state_get_many_handles += 1;
+        self.state_get_many
+            .insert(handle, StateGetMany { values, entries });
+        handle
     }
 
     pub(crate) fn insert_completion_parsing_hint(
@@ -118,7 +217,10 @@ impl AsyncResultsState {
                         .insert(index, completion_parsing_hint.parse(result)?);
                 }
                 UnparsedCompletionOrParsingHint::ParsingHint(_) => {
-                    panic!("Unexpected double call to insert_completion_parsing_hint for entry {index}")
+                    return Err(Error::new(
+                        codes::INTERNAL,
+                        format!("Unexpected double call to insert_completion_parsing_hint for entry {index}"),
+                    ));
                 }
             }
         } else {
@@ -140,7 +242,10 @@ impl AsyncResultsState {
         {
             match unparsed_completion_or_parsing_hint {
                 UnparsedCompletionOrParsingHint::UnparsedCompletion(_) => {
-                    panic!("Unexpected double call to insert_unparsed_completion for entry {index}")
+                    return Err(Error::new(
+                        codes::PROTOCOL_VIOLATION,
+                        format!("Received a second completion for entry {index}"),
+                    ));
                 }
                 UnparsedCompletionOrParsingHint::ParsingHint(completion_parsing_hint) => {
                     self.ready_results
@@ -174,17 +279,49 @@ impl AsyncResultsState {
         }
         self.last_acked_entry = ack;
 
-        while let Some((idx, _)) = self.waiting_ack_results.front() {
-            if *idx > self.last_acked_entry {
+        while let Some((idx, value)) = self.waiting_ack_results.pop_front() {
+            if idx > self.last_acked_entry {
+                self.waiting_ack_results.push_front((idx, value));
                 return;
             }
-            let (idx, value) = self.waiting_ack_results.pop_front().unwrap();
             self.ready_results.insert(idx, value);
         }
     }
 
+    pub(crate) fn snapshot(&self) -> AsyncResultsSnapshot {
+        let mut waiting_completion = vec![];
+        let mut unparsed_completions = vec![];
+        for (idx, v) in &self.unparsed_completions_or_parsing_hints {
+            match v {
+                UnparsedCompletionOrParsingHint::UnparsedCompletion(_) => {
+                    unparsed_completions.push(*idx)
+                }
+                UnparsedCompletionOrParsingHint::ParsingHint(_) => waiting_completion.push(*idx),
+            }
+        }
+        waiting_completion.sort();
+        unparsed_completions.sort();
+
+        AsyncResultsSnapshot {
+            ready_results: self
+                .ready_results
+                .iter()
+                .map(|(idx, val)| (*idx, val.clone()))
+                .collect(),
+            waiting_completion,
+            unparsed_completions,
+            waiting_ack: self
+                .waiting_ack_results
+                .iter()
+                .map(|(idx, _)| *idx)
+                .collect(),
+            last_acked_entry: self.last_acked_entry,
+        }
+    }
+
     pub(crate) fn get_ready_results_state(&self) -> HashMap<AsyncResultHandle, AsyncResultState> {
-        self.ready_results
+        let mut states: HashMap<_, _> = self
+            .ready_results
             .iter()
             .map(|(idx, val)| {
                 (
@@ -193,13 +330,37 @@ impl AsyncResultsState {
                         Value::Void
                         | Value::Success(_)
                         | Value::StateKeys(_)
+                        | Value::StateValues(_)
                         | Value::InvocationId(_)
                         | Value::CombinatorResult(_) => AsyncResultState::Success,
                         Value::Failure(_) => AsyncResultState::Failure,
                     },
                 )
             })
-            .collect()
+            .collect();
+
+        // The handle of sys_state_get_many is ready when all its entries are
+        for (index, get_many) in &self.state_get_many {
+            let entry_states = get_many
+                .entries
+                .iter()
+                .map(|(_, idx)| states.get(&AsyncResultHandle(*idx)).copied())
+                .collect::<Option<Vec<_>>>();
+            match entry_states {
+                Some(entry_states) => {
+                    let state = if entry_states.contains(&AsyncResultState::Failure) {
+                        AsyncResultState::Failure
+                    } else {
+                        AsyncResultState::Success
+                    };
+                    states.insert(AsyncResultHandle(*index), state);
+                }
+                None => {
+                    states.remove(&AsyncResultHandle(*index));
+                }
+            }
+        }
+        states
     }
 }
 
@@ -222,7 +383,6 @@ pub(crate) enum EagerGetState {
     Value(Bytes),
 }
 
-#[allow(dead_code)]
 pub(crate) enum EagerGetStateKeys {
     /// Means we don't have sufficient information to establish whether state is there or not, so the VM should interact with the runtime to deal with it.
     Unknown,
@@ -233,6 +393,12 @@ pub(crate) struct EagerState {
     is_partial: bool,
     // None means Void, Value means value
     values: HashMap<String, Option<Bytes>>,
+    // Keys returned by get state keys, whose value is not known
+    unknown_values: HashSet<String>,
+    // Lazy fetches waiting for their result, by entry index. None means get state keys
+    pending_fetches: HashMap<u32, Option<String>>,
+    // Writes of the handler, including the replayed ones
+    changes: StateChanges,
 }
 
 impl Default for EagerState {
@@ -240,6 +406,9 @@ impl Default for EagerState {
         Self {
             is_partial: true,
             values: Default::default(),
+            unknown_values: Default::default(),
+            pending_fetches: Default::default(),
+            changes: Default::default(),
         }
     }
 }
@@ -252,6 +421,7 @@ impl EagerState {
                 .into_iter()
                 .map(|(key, val)| (key, Some(val)))
                 .collect(),
+            ..Default::default()
         }
     }
 
@@ -262,33 +432,105 @@ impl EagerState {
                 None => EagerGetState::Empty,
                 Some(s) => EagerGetState::Value(s.clone()),
             })
-            .unwrap_or(if self.is_partial {
+            .unwrap_or(if self.is_partial || self.unknown_values.contains(k) {
                 EagerGetState::Unknown
             } else {
                 EagerGetState::Empty
             })
     }
 
-    #[allow(dead_code)]
     pub(crate) fn get_keys(&self) -> EagerGetStateKeys {
         if self.is_partial {
             EagerGetStateKeys::Unknown
         } else {
-            EagerGetStateKeys::Keys(self.values.keys().cloned().collect())
+            EagerGetStateKeys::Keys(
+                self.values
+                    .iter()
+                    .filter(|(_, v)| v.is_some())
+                    .map(|(k, _)| k)
+                    .chain(&self.unknown_values)
+                    .cloned()
+                    .collect(),
+            )
         }
     }
 
     pub(crate) fn set(&mut self, k: String, v: Bytes) {
+        self.forget_pending_fetches(&k);
+        self.unknown_values.remove(&k);
+        self.changes.values.insert(k.clone(), Some(v.clone()));
         self.values.insert(k, Some(v));
     }
 
     pub(crate) fn clear(&mut self, k: String) {
+        self.forget_pending_fetches(&k);
+        self.unknown_values.remove(&k);
+        self.changes.values.insert(k.clone(), None);
         self.values.insert(k, None);
     }
 
     pub(crate) fn clear_all(&mut self) {
         self.values.clear();
+        self.unknown_values.clear();
+        self.pending_fetches.clear();
         self.is_partial = false;
+        self.changes = StateChanges {
+            cleared_all: true,
+            values: Default::default(),
+        };
+    }
+
+    pub(crate) fn changes(&self) -> &StateChanges {
+        &self.changes
+    }
+
+    /// Track the entry lazily fetching `key`, or the state keys if `None`, to fold its result back.
+    pub(crate) fn track_fetch(&mut self, index: u32, key: Option<String>) {
+        self.pending_fetches.insert(index, key);
+    }
+
+    /// Fold back the result of a tracked fetch. Results of fetches followed by a write to the same key are discarded, as they're stale.
+    pub(crate) fn fold_fetch_result(&mut self, index: u32, value: &Value) {
+        let Some(key) = self.pending_fetches.remove(&index) else {
+            return;
+        };
+        match (key, value) {
+            (Some(k), Value::Void) => {
+                self.unknown_values.remove(&k);
+                self.values.insert(k, None);
+            }
+            (Some(k), Value::Success(v)) => {
+                self.unknown_values.remove(&k);
+                self.values.insert(k, Some(v.clone()));
+            }
+            (None, Value::StateKeys(keys)) => {
+                // Keys written after the fetch are already in values, and are more recent
+                for k in keys {
+                    if !self.values.contains_key(k) {
+                        self.unknown_values.insert(k.clone());
+                    }
+                }
+                self.is_partial = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn forget_pending_fetches(&mut self, k: &str) {
+        self.pending_fetches
+            .retain(|_, key| key.as_deref() != Some(k));
+    }
+
+    pub(crate) fn snapshot(&self) -> EagerStateSnapshot {
+        EagerStateSnapshot {
+            is_partial: self.is_partial,
+            values: self
+                .values
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            unknown_values: self.unknown_values.iter().cloned().collect(),
+        }
     }
 }
 
@@ -302,9 +544,17 @@ pub(crate) struct Context {
     pub(crate) input_is_closed: bool,
     pub(crate) output: Output,
     pub(crate) eager_state: EagerState,
+    // Like start_info, the entries and async results outlive the states using them, to report them in the VM snapshot after a failure
+    pub(crate) replay_entries: VecDeque<RawMessage>,
+    pub(crate) replayed_entries: Vec<RawMessage>,
+    pub(crate) async_results: AsyncResultsState,
+    // Lazily initialized on the first sys_random
+    pub(crate) random: Option<DeterministicRng>,
 
     // Used by the error handler to set ErrorMessage.next_retry_delay
     pub(crate) next_retry_delay: Option<Duration>,
+    // Last journal mismatch, reported in the VM snapshot
+    pub(crate) journal_mismatch: Option<EntryMismatchError>,
 
     pub(crate) options: VMOptions,
 }
@@ -314,10 +564,21 @@ impl Context {
         self.start_info.as_ref()
     }
 
+    /// Must be invoked only after leaving the `WaitingStart` state.
     pub(crate) fn expect_start_info(&self) -> &StartInfo {
+        // start_info is set by the start message, the only way out of WaitingStart, and never unset.
         self.start_info().expect("state is not WaitingStart")
     }
 
+    /// In request/response mode, the handler can execute only once the whole input is buffered.
+    /// The input being closed, the first await point that cannot be resolved suspends.
+    pub(crate) fn check_input_is_buffered(&self) -> Result<(), Error> {
+        if self.options.protocol_mode == ProtocolMode::RequestResponse && !self.input_is_closed {
+            return Err(INPUT_OPEN_IN_REQUEST_RESPONSE_MODE);
+        }
+        Ok(())
+    }
+
     pub(crate) fn infer_entry_retry_info(&self) -> EntryRetryInfo {
         let start_info = self.expect_start_info();
         if self.journal.expect_index() == start_info.entries_to_replay {
@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use crate::manifest::ManifestError;
+use crate::service_protocol::messages::{EntryMessageHeaderEq, RestateMessage};
 use crate::service_protocol::{DecodingError, MessageType, UnsupportedVersionError};
 use crate::{Error, Version};
 use std::borrow::Cow;
@@ -130,6 +133,21 @@ pub const EMPTY_IDEMPOTENCY_KEY: Error = Error::new_const(
     "Trying to execute an idempotent request with an empty idempotency key, this is not supported",
 );
 
+pub const EMPTY_STATE_GET_MANY_KEYS: Error = Error::new_const(
+    codes::INTERNAL,
+    "Trying to get many state keys with an empty list of keys",
+);
+
+pub const DURATION_OUT_OF_RANGE: Error = Error::new_const(
+    codes::BAD_REQUEST,
+    "The duration in milliseconds since the Unix epoch doesn't fit in u64",
+);
+
+pub const INPUT_OPEN_IN_REQUEST_RESPONSE_MODE: Error = Error::new_const(
+    codes::INTERNAL,
+    "In request/response mode, the input must be closed before executing the handler",
+);
+
 // Other errors
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -160,22 +178,100 @@ impl UnexpectedStateError {
     }
 }
 
-#[derive(Debug, thiserror::Error)]
-#[error("Entry {actual:?} doesn't match expected entry {expected:?}")]
-pub struct EntryMismatchError<M: fmt::Debug> {
-    actual: M,
-    expected: M,
+/// Field of a journal entry which differs between the replayed entry and the entry produced by the handler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryFieldMismatch {
+    pub field: &'static str,
+    /// Value in the replayed entry. Long values are truncated, and suffixed with their length and hash.
+    pub actual: String,
+    /// Value in the entry produced by the handler. Long values are truncated, and suffixed with their length and hash.
+    pub expected: String,
 }
 
-impl<M: fmt::Debug> EntryMismatchError<M> {
-    pub fn new(actual: M, expected: M) -> EntryMismatchError<M> {
-        Self { actual, expected }
+const MAX_RENDERED_FIELD_LEN: usize = 64;
+
+impl EntryFieldMismatch {
+    pub(crate) fn new(
+        field: &'static str,
+        actual: &impl fmt::Debug,
+        expected: &impl fmt::Debug,
+    ) -> Self {
+        Self {
+            field,
+            actual: render_field(actual),
+            expected: render_field(expected),
+        }
     }
 }
 
-impl<M: fmt::Debug> WithInvocationErrorCode for EntryMismatchError<M> {
-    fn code(&self) -> InvocationErrorCode {
-        codes::JOURNAL_MISMATCH
+fn render_field(value: &impl fmt::Debug) -> String {
+    let rendered = format!("{value:?}");
+    if rendered.len() <= MAX_RENDERED_FIELD_LEN {
+        return rendered;
+    }
+
+    // FNV-1a, to get the same hash regardless of the platform and the Rust version
+    let hash = rendered.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    let mut truncate_at = MAX_RENDERED_FIELD_LEN / 2;
+    while !rendered.is_char_boundary(truncate_at) {
+        truncate_at -= 1;
+    }
+    format!(
+        "{}... (length {}, hash {hash:016x})",
+        &rendered[..truncate_at],
+        rendered.len()
+    )
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Entry {entry_index} of type {entry_type} doesn't match the expected entry, mismatching fields: [{}]", self.field_names())]
+pub struct EntryMismatchError {
+    pub entry_index: u32,
+    pub entry_type: String,
+    pub fields: Vec<EntryFieldMismatch>,
+}
+
+impl EntryMismatchError {
+    pub fn new<M: RestateMessage + EntryMessageHeaderEq>(
+        entry_index: u32,
+        actual: &M,
+        expected: &M,
+    ) -> EntryMismatchError {
+        Self {
+            entry_index,
+            entry_type: format!("{:?}", M::ty()),
+            fields: actual.header_diff(expected),
+        }
+    }
+
+    fn field_names(&self) -> String {
+        self.fields
+            .iter()
+            .map(|f| f.field)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn description(&self) -> String {
+        self.fields
+            .iter()
+            .map(|f| {
+                format!(
+                    "{}: replayed {}, but the handler produced {}",
+                    f.field, f.actual, f.expected
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl From<EntryMismatchError> for Error {
+    fn from(value: EntryMismatchError) -> Self {
+        Error::new(codes::JOURNAL_MISMATCH.0, value.to_string())
+            .with_description(value.description())
     }
 }
 
@@ -210,6 +306,31 @@ pub struct EmptyGetCallInvocationId;
 #[error("Cannot decode get call invocation id: {0}")]
 pub struct DecodeGetCallInvocationIdUtf8(#[from] pub(crate) std::string::FromUtf8Error);
 
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(
+    "The runtime asked to replay {known_entries} entries, exceeding the limit of {limit} entries"
+)]
+pub struct TooManyReplayEntriesError {
+    pub(crate) known_entries: u32,
+    pub(crate) limit: u32,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum InvalidStateWriteError {
+    #[error("State key of {length} bytes exceeds the maximum length of {limit} bytes")]
+    KeyTooLong { length: usize, limit: usize },
+    #[error(
+        "Value of state key '{key}' of {size} bytes exceeds the maximum size of {limit} bytes"
+    )]
+    ValueTooLarge {
+        key: String,
+        size: usize,
+        limit: usize,
+    },
+    #[error("State key '{key}' starts with the prefix '{prefix}', reserved for the SDK")]
+    ReservedPrefix { key: String, prefix: String },
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("Feature {feature} is not supported by the negotiated protocol version '{current_version}', the minimum required version is '{minimum_required_version}'")]
 pub struct UnsupportedFeatureForNegotiatedVersion {
@@ -259,6 +380,9 @@ impl WithInvocationErrorCode for DecodingError {
     fn code(&self) -> InvocationErrorCode {
         match self {
             DecodingError::UnexpectedMessageType { .. } => codes::JOURNAL_MISMATCH,
+            DecodingError::MessageTooLarge { .. } | DecodingError::BufferLimitExceeded { .. } => {
+                codes::PROTOCOL_VIOLATION
+            }
             _ => codes::INTERNAL,
         }
     }
@@ -270,6 +394,10 @@ impl_error_code!(BadEagerStateKeyError, INTERNAL);
 impl_error_code!(DecodeStateKeysProst, PROTOCOL_VIOLATION);
 impl_error_code!(DecodeStateKeysUtf8, PROTOCOL_VIOLATION);
 impl_error_code!(EmptyStateKeys, PROTOCOL_VIOLATION);
+impl_error_code!(TooManyReplayEntriesError, PROTOCOL_VIOLATION);
+impl_error_code!(InvalidStateWriteError, BAD_REQUEST);
 impl_error_code!(EmptyGetCallInvocationId, PROTOCOL_VIOLATION);
 impl_error_code!(DecodeGetCallInvocationIdUtf8, PROTOCOL_VIOLATION);
 impl_error_code!(UnsupportedFeatureForNegotiatedVersion, UNSUPPORTED_FEATURE);
+#[cfg(feature = "serde")]
+impl_error_code!(ManifestError, INTERNAL);
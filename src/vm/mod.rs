@@ -9,23 +9,27 @@ use crate::service_protocol::messages::{
     GetStateEntryMessage, GetStateKeysEntryMessage, OneWayCallEntryMessage, OutputEntryMessage,
     PeekPromiseEntryMessage, SetStateEntryMessage, SleepEntryMessage,
 };
-use crate::service_protocol::{Decoder, RawMessage, Version};
+use crate::service_protocol::{Decoder, Version};
+use crate::snapshot::{EntrySnapshot, VMSnapshot};
 use crate::vm::context::{EagerGetState, EagerGetStateKeys};
 use crate::vm::errors::{
-    UnexpectedStateError, UnsupportedFeatureForNegotiatedVersion, EMPTY_IDEMPOTENCY_KEY,
+    InvalidStateWriteError, UnexpectedStateError, UnsupportedFeatureForNegotiatedVersion,
+    DURATION_OUT_OF_RANGE, EMPTY_IDEMPOTENCY_KEY, EMPTY_STATE_GET_MANY_KEYS,
 };
 use crate::vm::transitions::*;
 use crate::{
-    AsyncResultCombinator, AsyncResultHandle, CancelInvocationTarget, Error, GetInvocationIdTarget,
-    Header, Input, NonEmptyValue, ResponseHead, RetryPolicy, RunEnterResult, RunExitResult,
-    SendHandle, SuspendedOrVMError, TakeOutputResult, Target, VMOptions, VMResult, Value,
+    AsyncResultCombinator, AsyncResultHandle, CancelInvocationTarget, DeterministicRng,
+    EntryOptions, Error, GetInvocationIdTarget, Header, Input, NonEmptyValue, ProtocolMode,
+    ResponseHead, RetryPolicy, RunEnterResult, RunExitOutcome, RunExitResult, SendHandle,
+    StateChanges, StateKeysQuery, SuspendedOrVMError, TakeOutputResult, Target, VMOptions,
+    VMResult, Value,
 };
 use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
 use base64::{alphabet, Engine};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use context::{AsyncResultsState, Context, Output, RunState};
+use context::{Context, Output, RunState};
 use std::borrow::Cow;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
 use std::mem::size_of;
 use std::time::Duration;
@@ -43,19 +47,13 @@ const CONTENT_TYPE: &str = "content-type";
 #[derive(Debug, IntoStaticStr)]
 pub(crate) enum State {
     WaitingStart,
-    WaitingReplayEntries {
-        entries: VecDeque<RawMessage>,
-        async_results: AsyncResultsState,
-    },
+    WaitingReplayEntries,
     Replaying {
         current_await_point: Option<u32>,
-        entries: VecDeque<RawMessage>,
-        async_results: AsyncResultsState,
     },
     Processing {
         run_state: RunState,
         current_await_point: Option<u32>,
-        async_results: AsyncResultsState,
     },
     Ended,
     Suspended,
@@ -106,6 +104,90 @@ impl CoreVM {
         }
         Ok(())
     }
+
+    /// Rejects the writes violating the limits of [`VMOptions`].
+    /// The invocation is left untouched, the SDK decides how to fail it.
+    fn verify_state_write(&self, key: &str, value: Option<&Bytes>) -> VMResult<()> {
+        let options = &self.context.options;
+        let error = if let Some(limit) = options.max_state_key_length.filter(|l| key.len() > *l) {
+            InvalidStateWriteError::KeyTooLong {
+                length: key.len(),
+                limit,
+            }
+        } else if let Some((size, limit)) = value
+            .map(Bytes::len)
+            .zip(options.max_state_value_size)
+            .filter(|(size, limit)| size > limit)
+        {
+            InvalidStateWriteError::ValueTooLarge {
+                key: key.to_owned(),
+                size,
+                limit,
+            }
+        } else if let Some(prefix) = options
+            .reserved_state_key_prefixes
+            .iter()
+            .find(|prefix| key.starts_with(prefix.as_str()))
+        {
+            InvalidStateWriteError::ReservedPrefix {
+                key: key.to_owned(),
+                prefix: prefix.clone(),
+            }
+        } else {
+            return Ok(());
+        };
+        Err(error.into())
+    }
+}
+
+impl CoreVM {
+    /// Returns the net state changes produced so far by the handler, including the changes replayed from the journal.
+    pub fn state_changes(&self) -> StateChanges {
+        self.context.eager_state.changes().clone()
+    }
+
+    /// Returns a read-only snapshot of the VM internals, useful to debug journal mismatches.
+    pub fn snapshot(&self) -> VMSnapshot {
+        let (state, error) = match &self.last_transition {
+            Ok(s) => (s.into(), None),
+            Err(e) => ("Errored", Some(e.clone())),
+        };
+        let is_executing = match &self.last_transition {
+            Ok(
+                State::WaitingReplayEntries | State::Replaying { .. } | State::Processing { .. },
+            ) => true,
+            Ok(_) => false,
+            Err(_) => self.context.start_info().is_some(),
+        };
+        let replayed_entries = self.context.replayed_entries.len() as u32;
+
+        VMSnapshot {
+            version: self.version,
+            invocation_id: self.context.start_info().map(|si| si.debug_id.clone()),
+            state,
+            error,
+            journal_index: self.context.journal.index(),
+            current_entry_type: format!("{:?}", self.context.journal.current_entry_ty),
+            current_entry_name: self.context.journal.current_entry_name.clone(),
+            replayed_entries: self
+                .context
+                .replayed_entries
+                .iter()
+                .enumerate()
+                .map(|(i, msg)| EntrySnapshot::from_raw_message(i as u32, msg))
+                .collect(),
+            entries_to_replay: self
+                .context
+                .replay_entries
+                .iter()
+                .enumerate()
+                .map(|(i, msg)| EntrySnapshot::from_raw_message(replayed_entries + i as u32, msg))
+                .collect(),
+            async_results: is_executing.then(|| self.context.async_results.snapshot()),
+            eager_state: self.context.eager_state.snapshot(),
+            journal_mismatch: self.context.journal_mismatch.clone(),
+        }
+    }
 }
 
 impl fmt::Debug for CoreVM {
@@ -146,23 +228,37 @@ impl super::VM for CoreVM {
             .ok_or(errors::MISSING_CONTENT_TYPE)?
             .parse::<Version>()?;
 
-        if version != Version::maximum_supported_version() {
+        if version < Version::minimum_supported_version()
+            || version > Version::maximum_supported_version()
+        {
             return Err(Error::new(
                 errors::codes::UNSUPPORTED_MEDIA_TYPE,
-                format!("Unsupported protocol version {:?}", version),
+                format!(
+                    "Unsupported protocol version {:?}, not within [{:?} to {:?}]",
+                    version,
+                    Version::minimum_supported_version(),
+                    Version::maximum_supported_version()
+                ),
             ));
         }
 
         Ok(Self {
             version,
-            decoder: Decoder::new(version),
+            decoder: Decoder::new(version)
+                .with_max_message_size(options.max_message_size)
+                .with_max_buffered_bytes(options.max_buffered_bytes),
             context: Context {
                 input_is_closed: false,
                 output: Output::new(version),
                 start_info: None,
                 journal: Default::default(),
                 eager_state: Default::default(),
+                replay_entries: Default::default(),
+                replayed_entries: Default::default(),
+                async_results: Default::default(),
+                random: None,
                 next_retry_delay: None,
+                journal_mismatch: None,
                 options,
             },
             last_transition: Ok(State::WaitingStart),
@@ -183,6 +279,7 @@ impl super::VM for CoreVM {
                 value: Cow::Borrowed(self.version.content_type()),
             }],
             version: self.version,
+            protocol_mode: self.context.options.protocol_mode,
         }
     }
 
@@ -193,6 +290,10 @@ impl super::VM for CoreVM {
         ret
     )]
     fn notify_input(&mut self, buffer: Bytes) {
+        if self.last_transition.is_err() {
+            // Don't keep buffering input we won't process
+            return;
+        }
         self.decoder.push(buffer);
         loop {
             match self.decoder.consume_next() {
@@ -272,8 +373,14 @@ impl super::VM for CoreVM {
     )]
     fn is_ready_to_execute(&self) -> Result<bool, Error> {
         match &self.last_transition {
-            Ok(State::WaitingStart) | Ok(State::WaitingReplayEntries { .. }) => Ok(false),
-            Ok(State::Processing { .. }) | Ok(State::Replaying { .. }) => Ok(true),
+            Ok(State::WaitingStart) | Ok(State::WaitingReplayEntries) => Ok(false),
+            Ok(State::Processing { .. }) | Ok(State::Replaying { .. }) => {
+                // In request/response mode the input must be fully buffered before executing
+                Ok(
+                    self.context.options.protocol_mode == ProtocolMode::BidiStream
+                        || self.context.input_is_closed,
+                )
+            }
             Ok(s) => Err(UnexpectedStateError::new(s.into(), "IsReadyToExecute").into()),
             Err(e) => Err(e.clone()),
         }
@@ -316,26 +423,52 @@ impl super::VM for CoreVM {
         self.do_transition(SysInput)
     }
 
+    #[instrument(
+        level = "debug",
+        skip(self),
+        fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version)
+    )]
+    fn sys_random(&mut self) -> Result<&mut DeterministicRng, Error> {
+        self.do_transition(SysRandom)?;
+        Ok(self
+            .context
+            .random
+            .as_mut()
+            .expect("random is initialized by SysRandom"))
+    }
+
     #[instrument(
         level = "debug",
         skip(self),
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_state_get(&mut self, key: String) -> Result<AsyncResultHandle, Error> {
+    fn sys_state_get(
+        &mut self,
+        key: String,
+        options: EntryOptions,
+    ) -> Result<AsyncResultHandle, Error> {
         let result = match self.context.eager_state.get(&key) {
             EagerGetState::Unknown => None,
             EagerGetState::Empty => Some(get_state_entry_message::Result::Empty(Empty::default())),
             EagerGetState::Value(v) => Some(get_state_entry_message::Result::Value(v)),
         };
-        self.do_transition(SysCompletableEntry(
+        let is_lazy = result.is_none();
+        let handle = self.do_transition(SysCompletableEntry(
             "SysStateGet",
             GetStateEntryMessage {
-                key: Bytes::from(key),
+                key: Bytes::from(key.clone()),
                 result,
-                ..Default::default()
+                name: options.name,
             },
-        ))
+        ))?;
+        if is_lazy {
+            self.do_transition(TrackStateFetch {
+                index: handle.0,
+                key: Some(key),
+            })?;
+        }
+        Ok(handle)
     }
 
     #[instrument(
@@ -344,7 +477,7 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_state_get_keys(&mut self) -> VMResult<AsyncResultHandle> {
+    fn sys_state_get_keys(&mut self, options: EntryOptions) -> VMResult<AsyncResultHandle> {
         let result = match self.context.eager_state.get_keys() {
             EagerGetStateKeys::Unknown => None,
             EagerGetStateKeys::Keys(keys) => {
@@ -353,13 +486,109 @@ impl super::VM for CoreVM {
                 }))
             }
         };
-        self.do_transition(SysCompletableEntry(
+        let is_lazy = result.is_none();
+        let handle = self.do_transition(SysCompletableEntry(
             "SysStateGetKeys",
             GetStateKeysEntryMessage {
                 result,
-                ..Default::default()
+                name: options.name,
             },
-        ))
+        ))?;
+        if is_lazy {
+            self.do_transition(TrackStateFetch {
+                index: handle.0,
+                key: None,
+            })?;
+        }
+        Ok(handle)
+    }
+
+    #[instrument(
+        level = "debug",
+        skip(self),
+        fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
+        ret
+    )]
+    fn sys_state_get_keys_with_prefix(
+        &mut self,
+        query: StateKeysQuery,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle> {
+        let handle = match self.context.eager_state.get_keys() {
+            EagerGetStateKeys::Keys(mut keys) => {
+                // All the keys are known, journal only the requested page
+                keys.sort();
+                self.do_transition(SysCompletableEntry(
+                    "SysStateGetKeys",
+                    GetStateKeysEntryMessage {
+                        result: Some(get_state_keys_entry_message::Result::Value(StateKeys {
+                            keys: query.apply(keys).into_iter().map(Bytes::from).collect(),
+                        })),
+                        name: options.name,
+                    },
+                ))?
+            }
+            EagerGetStateKeys::Unknown => {
+                let is_replaying = matches!(self.last_transition, Ok(State::Replaying { .. }));
+                let handle = self.do_transition(SysCompletableEntry(
+                    "SysStateGetKeys",
+                    GetStateKeysEntryMessage {
+                        result: None,
+                        name: options.name,
+                    },
+                ))?;
+                // A replayed entry might contain a page written by a previous attempt,
+                // only the keys fetched for a new entry are all the keys
+                if !is_replaying {
+                    self.do_transition(TrackStateFetch {
+                        index: handle.0,
+                        key: None,
+                    })?;
+                }
+                handle
+            }
+        };
+        // The query is applied to the replayed results too, it's a no-op on a page
+        self.do_transition(TrackStateKeysQuery(handle.0, query))?;
+        Ok(handle)
+    }
+
+    #[instrument(
+        level = "debug",
+        skip(self),
+        fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
+        ret
+    )]
+    fn sys_state_get_many(
+        &mut self,
+        keys: Vec<String>,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle> {
+        if keys.is_empty() {
+            return Err(EMPTY_STATE_GET_MANY_KEYS);
+        }
+
+        let mut seen = HashSet::with_capacity(keys.len());
+        let mut values = BTreeMap::new();
+        let mut entries = vec![];
+        for key in keys {
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+            match self.context.eager_state.get(&key) {
+                EagerGetState::Unknown => {
+                    let handle = self.sys_state_get(key.clone(), options.clone())?;
+                    entries.push((key, handle.0));
+                }
+                EagerGetState::Empty => {
+                    values.insert(key, None);
+                }
+                EagerGetState::Value(v) => {
+                    values.insert(key, Some(v));
+                }
+            }
+        }
+        self.do_transition(TrackStateGetMany { values, entries })
     }
 
     #[instrument(
@@ -368,16 +597,24 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_state_set(&mut self, key: String, value: Bytes) -> Result<(), Error> {
-        self.context.eager_state.set(key.clone(), value.clone());
+    fn sys_state_set(
+        &mut self,
+        key: String,
+        value: Bytes,
+        options: EntryOptions,
+    ) -> Result<(), Error> {
+        self.verify_state_write(&key, Some(&value))?;
         self.do_transition(SysNonCompletableEntry(
             "SysStateSet",
             SetStateEntryMessage {
-                key: Bytes::from(key.into_bytes()),
-                value,
-                ..SetStateEntryMessage::default()
+                key: Bytes::from(key.clone().into_bytes()),
+                value: value.clone(),
+                name: options.name,
             },
-        ))
+        ))?;
+        // Only journaled writes are visible to the eager state and the state changes
+        self.context.eager_state.set(key, value);
+        Ok(())
     }
 
     #[instrument(
@@ -386,15 +623,17 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_state_clear(&mut self, key: String) -> Result<(), Error> {
-        self.context.eager_state.clear(key.clone());
+    fn sys_state_clear(&mut self, key: String, options: EntryOptions) -> Result<(), Error> {
+        self.verify_state_write(&key, None)?;
         self.do_transition(SysNonCompletableEntry(
             "SysStateClear",
             ClearStateEntryMessage {
-                key: Bytes::from(key.into_bytes()),
-                ..ClearStateEntryMessage::default()
+                key: Bytes::from(key.clone().into_bytes()),
+                name: options.name,
             },
-        ))
+        ))?;
+        self.context.eager_state.clear(key);
+        Ok(())
     }
 
     #[instrument(
@@ -403,12 +642,13 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_state_clear_all(&mut self) -> Result<(), Error> {
-        self.context.eager_state.clear_all();
+    fn sys_state_clear_all(&mut self, options: EntryOptions) -> Result<(), Error> {
         self.do_transition(SysNonCompletableEntry(
             "SysStateClearAll",
-            ClearAllStateEntryMessage::default(),
-        ))
+            ClearAllStateEntryMessage { name: options.name },
+        ))?;
+        self.context.eager_state.clear_all();
+        Ok(())
     }
 
     #[instrument(
@@ -417,12 +657,16 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_sleep(&mut self, duration: Duration) -> VMResult<AsyncResultHandle> {
+    fn sys_sleep(
+        &mut self,
+        duration: Duration,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle> {
         self.do_transition(SysCompletableEntry(
             "SysSleep",
             SleepEntryMessage {
-                wake_up_time: u64::try_from(duration.as_millis())
-                    .expect("millis since Unix epoch should fit in u64"),
+                wake_up_time: duration_to_millis(duration)?,
+                name: options.name,
                 ..Default::default()
             },
         ))
@@ -434,7 +678,12 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_call(&mut self, target: Target, input: Bytes) -> VMResult<AsyncResultHandle> {
+    fn sys_call(
+        &mut self,
+        target: Target,
+        input: Bytes,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle> {
         if let Some(idempotency_key) = &target.idempotency_key {
             self.verify_feature_support("attach idempotency key to call", Version::V3)?;
             if idempotency_key.is_empty() {
@@ -453,6 +702,7 @@ impl super::VM for CoreVM {
                 key: target.key.unwrap_or_default(),
                 idempotency_key: target.idempotency_key,
                 parameter: input,
+                name: options.name,
                 ..Default::default()
             },
         ))
@@ -469,6 +719,7 @@ impl super::VM for CoreVM {
         target: Target,
         input: Bytes,
         delay: Option<Duration>,
+        options: EntryOptions,
     ) -> VMResult<SendHandle> {
         if let Some(idempotency_key) = &target.idempotency_key {
             self.verify_feature_support("attach idempotency key to one way call", Version::V3)?;
@@ -489,11 +740,10 @@ impl super::VM for CoreVM {
                 idempotency_key: target.idempotency_key,
                 parameter: input,
                 invoke_time: delay
-                    .map(|d| {
-                        u64::try_from(d.as_millis())
-                            .expect("millis since Unix epoch should fit in u64")
-                    })
+                    .map(duration_to_millis)
+                    .transpose()?
                     .unwrap_or_default(),
+                name: options.name,
                 ..Default::default()
             },
         ))
@@ -506,10 +756,13 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_awakeable(&mut self) -> VMResult<(String, AsyncResultHandle)> {
+    fn sys_awakeable(&mut self, options: EntryOptions) -> VMResult<(String, AsyncResultHandle)> {
         self.do_transition(SysCompletableEntry(
             "SysAwakeable",
-            AwakeableEntryMessage::default(),
+            AwakeableEntryMessage {
+                name: options.name,
+                ..Default::default()
+            },
         ))
         .map(|h| {
             (
@@ -528,7 +781,12 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_complete_awakeable(&mut self, id: String, value: NonEmptyValue) -> VMResult<()> {
+    fn sys_complete_awakeable(
+        &mut self,
+        id: String,
+        value: NonEmptyValue,
+        options: EntryOptions,
+    ) -> VMResult<()> {
         self.do_transition(SysNonCompletableEntry(
             "SysCompleteAwakeable",
             CompleteAwakeableEntryMessage {
@@ -539,7 +797,7 @@ impl super::VM for CoreVM {
                         complete_awakeable_entry_message::Result::Failure(f.into())
                     }
                 }),
-                ..Default::default()
+                name: options.name,
             },
         ))
     }
@@ -550,11 +808,16 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_get_promise(&mut self, key: String) -> VMResult<AsyncResultHandle> {
+    fn sys_get_promise(
+        &mut self,
+        key: String,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle> {
         self.do_transition(SysCompletableEntry(
             "SysGetPromise",
             GetPromiseEntryMessage {
                 key,
+                name: options.name,
                 ..Default::default()
             },
         ))
@@ -566,11 +829,16 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_peek_promise(&mut self, key: String) -> VMResult<AsyncResultHandle> {
+    fn sys_peek_promise(
+        &mut self,
+        key: String,
+        options: EntryOptions,
+    ) -> VMResult<AsyncResultHandle> {
         self.do_transition(SysCompletableEntry(
             "SysPeekPromise",
             PeekPromiseEntryMessage {
                 key,
+                name: options.name,
                 ..Default::default()
             },
         ))
@@ -586,6 +854,7 @@ impl super::VM for CoreVM {
         &mut self,
         key: String,
         value: NonEmptyValue,
+        options: EntryOptions,
     ) -> VMResult<AsyncResultHandle> {
         self.do_transition(SysCompletableEntry(
             "SysCompletePromise",
@@ -599,6 +868,7 @@ impl super::VM for CoreVM {
                         complete_promise_entry_message::Completion::CompletionFailure(f.into())
                     }
                 }),
+                name: options.name,
                 ..Default::default()
             },
         ))
@@ -624,14 +894,32 @@ impl super::VM for CoreVM {
         &mut self,
         value: RunExitResult,
         retry_policy: RetryPolicy,
-    ) -> Result<AsyncResultHandle, Error> {
-        self.do_transition(SysRunExit(value, retry_policy))
+    ) -> Result<RunExitOutcome, Error> {
+        let outcome = self.do_transition(SysRunExit(value, retry_policy))?;
+        if let RunExitOutcome::Retry {
+            error,
+            next_retry_delay,
+            ..
+        } = &outcome
+        {
+            // Fail this attempt, the runtime will retry after next_retry_delay.
+            // HitError always returns the error, propagate it only if the VM didn't fail the attempt with it
+            let res = self.do_transition::<_, ()>(HitError {
+                error: error.clone(),
+                next_retry_delay: *next_retry_delay,
+            });
+            if self.last_transition.is_ok() {
+                res?;
+            }
+        }
+        Ok(outcome)
     }
 
     #[instrument(level = "debug", ret)]
     fn sys_get_call_invocation_id(
         &mut self,
         call: GetInvocationIdTarget,
+        options: EntryOptions,
     ) -> VMResult<AsyncResultHandle> {
         self.verify_feature_support("get call invocation id", Version::V3)?;
         self.do_transition(SysCompletableEntry(
@@ -641,13 +929,18 @@ impl super::VM for CoreVM {
                     GetInvocationIdTarget::CallEntry(h) => h.0,
                     GetInvocationIdTarget::SendEntry(h) => h.0,
                 },
+                name: options.name,
                 ..Default::default()
             },
         ))
     }
 
     #[instrument(level = "debug", ret)]
-    fn sys_cancel_invocation(&mut self, target: CancelInvocationTarget) -> VMResult<()> {
+    fn sys_cancel_invocation(
+        &mut self,
+        target: CancelInvocationTarget,
+        options: EntryOptions,
+    ) -> VMResult<()> {
         self.verify_feature_support("cancel invocation", Version::V3)?;
         self.do_transition(SysNonCompletableEntry(
             "SysCancelInvocation",
@@ -663,7 +956,7 @@ impl super::VM for CoreVM {
                         cancel_invocation_entry_message::Target::CallEntryIndex(handle.0)
                     }
                 }),
-                ..Default::default()
+                name: options.name,
             },
         ))
     }
@@ -674,7 +967,11 @@ impl super::VM for CoreVM {
         fields(restate.invocation.id = self.debug_invocation_id(), restate.journal.index = self.context.journal.index(), restate.protocol.version = %self.version),
         ret
     )]
-    fn sys_write_output(&mut self, value: NonEmptyValue) -> Result<(), Error> {
+    fn sys_write_output(
+        &mut self,
+        value: NonEmptyValue,
+        options: EntryOptions,
+    ) -> Result<(), Error> {
         self.do_transition(SysNonCompletableEntry(
             "SysWriteOutput",
             OutputEntryMessage {
@@ -682,7 +979,7 @@ impl super::VM for CoreVM {
                     NonEmptyValue::Success(b) => output_entry_message::Result::Value(b),
                     NonEmptyValue::Failure(f) => output_entry_message::Result::Failure(f.into()),
                 }),
-                ..OutputEntryMessage::default()
+                name: options.name,
             },
         ))
     }
@@ -721,6 +1018,7 @@ impl super::VM for CoreVM {
         &mut self,
         combinator: impl AsyncResultCombinator + fmt::Debug,
     ) -> VMResult<Option<AsyncResultHandle>> {
+        self.verify_feature_support("combinators", Version::V3)?;
         self.do_transition(SysTryCompleteCombinator(combinator))
     }
 }
@@ -736,3 +1034,7 @@ fn awakeable_id(id: &[u8], entry_index: u32) -> String {
     input_buf.put_u32(entry_index);
     format!("prom_1{}", URL_SAFE.encode(input_buf.freeze()))
 }
+
+fn duration_to_millis(duration: Duration) -> VMResult<u64> {
+    u64::try_from(duration.as_millis()).map_err(|_| DURATION_OUT_OF_RANGE)
+}
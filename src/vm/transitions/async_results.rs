@@ -4,7 +4,9 @@ use crate::vm::errors::{
 };
 use crate::vm::transitions::{HitSuspensionPoint, Transition, TransitionAndReturn};
 use crate::vm::State;
-use crate::{Error, SuspendedError, Value};
+use crate::{AsyncResultHandle, Error, StateKeysQuery, SuspendedError, Value};
+use bytes::Bytes;
+use std::collections::BTreeMap;
 use tracing::warn;
 
 pub(crate) struct NotifyInputClosed;
@@ -14,17 +16,15 @@ impl Transition<Context, NotifyInputClosed> for State {
         match self {
             State::Replaying {
                 current_await_point: Some(await_point),
-                ref async_results,
-                ..
             }
             | State::Processing {
                 current_await_point: Some(await_point),
-                ref async_results,
                 ..
-            } if !async_results.has_ready_result(await_point) => {
-                self.transition(context, HitSuspensionPoint(await_point))
+            } if !context.async_results.has_ready_result(await_point) => {
+                let awaited_entries = context.async_results.awaited_entries(await_point);
+                self.transition(context, HitSuspensionPoint(awaited_entries))
             }
-            State::WaitingStart | State::WaitingReplayEntries { .. } => {
+            State::WaitingStart | State::WaitingReplayEntries => {
                 Err(INPUT_CLOSED_WHILE_WAITING_ENTRIES)
             }
             _ => Ok(self),
@@ -43,14 +43,12 @@ impl Transition<Context, NotifyAwaitPoint> for State {
         match self {
             State::Replaying {
                 ref mut current_await_point,
-                ref async_results,
-                ..
             }
             | State::Processing {
                 ref mut current_await_point,
-                ref async_results,
                 ..
             } => {
+                context.check_input_is_buffered()?;
                 if let Some(previous) = current_await_point {
                     if *previous != await_point {
                         if context.options.fail_on_wait_concurrent_async_result {
@@ -70,8 +68,9 @@ impl Transition<Context, NotifyAwaitPoint> for State {
                         }
                     }
                 }
-                if context.input_is_closed && !async_results.has_ready_result(await_point) {
-                    return self.transition(context, HitSuspensionPoint(await_point));
+                if context.input_is_closed && !context.async_results.has_ready_result(await_point) {
+                    let awaited_entries = context.async_results.awaited_entries(await_point);
+                    return self.transition(context, HitSuspensionPoint(awaited_entries));
                 };
 
                 *current_await_point = Some(await_point);
@@ -90,21 +89,18 @@ impl TransitionAndReturn<Context, TakeAsyncResult> for State {
 
     fn transition_and_return(
         mut self,
-        _: &mut Context,
+        context: &mut Context,
         TakeAsyncResult(async_result): TakeAsyncResult,
     ) -> Result<(Self, Self::Output), Error> {
         match self {
             State::Processing {
                 ref mut current_await_point,
-                ref mut async_results,
                 ..
             }
             | State::Replaying {
                 ref mut current_await_point,
-                ref mut async_results,
-                ..
             } => {
-                let opt = async_results.take_ready_result(async_result);
+                let opt = context.async_results.take_ready_result(async_result)?;
 
                 // Reset current await point if matches
                 if opt.is_some() && current_await_point.is_some_and(|i| i == async_result) {
@@ -118,3 +114,72 @@ impl TransitionAndReturn<Context, TakeAsyncResult> for State {
         }
     }
 }
+
+pub(crate) struct TrackStateFetch {
+    pub(crate) index: u32,
+    pub(crate) key: Option<String>,
+}
+
+impl Transition<Context, TrackStateFetch> for State {
+    fn transition(
+        self,
+        context: &mut Context,
+        TrackStateFetch { index, key }: TrackStateFetch,
+    ) -> Result<Self, Error> {
+        match self {
+            State::Replaying { .. } | State::Processing { .. } => {
+                context.eager_state.track_fetch(index, key);
+                // The result might be there already, e.g. when replaying a completed entry
+                if let Some(value) = context.async_results.peek_ready_result(index) {
+                    context.eager_state.fold_fetch_result(index, value);
+                }
+                Ok(self)
+            }
+            s => Err(UnexpectedStateError::new(s.into(), "TrackStateFetch").into()),
+        }
+    }
+}
+
+pub(crate) struct TrackStateGetMany {
+    pub(crate) values: BTreeMap<String, Option<Bytes>>,
+    pub(crate) entries: Vec<(String, u32)>,
+}
+
+impl TransitionAndReturn<Context, TrackStateGetMany> for State {
+    type Output = AsyncResultHandle;
+
+    fn transition_and_return(
+        self,
+        context: &mut Context,
+        TrackStateGetMany { values, entries }: TrackStateGetMany,
+    ) -> Result<(Self, Self::Output), Error> {
+        // Keys known in the eager state don't go through an entry, so check here like for any other syscall
+        self.check_side_effect_guard()?;
+        context.check_input_is_buffered()?;
+        match self {
+            State::Replaying { .. } | State::Processing { .. } => {
+                let handle = context.async_results.insert_state_get_many(values, entries);
+                Ok((self, AsyncResultHandle::from(handle)))
+            }
+            s => Err(UnexpectedStateError::new(s.into(), "TrackStateGetMany").into()),
+        }
+    }
+}
+
+pub(crate) struct TrackStateKeysQuery(pub(crate) u32, pub(crate) StateKeysQuery);
+
+impl Transition<Context, TrackStateKeysQuery> for State {
+    fn transition(
+        self,
+        context: &mut Context,
+        TrackStateKeysQuery(index, query): TrackStateKeysQuery,
+    ) -> Result<Self, Error> {
+        match self {
+            State::Replaying { .. } | State::Processing { .. } => {
+                context.async_results.insert_state_keys_query(index, query);
+                Ok(self)
+            }
+            s => Err(UnexpectedStateError::new(s.into(), "TrackStateKeysQuery").into()),
+        }
+    }
+}
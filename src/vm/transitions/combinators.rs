@@ -64,20 +64,18 @@ where
     type Output = Option<AsyncResultHandle>;
 
     fn transition_and_return(
-        mut self,
+        self,
         context: &mut Context,
         SysTryCompleteCombinator(combinator): SysTryCompleteCombinator<C>,
     ) -> Result<(Self, Self::Output), Error> {
         self.check_side_effect_guard()?;
+        context.check_input_is_buffered()?;
         match self {
-            State::Processing {
-                ref mut async_results,
-                ..
-            } => {
+            State::Processing { .. } => {
                 // Try complete the combinator
                 let mut async_result_tracker =
                     AsyncResultAccessTracker(AsyncResultAccessTrackerInner::Processing {
-                        known_results: async_results.get_ready_results_state(),
+                        known_results: context.async_results.get_ready_results_state(),
                         tracked_access_to_completed_results: vec![],
                         tracked_access_to_uncompleted_results: vec![],
                     });
@@ -107,7 +105,7 @@ where
                     let current_journal_index = context.journal.expect_index();
 
                     // Cache locally the Combinator result, the user will be able to access this once the ack is received.
-                    async_results.insert_waiting_ack_result(
+                    context.async_results.insert_waiting_ack_result(
                         current_journal_index,
                         Value::CombinatorResult(combinator_result),
                     );
@@ -132,7 +130,9 @@ where
                         context.output.send(&SuspensionMessage {
                             entry_indexes: uncompleted_entries_order
                                 .into_iter()
-                                .map(Into::into)
+                                .flat_map(|handle| {
+                                    context.async_results.awaited_entries(handle.into())
+                                })
                                 .collect(),
                         });
                         context.output.send_eof();
@@ -151,21 +151,14 @@ where
                 let current_journal_index = context.journal.expect_index();
 
                 // We should get the combinator message now
-                let (mut s, msg) = s.transition_and_return(
+                let (s, msg) = s.transition_and_return(
                     context,
                     PopJournalEntry("SysTryCompleteCombinator", expected),
                 )?;
 
                 match s {
-                    State::Replaying {
-                        ref mut async_results,
-                        ..
-                    }
-                    | State::Processing {
-                        ref mut async_results,
-                        ..
-                    } => {
-                        let ar_states = async_results.get_ready_results_state();
+                    State::Replaying { .. } | State::Processing { .. } => {
+                        let ar_states = context.async_results.get_ready_results_state();
 
                         // Compute the replay_combinators
                         let mut replay_combinators =
@@ -187,7 +180,7 @@ where
                             .ok_or(BAD_COMBINATOR_ENTRY)?;
 
                         // Store the ready result
-                        async_results.insert_ready_result(
+                        context.async_results.insert_ready_result(
                             current_journal_index,
                             Value::CombinatorResult(combinator_result),
                         );
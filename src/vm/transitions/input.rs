@@ -1,7 +1,10 @@
 use crate::service_protocol::messages::{CompletionMessage, EntryAckMessage, StartMessage};
 use crate::service_protocol::{MessageType, RawMessage};
 use crate::vm::context::{Context, EagerState, StartInfo};
-use crate::vm::errors::{BadEagerStateKeyError, KNOWN_ENTRIES_IS_ZERO, UNEXPECTED_INPUT_MESSAGE};
+use crate::vm::errors::{
+    BadEagerStateKeyError, TooManyReplayEntriesError, KNOWN_ENTRIES_IS_ZERO,
+    UNEXPECTED_INPUT_MESSAGE,
+};
 use crate::vm::transitions::Transition;
 use crate::vm::{errors, State};
 use crate::Error;
@@ -64,11 +67,17 @@ impl Transition<Context, NewStartMessage> for State {
         if msg.known_entries == 0 {
             return Err(KNOWN_ENTRIES_IS_ZERO);
         }
+        if let Some(limit) = context.options.max_replay_entries {
+            if msg.known_entries > limit {
+                return Err(TooManyReplayEntriesError {
+                    known_entries: msg.known_entries,
+                    limit,
+                }
+                .into());
+            }
+        }
 
-        Ok(State::WaitingReplayEntries {
-            entries: Default::default(),
-            async_results: Default::default(),
-        })
+        Ok(State::WaitingReplayEntries)
     }
 }
 
@@ -76,8 +85,8 @@ struct NewCompletionMessage(CompletionMessage);
 
 impl Transition<Context, NewCompletionMessage> for State {
     fn transition(
-        mut self,
-        _: &mut Context,
+        self,
+        context: &mut Context,
         NewCompletionMessage(msg): NewCompletionMessage,
     ) -> Result<Self, Error> {
         // Add completion to completions buffer
@@ -85,23 +94,15 @@ impl Transition<Context, NewCompletionMessage> for State {
             entry_index,
             result,
         } = msg;
-        match &mut self {
-            State::WaitingReplayEntries {
-                ref mut async_results,
-                ..
-            }
-            | State::Replaying {
-                ref mut async_results,
-                ..
-            }
-            | State::Processing {
-                ref mut async_results,
-                ..
-            } => {
-                async_results.insert_unparsed_completion(
+        match &self {
+            State::WaitingReplayEntries | State::Replaying { .. } | State::Processing { .. } => {
+                context.async_results.insert_unparsed_completion(
                     entry_index,
                     result.ok_or(errors::EXPECTED_COMPLETION_RESULT)?,
                 )?;
+                if let Some(value) = context.async_results.peek_ready_result(entry_index) {
+                    context.eager_state.fold_fetch_result(entry_index, value);
+                }
             }
             State::Ended | State::Suspended => {
                 // Can ignore
@@ -117,24 +118,13 @@ struct NewEntryAckMessage(EntryAckMessage);
 
 impl Transition<Context, NewEntryAckMessage> for State {
     fn transition(
-        mut self,
-        _: &mut Context,
+        self,
+        context: &mut Context,
         NewEntryAckMessage(msg): NewEntryAckMessage,
     ) -> Result<Self, Error> {
         match self {
-            State::WaitingReplayEntries {
-                ref mut async_results,
-                ..
-            }
-            | State::Replaying {
-                ref mut async_results,
-                ..
-            }
-            | State::Processing {
-                ref mut async_results,
-                ..
-            } => {
-                async_results.notify_ack(msg.entry_index);
+            State::WaitingReplayEntries | State::Replaying { .. } | State::Processing { .. } => {
+                context.async_results.notify_ack(msg.entry_index);
             }
             State::Ended | State::Suspended => {
                 // Can ignore
@@ -154,23 +144,17 @@ impl Transition<Context, NewEntryMessage> for State {
         NewEntryMessage(msg): NewEntryMessage,
     ) -> Result<Self, Error> {
         match self {
-            State::WaitingReplayEntries {
-                mut entries,
-                async_results,
-            } => {
-                entries.push_back(msg);
+            State::WaitingReplayEntries => {
+                context.replay_entries.push_back(msg);
 
-                if context.expect_start_info().entries_to_replay == entries.len() as u32 {
+                if context.expect_start_info().entries_to_replay
+                    == context.replay_entries.len() as u32
+                {
                     Ok(State::Replaying {
                         current_await_point: None,
-                        entries,
-                        async_results,
                     })
                 } else {
-                    Ok(State::WaitingReplayEntries {
-                        entries,
-                        async_results,
-                    })
+                    Ok(State::WaitingReplayEntries)
                 }
             }
             _ => Err(errors::UNEXPECTED_ENTRY_MESSAGE),
@@ -1,3 +1,4 @@
+use crate::random::{compute_random_seed, DeterministicRng};
 use crate::retries::NextRetry;
 use crate::service_protocol::messages;
 use crate::service_protocol::messages::{
@@ -13,9 +14,9 @@ use crate::vm::transitions::{Transition, TransitionAndReturn};
 use crate::vm::State;
 use crate::{
     AsyncResultHandle, Error, Header, Input, NonEmptyValue, RetryPolicy, RunEnterResult,
-    RunExitResult, TerminalFailure,
+    RunExitOutcome, RunExitResult, TerminalFailure,
 };
-use std::{fmt, mem};
+use std::mem;
 
 impl State {
     pub(crate) fn check_side_effect_guard(&self) -> Result<(), Error> {
@@ -42,30 +43,29 @@ impl<M: RestateMessage + EntryMessageHeaderEq + EntryMessage + Clone>
     ) -> Result<(Self, Self::Output), Error> {
         match self {
             State::Replaying {
-                mut entries,
                 current_await_point,
-                mut async_results,
             } => {
-                let actual = entries
+                let raw = context
+                    .replay_entries
                     .pop_front()
-                    .ok_or(UnavailableEntryError::new(M::ty()))?
-                    .decode_to::<M>()?;
-                let new_state = if entries.is_empty() {
-                    async_results.notify_ack(context.journal.expect_index());
+                    .ok_or(UnavailableEntryError::new(M::ty()))?;
+                context.replayed_entries.push(raw.clone());
+                let actual = raw.decode_to::<M>()?;
+                let new_state = if context.replay_entries.is_empty() {
+                    context
+                        .async_results
+                        .notify_ack(context.journal.expect_index());
                     State::Processing {
                         run_state: RunState::NotRunning,
                         current_await_point,
-                        async_results,
                     }
                 } else {
                     State::Replaying {
                         current_await_point,
-                        entries,
-                        async_results,
                     }
                 };
 
-                check_entry_header_match(&actual, &expected)?;
+                check_entry_header_match(context, &actual, &expected)?;
 
                 Ok((new_state, actual))
             }
@@ -108,6 +108,7 @@ impl TransitionAndReturn<Context, SysInput> for State {
     ) -> Result<(Self, Self::Output), Error> {
         context.journal.transition(&InputEntryMessage::default());
         self.check_side_effect_guard()?;
+        context.check_input_is_buffered()?;
         let (s, msg) = TransitionAndReturn::transition_and_return(
             self,
             context,
@@ -135,23 +136,24 @@ impl TransitionAndReturn<Context, SysInput> for State {
     }
 }
 
-#[cfg(feature = "sha2_random_seed")]
-fn compute_random_seed(id: &[u8]) -> u64 {
-    use bytes::Buf;
-    use sha2::{Digest, Sha256};
+pub(crate) struct SysRandom;
 
-    let id_hash = Sha256::digest(id);
-    let mut b = id_hash.as_slice();
-    b.get_u64()
-}
-
-#[cfg(not(feature = "sha2_random_seed"))]
-fn compute_random_seed(id: &[u8]) -> u64 {
-    use std::hash::{DefaultHasher, Hash, Hasher};
-
-    let mut hasher = DefaultHasher::new();
-    id.hash(&mut hasher);
-    hasher.finish()
+impl Transition<Context, SysRandom> for State {
+    fn transition(self, context: &mut Context, _: SysRandom) -> Result<Self, Error> {
+        match self {
+            State::Replaying { .. } | State::Processing { .. } => {
+                self.check_side_effect_guard()?;
+                context.check_input_is_buffered()?;
+                if context.random.is_none() {
+                    context.random = Some(DeterministicRng::new(compute_random_seed(
+                        &context.expect_start_info().id,
+                    )));
+                }
+                Ok(self)
+            }
+            s => Err(UnexpectedStateError::new(s.into(), "SysRandom").into()),
+        }
+    }
 }
 
 pub(crate) struct SysNonCompletableEntry<M>(pub(crate) &'static str, pub(crate) M);
@@ -166,6 +168,7 @@ impl<M: RestateMessage + EntryMessageHeaderEq + EntryMessage + Clone + Writeable
     ) -> Result<Self, Error> {
         context.journal.transition(&expected);
         self.check_side_effect_guard()?;
+        context.check_input_is_buffered()?;
         let (s, _) =
             self.transition_and_return(context, PopOrWriteJournalEntry(sys_name, expected))?;
         Ok(s)
@@ -192,7 +195,8 @@ impl<
     ) -> Result<(Self, Self::Output), Error> {
         context.journal.transition(&expected);
         self.check_side_effect_guard()?;
-        let (mut s, actual) = TransitionAndReturn::transition_and_return(
+        context.check_input_is_buffered()?;
+        let (s, actual) = TransitionAndReturn::transition_and_return(
             self,
             context,
             PopOrWriteJournalEntry(sys_name, expected),
@@ -200,18 +204,11 @@ impl<
 
         let ar_handle = AsyncResultHandle(context.journal.expect_index());
         match s {
-            State::Replaying {
-                ref mut async_results,
-                ..
-            }
-            | State::Processing {
-                ref mut async_results,
-                ..
-            } => {
+            State::Replaying { .. } | State::Processing { .. } => {
                 if let Some(c) = actual.into_completion()? {
-                    async_results.insert_ready_result(ar_handle.0, c);
+                    context.async_results.insert_ready_result(ar_handle.0, c);
                 } else {
-                    async_results.insert_completion_parsing_hint(
+                    context.async_results.insert_completion_parsing_hint(
                         ar_handle.0,
                         M::completion_parsing_hint(),
                     )?;
@@ -239,6 +236,7 @@ impl TransitionAndReturn<Context, SysRunEnter> for State {
         };
         context.journal.transition(&expected);
         self.check_side_effect_guard()?;
+        context.check_input_is_buffered()?;
         match self {
             State::Processing {
                 ref mut run_state, ..
@@ -265,7 +263,7 @@ impl TransitionAndReturn<Context, SysRunEnter> for State {
 pub(crate) struct SysRunExit(pub(crate) RunExitResult, pub(crate) RetryPolicy);
 
 impl TransitionAndReturn<Context, SysRunExit> for State {
-    type Output = AsyncResultHandle;
+    type Output = RunExitOutcome;
 
     fn transition_and_return(
         mut self,
@@ -274,9 +272,7 @@ impl TransitionAndReturn<Context, SysRunExit> for State {
     ) -> Result<(Self, Self::Output), Error> {
         match self {
             State::Processing {
-                ref mut async_results,
-                ref mut run_state,
-                ..
+                ref mut run_state, ..
             } => {
                 let name = match mem::replace(run_state, RunState::NotRunning) {
                     RunState::Running(n) => n,
@@ -286,35 +282,62 @@ impl TransitionAndReturn<Context, SysRunExit> for State {
                 };
                 let current_journal_index = context.journal.expect_index();
 
-                let value = match run_exit_result {
-                    RunExitResult::Success(s) => NonEmptyValue::Success(s),
-                    RunExitResult::TerminalFailure(f) => NonEmptyValue::Failure(f),
+                let handle = AsyncResultHandle(current_journal_index);
+                let (value, outcome) = match run_exit_result {
+                    RunExitResult::Success(s) => {
+                        (NonEmptyValue::Success(s), RunExitOutcome::Recorded(handle))
+                    }
+                    RunExitResult::TerminalFailure(f) => {
+                        (NonEmptyValue::Failure(f), RunExitOutcome::Recorded(handle))
+                    }
                     RunExitResult::RetryableFailure {
                         error: failure,
                         attempt_duration,
                     } => {
                         let mut retry_info = context.infer_entry_retry_info();
-                        retry_info.retry_count += 1;
-                        retry_info.retry_loop_duration += attempt_duration;
-
-                        match retry_policy.next_retry(retry_info) {
-                            NextRetry::Retry(next_retry_interval) => {
-                                // We need to retry!
-                                context.next_retry_delay = next_retry_interval;
-                                return Err(Error::new(failure.code, failure.message));
-                            }
-                            NextRetry::DoNotRetry => {
-                                // We don't retry, but convert the retryable error to actual error
-                                NonEmptyValue::Failure(TerminalFailure {
-                                    code: failure.code,
-                                    message: failure.message.to_string(),
-                                })
+                        retry_info.retry_count = retry_info.retry_count.saturating_add(1);
+                        retry_info.retry_loop_duration = retry_info
+                            .retry_loop_duration
+                            .saturating_add(attempt_duration);
+
+                        // Convert the retryable error to actual error
+                        let terminal_failure = NonEmptyValue::Failure(TerminalFailure {
+                            code: failure.code,
+                            message: failure.message.to_string(),
+                        });
+
+                        if retry_policy.is_terminal(&failure) {
+                            (
+                                terminal_failure,
+                                RunExitOutcome::ClassifiedAsTerminal { handle, retry_info },
+                            )
+                        } else {
+                            // Seed the jitter with the entry index too, so different run entries retry at different times
+                            let jitter_seed = compute_random_seed(&context.expect_start_info().id)
+                                ^ u64::from(current_journal_index);
+                            match retry_policy.next_retry(retry_info.clone(), jitter_seed) {
+                                NextRetry::Retry(next_retry_delay) => {
+                                    // We need to retry! CoreVM will fail the attempt
+                                    return Ok((
+                                        self,
+                                        RunExitOutcome::Retry {
+                                            error: failure,
+                                            next_retry_delay,
+                                            retry_info,
+                                        },
+                                    ));
+                                }
+                                NextRetry::DoNotRetry => (
+                                    terminal_failure,
+                                    RunExitOutcome::GaveUp { handle, retry_info },
+                                ),
                             }
                         }
                     }
                 };
 
-                async_results
+                context
+                    .async_results
                     .insert_waiting_ack_result(current_journal_index, value.clone().into());
 
                 let expected = RunEntryMessage {
@@ -326,19 +349,23 @@ impl TransitionAndReturn<Context, SysRunExit> for State {
                 };
                 context.output.send(&expected);
 
-                Ok((self, AsyncResultHandle(current_journal_index)))
+                Ok((self, outcome))
             }
             s => Err(UnexpectedStateError::new(s.into(), "SysRunExit").into()),
         }
     }
 }
 
-fn check_entry_header_match<M: EntryMessageHeaderEq + Clone + fmt::Debug>(
+fn check_entry_header_match<M: RestateMessage + EntryMessageHeaderEq>(
+    context: &mut Context,
     actual: &M,
     expected: &M,
 ) -> Result<(), Error> {
     if !actual.header_eq(expected) {
-        return Err(EntryMismatchError::new(actual.clone(), expected.clone()).into());
+        let mismatch = EntryMismatchError::new(context.journal.expect_index(), actual, expected);
+        // Keep it around for VM snapshots
+        context.journal_mismatch = Some(mismatch.clone());
+        return Err(mismatch.into());
     }
 
     Ok(())
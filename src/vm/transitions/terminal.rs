@@ -27,21 +27,20 @@ impl Transition<Context, HitError> for State {
     }
 }
 
-pub(crate) struct HitSuspensionPoint(pub(crate) u32);
+/// Suspends waiting for any of the given entries.
+pub(crate) struct HitSuspensionPoint(pub(crate) Vec<u32>);
 
 impl Transition<Context, HitSuspensionPoint> for State {
     fn transition(
         self,
         context: &mut Context,
-        HitSuspensionPoint(await_point): HitSuspensionPoint,
+        HitSuspensionPoint(entry_indexes): HitSuspensionPoint,
     ) -> Result<Self, Error> {
         if matches!(self, State::Suspended | State::Ended) {
             // Nothing to do
             return Ok(self);
         }
-        context.output.send(&SuspensionMessage {
-            entry_indexes: vec![await_point],
-        });
+        context.output.send(&SuspensionMessage { entry_indexes });
         context.output.send_eof();
 
         Ok(State::Suspended)
@@ -3,13 +3,59 @@
 
 use std::{path::PathBuf, process::Command};
 
+// Bytes fields are encoded as base64 strings in JSON
+const BYTES_FIELDS: &[&str] = &[
+    ".dev.restate.service.protocol.StartMessage.id",
+    ".dev.restate.service.protocol.StartMessage.StateEntry.key",
+    ".dev.restate.service.protocol.StartMessage.StateEntry.value",
+    ".dev.restate.service.protocol.CompletionMessage.result.value",
+    ".dev.restate.service.protocol.InputEntryMessage.value",
+    ".dev.restate.service.protocol.OutputEntryMessage.result.value",
+    ".dev.restate.service.protocol.GetStateEntryMessage.key",
+    ".dev.restate.service.protocol.GetStateEntryMessage.result.value",
+    ".dev.restate.service.protocol.SetStateEntryMessage.key",
+    ".dev.restate.service.protocol.SetStateEntryMessage.value",
+    ".dev.restate.service.protocol.ClearStateEntryMessage.key",
+    ".dev.restate.service.protocol.GetPromiseEntryMessage.result.value",
+    ".dev.restate.service.protocol.PeekPromiseEntryMessage.result.value",
+    ".dev.restate.service.protocol.CompletePromiseEntryMessage.completion.completion_value",
+    ".dev.restate.service.protocol.CallEntryMessage.parameter",
+    ".dev.restate.service.protocol.CallEntryMessage.result.value",
+    ".dev.restate.service.protocol.OneWayCallEntryMessage.parameter",
+    ".dev.restate.service.protocol.AwakeableEntryMessage.result.value",
+    ".dev.restate.service.protocol.CompleteAwakeableEntryMessage.result.value",
+    ".dev.restate.service.protocol.RunEntryMessage.result.value",
+];
+const REPEATED_BYTES_FIELDS: &[&str] =
+    &[".dev.restate.service.protocol.GetStateKeysEntryMessage.StateKeys.keys"];
+
 #[test]
 fn bootstrap() {
     let root_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     let out_dir = root_dir.join("src/service_protocol/generated");
 
-    if let Err(error) = prost_build::Config::new()
+    let mut config = prost_build::Config::new();
+    config
         .bytes(["."])
+        .type_attribute(
+            ".",
+            "#[cfg_attr(feature = \"serde\", derive(::serde::Serialize, ::serde::Deserialize))]",
+        )
+        .message_attribute(".", "#[cfg_attr(feature = \"serde\", serde(default))]");
+    for bytes_field in BYTES_FIELDS {
+        config.field_attribute(
+            bytes_field,
+            "#[cfg_attr(feature = \"serde\", serde(with = \"crate::service_protocol::serde_base64\"))]",
+        );
+    }
+    for repeated_bytes_field in REPEATED_BYTES_FIELDS {
+        config.field_attribute(
+            repeated_bytes_field,
+            "#[cfg_attr(feature = \"serde\", serde(with = \"crate::service_protocol::serde_base64::repeated\"))]",
+        );
+    }
+
+    if let Err(error) = config
         .protoc_arg("--experimental_allow_proto3_optional")
         .out_dir(out_dir.clone())
         .compile_protos(